@@ -1,6 +1,13 @@
 // build.rs
 fn main() {
+    // Use the vendored protoc unless one is given through PROTOC
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc =
+            protoc_bin_vendored::protoc_bin_path().expect("Failed to find vendored protoc");
+        // SAFETY: the build script is single threaded, nothing else reads the environment yet
+        unsafe { std::env::set_var("PROTOC", protoc) };
+    }
     tonic_build::configure()
-    .compile_protos(&["src/proto/communication.proto"], &["src/proto"])
-    .expect("Failed to compile proto files");
+        .compile_protos(&["src/proto/communication.proto"], &["src/proto"])
+        .expect("Failed to compile proto files");
 }
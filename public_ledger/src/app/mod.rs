@@ -3,10 +3,18 @@ use crate::auction;
 use crate::blockchain;
 use crate::blockchain::block::Block;
 use crate::blockchain::chain::Chain;
+use crate::identity::Identity;
 use crate::kademlia;
+use crate::kademlia::communication::SubscribeRequest;
+use crate::kademlia::content::block_key;
 use crate::kademlia::find_value_dht;
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+use crate::kademlia::record::UserRecord;
+use crate::kademlia::shutdown::Shutdown;
 use crate::kademlia::store_value_dht;
-use crate::kademlia::string_to_hash_key;
+use crate::kademlia::{find_content_dht, find_record_dht, store_record_dht};
+use crate::pubsub;
 use crate::routing_table::{self, RoutingTable};
 use eframe::{App, Frame, egui};
 use screens::auction_screen::AuctionScreenEvent;
@@ -17,15 +25,27 @@ use screens::menu_screen::MenuScreen;
 use screens::menu_screen::MenuScreenEvent;
 
 use crate::auction::Auction;
-
+use crate::auction::account::{FaucetTransaction, MAX_FAUCET_GRANT, TransferTransaction};
+use crate::auction::attachment::{Attachment, AttachmentError, AttachmentManifest, chunk_key};
+use crate::auction::index::{AuctionQuery, PostingsList};
+use crate::auction::money::{Currency, Money};
+use crate::auction::private::{AuctionKey, PrivateAuction, PrivateBid};
+use crate::auction::record::LedgerMode;
+use crate::auction::reputation::FeedbackTransaction;
+use crate::auction::settlement::SettlementTransaction;
+use crate::auction::signature::{AuctionSignature, BidSignature};
+use crate::auction::state::AuctionState;
+
+use std::collections::HashMap;
 use std::result;
-use std::str::from_utf8;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::sync::RwLock;
 use tokio::sync::oneshot;
 
+pub(crate) mod proxy_agent;
 mod screens;
+use proxy_agent::ProxyAgents;
 use screens::AppState;
 use screens::auction_screen::AuctionScreen;
 use screens::create_screen::CreateScreen;
@@ -33,6 +53,18 @@ use screens::initial_screen::{InitialScreen, InitialScreenEvent};
 use screens::join_screen::JoinScreenEvent;
 use screens::selection_screen::{SelectionScreen, SelectionScreenEvent};
 
+// How many of the k closest nodes must store a bid before it is committed on the chain
+const BID_WRITE_QUORUM: usize = 2;
+// How many of the k closest nodes must store a mined block, and the pointer to it, before it
+// is published
+const BLOCK_WRITE_QUORUM: usize = 2;
+// How many times a transaction is mined again when another miner's block takes the tip first
+const MAX_MINE_ATTEMPTS: usize = 3;
+// Name of the pointer every miner keeps to the tip of its chain
+const LATEST_BLOCK: &str = "latest_block";
+// Block events arriving within this long of each other are handled with a single refresh
+const EVENT_BATCH_WINDOW: std::time::Duration = std::time::Duration::from_secs(2);
+
 pub struct AuctionApp {
     pub(crate) state: AppState,
     routing_table: Option<Arc<RwLock<routing_table::RoutingTable>>>,
@@ -45,15 +77,41 @@ pub struct AuctionApp {
     block_screen: BlockScreen,
     bid_screen: BidScreen,
     result_string: Arc<Mutex<String>>,
-    latest_auction: Arc<Mutex<Auction>>,
     auction_list: Arc<Mutex<Vec<Auction>>>,
     blockchain: Arc<Mutex<blockchain::chain::Chain>>,
-    latest_bid: Arc<Mutex<auction::bid::Bid>>,
     bid_list: Arc<Mutex<Vec<auction::bid::Bid>>>,
+    // Key pair signing our auctions, bids and seller actions
+    identity: Identity,
+    proxy_agents: ProxyAgents,
+    // What is refreshed on new blocks
+    followed: Arc<Mutex<Followed>>,
+    attachments: FetchedAttachments,
+    // Outcome of the latest audit and key disclosure, shown once they complete
+    audit_report: PendingStatus,
+    bid_status: PendingStatus,
+    // Keys of the private auctions we created, by auction ID, disclosed without asking the DHT
+    auction_keys: HashMap<String, AuctionKey>,
+    // Stops our node's server and background maintenance when the app closes
+    shutdown: Shutdown,
+}
+
+// Attachments fetched from the DHT, by content ID
+type FetchedAttachments = Arc<Mutex<HashMap<String, Result<Vec<u8>, AttachmentError>>>>;
+
+// Message left by a background task for a screen, taken on the next frame
+type PendingStatus = Arc<Mutex<Option<String>>>;
+
+// What the screens show and should be kept up to date
+#[derive(Default)]
+struct Followed {
+    // Auction open in the bid screen
+    auction_id: Option<String>,
+    // Search shown in the auction screen, empty to list every auction
+    query: AuctionQuery,
 }
 
 impl AuctionApp {
-    pub fn new() -> Self {
+    pub fn new(proxy_agents: ProxyAgents, identity: Identity) -> Self {
         Self {
             state: AppState::Initial,
             initial_screen: InitialScreen::default(),
@@ -66,14 +124,30 @@ impl AuctionApp {
             block_screen: BlockScreen::default(),
             bid_screen: BidScreen::default(),
             result_string: Arc::new(Mutex::new("".to_string())),
-            latest_auction: Arc::new(Mutex::new(Auction::default())),
             auction_list: Arc::new(Mutex::new(Vec::new())),
             blockchain: Arc::new(Mutex::new(blockchain::chain::Chain::new())),
-            latest_bid: Arc::new(Mutex::new(auction::bid::Bid::default())),
             bid_list: Arc::new(Mutex::new(Vec::new())),
+            identity,
+            proxy_agents,
+            followed: Arc::new(Mutex::new(Followed::default())),
+            attachments: Arc::new(Mutex::new(HashMap::new())),
+            audit_report: Arc::new(Mutex::new(None)),
+            bid_status: Arc::new(Mutex::new(None)),
+            auction_keys: HashMap::new(),
+            shutdown: Shutdown::new(),
         }
     }
 
+    // Changes the auction whose bids are kept up to date, dropping the bids of the previous one
+    fn follow_auction(&self, auction_id: Option<String>) {
+        let followed = self.followed.clone();
+        let bid_list = self.bid_list.clone();
+        tokio::spawn(async move {
+            followed.lock().await.auction_id = auction_id;
+            bid_list.lock().await.clear();
+        });
+    }
+
     pub fn update_menu_screen_info(&mut self) {
         if let Some(rt) = &self.routing_table {
             if let Ok(rt_read) = rt.try_read() {
@@ -104,6 +178,7 @@ impl App for AuctionApp {
                                 if let Some(routing_table) = self.routing_table.clone() {
                                     let routing_table_clone = routing_table.clone();
                                     let addr_clone = addr.clone();
+                                    let shutdown = self.shutdown.clone();
                                     tokio::spawn({
                                         let addr_clone = addr_clone.clone();
                                         async move {
@@ -111,6 +186,7 @@ impl App for AuctionApp {
                                                 routing_table_clone,
                                                 addr_clone.clone(),
                                                 port,
+                                                shutdown,
                                             )
                                             .await
                                             {
@@ -119,6 +195,17 @@ impl App for AuctionApp {
                                         }
                                     });
                                     println!("Kademlia server started on {}:{}", addr, port);
+
+                                    // Refresh the screens whenever our node hears of a new block
+                                    tokio::spawn(follow_chain_events(
+                                        ctx.clone(),
+                                        routing_table.clone(),
+                                        self.blockchain.clone(),
+                                        self.auction_list.clone(),
+                                        self.bid_list.clone(),
+                                        self.followed.clone(),
+                                        self.identity.clone(),
+                                    ));
                                     self.state = AppState::Selection;
                                 } else {
                                     println!("Routing table is not initialized");
@@ -137,10 +224,19 @@ impl App for AuctionApp {
                             SelectionScreenEvent::Create => {
                                 let blockchain = self.blockchain.clone();
                                 let routing_table = self.routing_table.clone().unwrap();
+                                // Networks created from the app are test networks, the faucet
+                                // stays open and its creator starts with a grant
+                                let allocation = FaucetTransaction::test_network(
+                                    vec![self.identity.get_public_key()],
+                                    Money::new(MAX_FAUCET_GRANT, Currency::Eur),
+                                )
+                                .serialized_to_bytes()
+                                .unwrap();
 
                                 tokio::spawn(async move {
                                     // Add Genesis Block
-                                    let genesis_block = blockchain::block::Block::genesis();
+                                    let genesis_block =
+                                        blockchain::block::Block::genesis(allocation);
 
                                     {
                                         let mut blockchain = blockchain.lock().await;
@@ -149,10 +245,8 @@ impl App for AuctionApp {
 
                                     let clone_genesis_block = genesis_block.clone();
 
-                                    // Store Genesis Block under its truncated hash
-                                    let truncated_hash = &genesis_block.get_hash()[0..20];
-                                    let block_dht_key =
-                                        kademlia::string_to_hash_key(&hex::encode(truncated_hash));
+                                    // Store Genesis Block under its hash key
+                                    let block_dht_key = block_key(&genesis_block.get_hash());
 
                                     let genesis_serialized =
                                         genesis_block.serialized().as_bytes().to_vec();
@@ -160,24 +254,23 @@ impl App for AuctionApp {
                                     let routing_table_clone = routing_table.clone();
                                     store_value_dht(
                                         &routing_table_clone,
+                                        Namespace::Block,
                                         block_dht_key,
                                         genesis_serialized,
                                     )
                                     .await;
                                     println!(
                                         "Genesis block stored under key {:?}",
-                                        hex::encode(truncated_hash)
+                                        hex::encode(block_dht_key)
                                     );
 
-                                    // Store Latest Block pointer
-                                    let latest_block_key =
-                                        kademlia::string_to_hash_key("latest_block");
-
+                                    // Store our Latest Block pointer, the genesis block is the first version
                                     let blockchain = blockchain.lock().await;
 
-                                    store_value_dht(
+                                    store_record_dht(
                                         &routing_table,
-                                        latest_block_key,
+                                        LATEST_BLOCK,
+                                        1,
                                         clone_genesis_block.serialized().as_bytes().to_vec(),
                                     )
                                     .await;
@@ -222,12 +315,13 @@ impl App for AuctionApp {
                             MenuScreenEvent::SubmittedStore { key, value } => {
                                 let routing_table = self.routing_table.clone().unwrap();
                                 let routing_table_clone = routing_table.clone();
-                                let hash = kademlia::string_to_hash_key(&key.clone());
+                                let hash = Namespace::UserData.key(&key);
                                 tokio::spawn(async move {
                                     store_value_dht(
                                         &routing_table_clone,
+                                        Namespace::UserData,
                                         hash,
-                                        value.as_bytes().to_vec(),
+                                        UserRecord::new(&key, &value).serialized_to_bytes().unwrap(),
                                     )
                                     .await;
                                 });
@@ -241,17 +335,18 @@ impl App for AuctionApp {
                                         Arc::clone(&result_string); // Clone Arc for async task
 
                                     async move {
-                                        let hash = kademlia::string_to_hash_key(&key);
-                                        let value = find_value_dht(&routing_table, hash).await;
+                                        let hash = Namespace::UserData.key(&key);
+                                        let value =
+                                            find_value_dht(&routing_table, Namespace::UserData, hash)
+                                                .await;
                                         let mut result_string = result_string.lock().await; // Lock to update the result string
 
-                                        match value {
-                                            Some(bytes) => {
-                                                *result_string = format!(
-                                                    "{}",
-                                                    from_utf8(&bytes).unwrap_or("Invalid UTF-8")
-                                                );
-                                                println!("Value found: {:?}", from_utf8(&bytes)); // Debugging
+                                        match value.and_then(|bytes| {
+                                            UserRecord::deserialized_from_bytes(&bytes).ok()
+                                        }) {
+                                            Some(record) => {
+                                                *result_string = record.value.clone();
+                                                println!("Value found: {:?}", record.value); // Debugging
                                             }
                                             None => {
                                                 *result_string = "Value not found".to_string();
@@ -280,8 +375,27 @@ impl App for AuctionApp {
                     let auction_list = self.auction_list.try_lock().unwrap(); // Lock to read the auction list
                     self.auction_screen.refresh_auctions(auction_list.clone());
 
-                    let chain = self.blockchain.try_lock().unwrap().clone();
-                    self.auction_screen.set_chain(chain);
+                    // The chain is locked while a fetch replaces it, keep the last copy until then
+                    if let Ok(chain) = self.blockchain.try_lock() {
+                        self.auction_screen.set_chain(chain.clone());
+                    }
+                    self.auction_screen
+                        .set_user_id(self.identity.get_public_key());
+
+                    // Hand over the attachments fetched since the last frame
+                    if let Ok(attachments) = self.attachments.try_lock() {
+                        for (content_id, attachment) in attachments.iter() {
+                            if !self.auction_screen.has_attachment(content_id) {
+                                self.auction_screen
+                                    .set_attachment(content_id.clone(), attachment.clone());
+                            }
+                        }
+                    }
+                    if let Ok(mut audit_report) = self.audit_report.try_lock()
+                        && let Some(audit_report) = audit_report.take()
+                    {
+                        self.auction_screen.set_audit_report(audit_report);
+                    }
 
                     if let Some(event) = self.auction_screen.ui(ui) {
                         match event {
@@ -292,82 +406,149 @@ impl App for AuctionApp {
                                 self.state = AppState::Menu;
                             }
                             AuctionScreenEvent::GetAuctions => {
-                                // Get latest auction id
+                                // Auctions are enumerated from the commitments recorded on the chain
                                 let routing_table = self.routing_table.clone().unwrap();
-                                let latest_auction = self.latest_auction.clone();
-                                tokio::spawn({
-                                    let routing_table = routing_table.clone(); // Clone for async task
-                                    let latest_auction = latest_auction.clone(); // Clone for async task
+                                let blockchain = self.blockchain.clone();
+                                let auction_list = self.auction_list.clone();
+                                let followed = self.followed.clone();
+                                let identity = self.identity.clone();
+                                tokio::spawn(async move {
+                                    followed.lock().await.query = AuctionQuery::default();
+                                    let Some(fetched_chain) =
+                                        fetch_full_chain(&routing_table, &blockchain).await
+                                    else {
+                                        println!("Failed to fetch chain");
+                                        return;
+                                    };
+                                    println!("Chain fetched successfully");
 
-                                    async move {
-                                        let hash = kademlia::string_to_hash_key("last_auction"); // Predefined key
-                                        let value = find_value_dht(&routing_table, hash).await;
-                                        let mut latest_auction = latest_auction.lock().await; // Lock to update the result string
-
-                                        match value {
-                                            Some(bytes) => {
-                                                *latest_auction = Auction::deserialized(
-                                                    from_utf8(&bytes).unwrap(),
-                                                );
-                                                println!("Auction found: {:?}", latest_auction); // Debugging
-                                            }
-                                            None => {
-                                                println!("Auction not found"); // Debugging
-                                            }
-                                        }
-                                    }
-                                });
+                                    let auctions =
+                                        fetch_auctions(&routing_table, &fetched_chain, &identity)
+                                            .await;
 
-                                // Get all auctions and verify
+                                    *blockchain.lock().await = fetched_chain;
+                                    let mut auction_list = auction_list.lock().await; // Lock to update the result string
+                                    *auction_list = auctions;
+                                });
+                            }
+                            AuctionScreenEvent::Search { query } => {
+                                // Only the postings lists of the search are fetched
                                 let routing_table = self.routing_table.clone().unwrap();
+                                let blockchain = self.blockchain.clone();
                                 let auction_list = self.auction_list.clone();
-                                tokio::spawn({
-                                    let routing_table = routing_table.clone(); // Clone for async task
-                                    let auction_list = auction_list.clone(); // Clone for async task
+                                let followed = self.followed.clone();
+                                tokio::spawn(async move {
+                                    followed.lock().await.query = query.clone();
+                                    let Some(fetched_chain) =
+                                        fetch_full_chain(&routing_table, &blockchain).await
+                                    else {
+                                        println!("Failed to fetch chain");
+                                        return;
+                                    };
 
-                                    async move {
-                                        let mut auctions = Vec::new();
-                                        for i in 0..=latest_auction.lock().await.id {
-                                            let hash = kademlia::string_to_hash_key(
-                                                &("auction:".to_string() + &i.to_string()),
-                                            );
-                                            let value = find_value_dht(&routing_table, hash).await;
-                                            match value {
-                                                Some(bytes) => {
-                                                    let auction = Auction::deserialized(
-                                                        from_utf8(&bytes).unwrap(),
-                                                    );
-                                                    auctions.push(auction);
-                                                }
-                                                None => {
-                                                    println!("Auction not found"); // Debugging
-                                                }
-                                            }
-                                        }
-                                        let mut auction_list = auction_list.lock().await; // Lock to update the result string
-                                        *auction_list = auctions;
-                                    }
-                                });
+                                    let auctions =
+                                        search_auctions(&routing_table, &fetched_chain, &query)
+                                            .await;
 
-                                // Get Chain
+                                    *blockchain.lock().await = fetched_chain;
+                                    *auction_list.lock().await = auctions;
+                                });
+                            }
+                            AuctionScreenEvent::LoadAttachment { manifest } => {
                                 let routing_table = self.routing_table.clone().unwrap();
-                                let blockchain = self.blockchain.clone();
-                                let routing_table_clone = routing_table.clone();
+                                let attachments = self.attachments.clone();
+                                let ctx = ui.ctx().clone();
                                 tokio::spawn(async move {
-                                    if let Some(fetched_chain) =
-                                        fetch_full_chain(&routing_table_clone).await
+                                    let attachment =
+                                        fetch_attachment(&routing_table, &manifest).await;
+                                    attachments
+                                        .lock()
+                                        .await
+                                        .insert(manifest.content_id(), attachment);
+                                    ctx.request_repaint();
+                                });
+                            }
+                            AuctionScreenEvent::SaveAttachment { manifest } => {
+                                // Only the file name is kept, never a path chosen by the seller
+                                let file_name = std::path::Path::new(&manifest.file_name)
+                                    .file_name()
+                                    .map_or("attachment".to_string(), |name| {
+                                        name.to_string_lossy().to_string()
+                                    });
+                                let path = format!("attachment_{}", file_name);
+                                // A fetch may be storing another attachment at the same time
+                                let status = match self.attachments.try_lock() {
+                                    Ok(attachments) => match attachments.get(&manifest.content_id())
                                     {
-                                        let mut blockchain = blockchain.lock().await;
-                                        *blockchain = fetched_chain;
-                                        println!("Chain fetched successfully");
-                                    } else {
-                                        println!("Failed to fetch chain");
-                                    }
+                                        Some(Ok(bytes)) => match std::fs::write(&path, bytes) {
+                                            Ok(()) => format!("Attachment saved to {}", path),
+                                            Err(e) => format!("Failed to save attachment: {}", e),
+                                        },
+                                        _ => "Attachment not loaded.".to_string(),
+                                    },
+                                    Err(_) => "Attachments are busy, try again.".to_string(),
+                                };
+                                self.auction_screen.set_status(status);
+                            }
+                            AuctionScreenEvent::Faucet { amount } => {
+                                let transaction = FaucetTransaction::new(
+                                    vec![self.identity.get_public_key()],
+                                    amount,
+                                )
+                                .serialized_to_bytes()
+                                .unwrap();
+                                let routing_table = self.routing_table.clone().unwrap();
+                                let blockchain = self.blockchain.clone();
+                                tokio::spawn(async move {
+                                    mine_transaction(&routing_table, &blockchain, transaction)
+                                        .await;
+                                });
+                            }
+                            AuctionScreenEvent::Transfer { to, amount } => {
+                                // Signed with our key, the chain only accepts it if we can afford it
+                                let transaction =
+                                    TransferTransaction::new(&self.identity, to, amount)
+                                        .serialized_to_bytes()
+                                        .unwrap();
+                                let routing_table = self.routing_table.clone().unwrap();
+                                let blockchain = self.blockchain.clone();
+                                tokio::spawn(async move {
+                                    mine_transaction(&routing_table, &blockchain, transaction)
+                                        .await;
+                                });
+                            }
+                            AuctionScreenEvent::Audit {
+                                auction_id,
+                                auction_key,
+                            } => {
+                                let routing_table = self.routing_table.clone().unwrap();
+                                let Ok(chain) = self.blockchain.try_lock().map(|chain| chain.clone())
+                                else {
+                                    self.auction_screen
+                                        .set_audit_report("Chain is busy, try again.".to_string());
+                                    return;
+                                };
+                                let audit_report = self.audit_report.clone();
+                                let ctx = ui.ctx().clone();
+                                self.auction_screen
+                                    .set_audit_report("Auditing...".to_string());
+                                tokio::spawn(async move {
+                                    let report = audit_private_auction(
+                                        &routing_table,
+                                        &chain,
+                                        &auction_id,
+                                        &auction_key,
+                                    )
+                                    .await;
+                                    *audit_report.lock().await = Some(report);
+                                    ctx.request_repaint();
                                 });
                             }
                             AuctionScreenEvent::BidMenu { auction } => {
                                 // Set the auction in the bid screen
                                 self.bid_screen.set_auction(auction.clone());
+                                self.bid_screen.set_user_id(self.identity.get_public_key());
+                                self.follow_auction(Some(auction.id.clone()));
                                 self.state = AppState::Bid;
                             }
                         }
@@ -379,158 +560,91 @@ impl App for AuctionApp {
                             screens::create_screen::CreateScreenEvent::Back => {
                                 self.state = AppState::Auction;
                             }
-                            screens::create_screen::CreateScreenEvent::Submitted(
+                            screens::create_screen::CreateScreenEvent::Submitted {
                                 item_name,
+                                description,
+                                category,
+                                attachments,
+                                invitees,
+                                lots,
                                 starting_price,
+                                start_delay_hours,
                                 duration_hours,
-                            ) => {
-                                // Get Last Auction ID
-                                let routing_table = self.routing_table.clone().unwrap();
-                                let latest_auction = self.latest_auction.clone();
-                                tokio::spawn({
-                                    let routing_table = routing_table.clone(); // Clone for async task
-                                    let latest_auction = latest_auction.clone(); // Clone for async task
-
-                                    async move {
-                                        let hash = kademlia::string_to_hash_key("last_auction"); // Predefined key
-                                        let value = find_value_dht(&routing_table, hash).await;
-                                        let mut latest_auction = latest_auction.lock().await; // Lock to update the result string
-
-                                        match value {
-                                            Some(bytes) => {
-                                                *latest_auction = Auction::deserialized(
-                                                    from_utf8(&bytes).unwrap(),
-                                                );
-                                                println!("Auction found: {:?}", latest_auction); // Debugging
-                                            }
-                                            None => {
-                                                println!("Auction not found"); // Debugging
-                                            }
-                                        }
-                                    }
-                                });
-
-                                // Increment Auction ID
-                                let latest_auction = self.latest_auction.clone();
-                                let auction_id = tokio::task::block_in_place(|| {
-                                    let rt = tokio::runtime::Handle::current();
-                                    rt.block_on(async {
-                                        let mut latest_auction = latest_auction.lock().await; // Lock to update the result string
-                                        latest_auction.id.clone() + 1
-                                    })
-                                });
-
-                                // Create Auction
+                            } => {
+                                // Create Auction, its ID is derived from our public key and a fresh
+                                // nonce, the key also lets us sign changes to it later
                                 let auction = Auction::new_with_duration(
-                                    auction_id,
+                                    self.identity.get_public_key(),
                                     item_name,
                                     starting_price,
+                                    start_delay_hours,
                                     duration_hours,
-                                );
-
-                                let auction_hash = auction.get_hash();
+                                )
+                                .with_description(description)
+                                .with_category(category)
+                                .with_attachments(
+                                    attachments
+                                        .iter()
+                                        .map(|attachment| attachment.manifest.clone())
+                                        .collect(),
+                                )
+                                .with_invitees(invitees)
+                                .with_lots(lots);
+
+                                // Private auctions are stored encrypted to their invitees
+                                let record = match auction.is_private() {
+                                    true => match PrivateAuction::seal(&auction) {
+                                        Some((private_auction, auction_key)) => {
+                                            self.auction_keys
+                                                .insert(auction.id.clone(), auction_key);
+                                            private_auction.serialized_to_bytes().unwrap()
+                                        }
+                                        None => {
+                                            self.create_screen.set_status(
+                                                "Invalid invitee key, the auction wasn't created."
+                                                    .to_string(),
+                                            );
+                                            return;
+                                        }
+                                    },
+                                    false => auction.serialized().as_bytes().to_vec(),
+                                };
 
                                 // Store Auction
                                 let routing_table = self.routing_table.clone().unwrap();
                                 let routing_table_clone = routing_table.clone();
-                                let hash = kademlia::string_to_hash_key(
-                                    &("auction:".to_string() + &auction_id.to_string()),
-                                );
+                                let hash = Namespace::Auction.key(&auction.id);
                                 let auction_clone = auction.clone();
                                 tokio::spawn(async move {
-                                    store_value_dht(
-                                        &routing_table_clone,
-                                        hash,
-                                        auction_clone.serialized().as_bytes().to_vec(),
-                                    )
-                                    .await;
-                                });
-
-                                // Update Latest Auction
-                                let routing_table = self.routing_table.clone().unwrap();
-                                let routing_table_clone = routing_table.clone();
-                                let hash =
-                                    kademlia::string_to_hash_key(&("last_auction".to_string()));
-                                tokio::spawn(async move {
-                                    store_value_dht(
-                                        &routing_table_clone,
-                                        hash,
-                                        auction.serialized().as_bytes().to_vec(),
-                                    )
-                                    .await;
+                                    // Chunks first, so the manifests never point to missing data
+                                    for attachment in &attachments {
+                                        store_attachment(&routing_table_clone, attachment).await;
+                                    }
+                                    store_value_dht(&routing_table_clone, Namespace::Auction, hash, record).await;
+                                    // File it under the search index, private auctions stay unlisted
+                                    if !auction_clone.is_private() {
+                                        index_auction(&routing_table_clone, &auction_clone).await;
+                                    }
                                 });
 
-                                // Create Auction Signature
-                                let auction_signature = auction::signature::AuctionSignature::new(
-                                    auction_id.to_string(),
-                                    auction_hash,
-                                );
+                                // Create the Auction Signature, or the full record, depending on the ledger mode
+                                let transaction = self
+                                    .menu_screen
+                                    .get_ledger_mode()
+                                    .for_auction(&auction)
+                                    .auction_transaction(&auction);
 
-                                // Create Block with Auction Signature as transaction
+                                // Create Block with it as transaction
                                 let routing_table = self.routing_table.clone().unwrap();
                                 let blockchain = self.blockchain.clone();
                                 let routing_table_clone = routing_table.clone();
-                                let auction_signature_clone = auction_signature.clone();
                                 tokio::spawn(async move {
-                                    //Fetch the latest chain
-                                    if let Some(fetched_chain) =
-                                        fetch_full_chain(&routing_table_clone).await
-                                    {
-                                        let mut blockchain_lock = blockchain.lock().await;
-                                        *blockchain_lock = fetched_chain;
-                                        println!("Chain fetched successfully");
-                                    } else {
-                                        println!("Failed to fetch chain, aborting mine");
-                                        return;
-                                    }
-
-                                    //Prepare the new block
-                                    let mut blockchain_lock = blockchain.lock().await;
-                                    let last_block_hash =
-                                        blockchain_lock.get_first_block().get_hash();
-
-                                    let header = blockchain::block::block_header::BlockHeader::new(
-                                        last_block_hash.into(),
-                                    );
-                                    let body = blockchain::block::block_body::BlockBody::new(
-                                        auction_signature_clone.serialized_to_bytes().unwrap(),
-                                    );
-                                    let mut block = blockchain::block::Block::new(header, body);
-
-                                    // Mine the block
-                                    block.mine();
-
-                                    drop(blockchain_lock); // Release lock before DHT operations
-
-                                    // Store the block in the DHT under its truncated hash
-                                    let truncated_hash = &block.get_hash()[0..20]; // First 20 bytes
-                                    let block_dht_key =
-                                        kademlia::string_to_hash_key(&hex::encode(truncated_hash));
-
-                                    store_value_dht(
-                                        &routing_table,
-                                        block_dht_key,
-                                        block.serialized().as_bytes().to_vec(),
-                                    )
-                                    .await;
-
-                                    println!(
-                                        "Block stored under key {:?}",
-                                        hex::encode(truncated_hash)
-                                    );
-
-                                    // Update 'latest_block' pointer in DHT
-                                    let latest_block_key =
-                                        kademlia::string_to_hash_key("latest_block");
-
-                                    store_value_dht(
-                                        &routing_table,
-                                        latest_block_key,
-                                        block.serialized().as_bytes().to_vec(),
+                                    mine_transaction(
+                                        &routing_table_clone,
+                                        &blockchain,
+                                        transaction,
                                     )
                                     .await;
-
-                                    println!("Latest block updated");
                                 });
 
                                 // Change state to Auction
@@ -560,7 +674,7 @@ impl App for AuctionApp {
                                 let blockchain_clone = self.blockchain.clone();
                                 tokio::spawn(async move {
                                     if let Some(chain) =
-                                        fetch_full_chain(&routing_table_clone).await
+                                        fetch_full_chain(&routing_table_clone, &blockchain_clone).await
                                     {
                                         let mut blockchain = blockchain_clone.lock().await;
                                         *blockchain = chain;
@@ -575,282 +689,297 @@ impl App for AuctionApp {
                                 let blockchain = self.blockchain.clone();
 
                                 tokio::spawn(async move {
-                                    //Fetch the latest chain
-                                    if let Some(fetched_chain) =
-                                        fetch_full_chain(&routing_table).await
-                                    {
-                                        let mut blockchain_lock = blockchain.lock().await;
-                                        *blockchain_lock = fetched_chain;
-                                        println!("Chain fetched successfully");
-                                    } else {
-                                        println!("Failed to fetch chain, aborting mine");
-                                        return;
-                                    }
-
-                                    //Prepare the new block
-                                    let mut blockchain_lock = blockchain.lock().await;
-                                    let last_block_hash =
-                                        blockchain_lock.get_first_block().get_hash();
-
-                                    let header = blockchain::block::block_header::BlockHeader::new(
-                                        last_block_hash.into(),
-                                    );
-                                    let body = blockchain::block::block_body::BlockBody::new(
-                                        transaction.as_bytes().to_vec(),
-                                    );
-                                    let mut block = blockchain::block::Block::new(header, body);
-
-                                    // Mine the block
-                                    block.mine();
-
-                                    drop(blockchain_lock); // Release lock before DHT operations
-
-                                    // Store the block in the DHT under its truncated hash
-                                    let truncated_hash = &block.get_hash()[0..20]; // First 20 bytes
-                                    let block_dht_key =
-                                        kademlia::string_to_hash_key(&hex::encode(truncated_hash));
-
-                                    store_value_dht(
+                                    mine_transaction(
                                         &routing_table,
-                                        block_dht_key,
-                                        block.serialized().as_bytes().to_vec(),
-                                    )
-                                    .await;
-
-                                    println!(
-                                        "Block stored under key {:?}",
-                                        hex::encode(truncated_hash)
-                                    );
-
-                                    // Update 'latest_block' pointer in DHT
-                                    let latest_block_key =
-                                        kademlia::string_to_hash_key("latest_block");
-
-                                    store_value_dht(
-                                        &routing_table,
-                                        latest_block_key,
-                                        block.serialized().as_bytes().to_vec(),
+                                        &blockchain,
+                                        transaction.as_bytes().to_vec(),
                                     )
                                     .await;
-
-                                    println!("Latest block updated");
                                 });
                             }
                         }
                     }
                 }
                 AppState::Bid => {
-                    let chain = self.blockchain.try_lock().unwrap().clone();
-                    self.bid_screen.set_chain(chain);
+                    if let Ok(chain) = self.blockchain.try_lock() {
+                        self.bid_screen.set_chain(chain.clone());
+                    }
+                    if let Ok(bid_list) = self.bid_list.try_lock() {
+                        self.bid_screen.set_bids(bid_list.clone());
+                    }
+                    if let Ok(proxy_agents) = self.proxy_agents.try_lock() {
+                        self.bid_screen.set_proxy_agents(proxy_agents.clone());
+                    }
+                    if let Ok(mut bid_status) = self.bid_status.try_lock()
+                        && let Some(bid_status) = bid_status.take()
+                    {
+                        self.bid_screen.set_status(bid_status);
+                    }
 
                     if let Some(event) = self.bid_screen.ui(ui) {
                         match event {
                             screens::bid_screen::BidScreenEvent::Back => {
                                 self.bid_screen.set_bids(Vec::new());
+                                self.follow_auction(None);
                                 self.state = AppState::Auction;
                             }
-                            screens::bid_screen::BidScreenEvent::SubmitBid { amount } => {
+                            screens::bid_screen::BidScreenEvent::SubmitBid {
+                                amount,
+                                lot_index,
+                                quantity,
+                            } => {
                                 let curr_auction = self.bid_screen.get_auction().unwrap().clone();
-                                let node_id = self.menu_screen.get_node_id();
+                                let new_bid = auction::bid::Bid::new(
+                                    curr_auction.id.clone(),
+                                    self.identity.get_public_key(),
+                                    amount,
+                                )
+                                .with_lot(lot_index, quantity);
+                                let identity = self.identity.clone();
+                                let ledger_mode = self.menu_screen.get_ledger_mode();
                                 let routing_table = self.routing_table.clone().unwrap();
                                 let blockchain = self.blockchain.clone();
-                                let latest_bid_arc = self.latest_bid.clone();
 
                                 tokio::spawn(async move {
-                                    // Fetch latest bid from DHT
-                                    let latest_hash = kademlia::string_to_hash_key(&format!(
-                                        "auction:{}:bid:latest_bid",
-                                        curr_auction.id
-                                    ));
-                                    let fetched_bid =
-                                        find_value_dht(&routing_table, latest_hash.clone()).await;
-
-                                    let latest_bid = match fetched_bid {
-                                        Some(bytes) => auction::bid::Bid::deserialized(
-                                            std::str::from_utf8(&bytes).unwrap(),
-                                        ),
-                                        None => {
-                                            println!("No latest bid found. Starting from default.");
-                                            let mut b = auction::bid::Bid::default();
-                                            b.auction_id = curr_auction.id;
-                                            b
-                                        }
-                                    };
-
-                                    // Create new bid
-                                    let new_bid = auction::bid::Bid::new(
-                                        latest_bid.id + 1,
-                                        curr_auction.id,
-                                        node_id,
-                                        amount as f64,
-                                    );
-
-                                    // Store new bid in DHT
-                                    let bid_hash = kademlia::string_to_hash_key(&format!(
-                                        "auction:{}:bid:{}",
-                                        curr_auction.id, new_bid.id
-                                    ));
-                                    store_value_dht(
+                                    submit_bid(
                                         &routing_table,
-                                        bid_hash.clone(),
-                                        new_bid.serialized().as_bytes().to_vec(),
+                                        &blockchain,
+                                        &identity,
+                                        ledger_mode,
+                                        &curr_auction,
+                                        new_bid,
                                     )
                                     .await;
-                                    println!("Bid stored under key {:?}", hex::encode(bid_hash));
+                                });
+                            }
+                            screens::bid_screen::BidScreenEvent::StartProxy {
+                                max_amount,
+                                increment,
+                            } => {
+                                let curr_auction = self.bid_screen.get_auction().unwrap().clone();
+                                let proxy_bid = auction::proxy::ProxyBid::new(
+                                    curr_auction.id.clone(),
+                                    self.identity.get_public_key(),
+                                    max_amount,
+                                    increment,
+                                );
+                                proxy_agent::start_proxy_agent(
+                                    self.proxy_agents.clone(),
+                                    self.routing_table.clone().unwrap(),
+                                    self.blockchain.clone(),
+                                    self.identity.clone(),
+                                    self.menu_screen.get_ledger_mode(),
+                                    curr_auction,
+                                    proxy_bid,
+                                );
+                            }
+                            screens::bid_screen::BidScreenEvent::StopProxy => {
+                                let auction_id = self.bid_screen.get_auction().unwrap().id.clone();
+                                let proxy_agents = self.proxy_agents.clone();
+                                tokio::spawn(async move {
+                                    proxy_agent::stop_proxy_agents(&proxy_agents, &auction_id)
+                                        .await;
+                                });
+                            }
+                            screens::bid_screen::BidScreenEvent::GetBids => {
+                                let curr_auction: Auction =
+                                    self.bid_screen.get_auction().unwrap().clone();
+                                let auction_id = curr_auction.id.clone();
 
-                                    // Update latest bid pointer
-                                    store_value_dht(
+                                let routing_table = self.routing_table.clone().unwrap();
+                                let blockchain = self.blockchain.clone();
+                                let bid_list = self.bid_list.clone();
+                                let identity = self.identity.clone();
+
+                                tokio::spawn(async move {
+                                    let Some(fetched_chain) =
+                                        fetch_full_chain(&routing_table, &blockchain).await
+                                    else {
+                                        println!("Failed to fetch chain");
+                                        return;
+                                    };
+                                    println!("Chain fetched successfully");
+
+                                    let bids = fetch_bids(
                                         &routing_table,
-                                        latest_hash,
-                                        new_bid.serialized().as_bytes().to_vec(),
+                                        &fetched_chain,
+                                        &identity,
+                                        &auction_id,
                                     )
                                     .await;
-                                    println!("Latest bid pointer updated");
 
-                                    // Update local state
-                                    let mut latest_bid_lock = latest_bid_arc.lock().await;
-                                    *latest_bid_lock = new_bid.clone();
+                                    *blockchain.lock().await = fetched_chain;
+                                    let mut bid_list_guard = bid_list.lock().await;
+                                    *bid_list_guard = bids;
+                                });
 
-                                    // Create Bid Signature
-                                    let bid_signature = auction::signature::BidSignature::new(
-                                        new_bid.id.to_string(),
-                                        new_bid.get_hash(),
-                                    );
+                                // Refresh the bid screen with bids
+                                let bid_list = self.bid_list.try_lock().unwrap(); // Lock to read the auction list
+                                self.bid_screen.set_bids(bid_list.clone());
+                            }
+                            screens::bid_screen::BidScreenEvent::Settle => {
+                                let curr_auction = self.bid_screen.get_auction().unwrap().clone();
+                                let routing_table = self.routing_table.clone().unwrap();
+                                let blockchain = self.blockchain.clone();
+                                let identity = self.identity.clone();
 
-                                    // Fetch blockchain
-                                    if let Some(fetched_chain) =
-                                        fetch_full_chain(&routing_table).await
-                                    {
-                                        let mut blockchain_lock = blockchain.lock().await;
-                                        *blockchain_lock = fetched_chain;
-                                        println!("Chain fetched successfully");
-                                    } else {
-                                        println!("Failed to fetch chain, aborting mine");
+                                tokio::spawn(async move {
+                                    let Some(fetched_chain) =
+                                        fetch_full_chain(&routing_table, &blockchain).await
+                                    else {
+                                        println!("Failed to fetch chain, aborting settlement");
                                         return;
-                                    }
-
-                                    // Prepare and mine new block
-                                    let mut blockchain_lock = blockchain.lock().await;
-                                    let last_block_hash =
-                                        blockchain_lock.get_first_block().get_hash();
-                                    let header = blockchain::block::block_header::BlockHeader::new(
-                                        last_block_hash.into(),
-                                    );
-                                    let body = blockchain::block::block_body::BlockBody::new(
-                                        bid_signature.serialized_to_bytes().unwrap(),
-                                    );
-                                    let mut block = blockchain::block::Block::new(header, body);
-
-                                    block.mine();
-
-                                    drop(blockchain_lock); // Release lock before storing in DHT
+                                    };
 
-                                    // Store block in DHT
-                                    let truncated_hash = &block.get_hash()[0..20];
-                                    let block_dht_key =
-                                        kademlia::string_to_hash_key(&hex::encode(truncated_hash));
-                                    store_value_dht(
+                                    // Settle with every bid on the chain, not just the ones on screen
+                                    let bids = fetch_bids(
                                         &routing_table,
-                                        block_dht_key,
-                                        block.serialized().as_bytes().to_vec(),
+                                        &fetched_chain,
+                                        &identity,
+                                        &curr_auction.id,
                                     )
                                     .await;
-                                    println!(
-                                        "Block stored under key {:?}",
-                                        hex::encode(truncated_hash)
-                                    );
-
-                                    // Update 'latest_block' pointer
-                                    let latest_block_key =
-                                        kademlia::string_to_hash_key("latest_block");
-                                    store_value_dht(
+                                    let settlement =
+                                        auction::settlement::SettlementTransaction::new(
+                                            &fetched_chain,
+                                            &curr_auction,
+                                            bids,
+                                        );
+
+                                    mine_transaction(
                                         &routing_table,
-                                        latest_block_key,
-                                        block.serialized().as_bytes().to_vec(),
+                                        &blockchain,
+                                        settlement.serialized_to_bytes().unwrap(),
                                     )
                                     .await;
-                                    println!("Latest block updated");
+                                    println!("Auction {} settled", curr_auction.id);
                                 });
                             }
-                            screens::bid_screen::BidScreenEvent::GetBids => {
-                                let curr_auction: Auction =
-                                    self.bid_screen.get_auction().unwrap().clone();
-                                let auction_id = curr_auction.id;
-
+                            screens::bid_screen::BidScreenEvent::SellerAction { action } => {
+                                let curr_auction = self.bid_screen.get_auction().unwrap().clone();
                                 let routing_table = self.routing_table.clone().unwrap();
-                                let latest_bid = self.latest_bid.clone();
-                                let bid_list = self.bid_list.clone();
+                                let blockchain = self.blockchain.clone();
 
+                                // An extended auction is filed again under its new ending time
+                                let extended = match action {
+                                    auction::seller::SellerAction::ExtendDeadline { new_ending_time }
+                                        if !curr_auction.is_private() =>
+                                    {
+                                        let mut extended = curr_auction.clone();
+                                        extended.ending_time = new_ending_time;
+                                        Some(extended)
+                                    }
+                                    _ => None,
+                                };
+
+                                // Sign the action with our key, nodes ignore it unless we are the seller
+                                let seller_transaction = auction::seller::SellerTransaction::new(
+                                    &self.identity,
+                                    curr_auction.id.clone(),
+                                    action,
+                                );
                                 tokio::spawn(async move {
-                                    // Fetch the latest bid
-                                    let hash = kademlia::string_to_hash_key(&format!(
-                                        "auction:{}:bid:latest_bid",
-                                        auction_id
-                                    ));
-
-                                    let value = find_value_dht(&routing_table, hash).await;
-                                    let mut latest_bid_guard = latest_bid.lock().await;
-
-                                    match value {
-                                        Some(bytes) => {
-                                            *latest_bid_guard = auction::bid::Bid::deserialized(
-                                                from_utf8(&bytes).unwrap(),
-                                            );
-                                            println!("Latest bid found: {:?}", *latest_bid_guard);
-                                        }
+                                    let mined = mine_transaction(
+                                        &routing_table,
+                                        &blockchain,
+                                        seller_transaction.serialized_to_bytes().unwrap(),
+                                    )
+                                    .await;
+                                    if let (Some(_), Some(extended)) = (mined, extended) {
+                                        index_auction(&routing_table, &extended).await;
+                                    }
+                                });
+                            }
+                            screens::bid_screen::BidScreenEvent::DiscloseKey => {
+                                let auction_id = self.bid_screen.get_auction().unwrap().id.clone();
+                                let known_key = self.auction_keys.get(&auction_id).copied();
+                                let routing_table = self.routing_table.clone().unwrap();
+                                let identity = self.identity.clone();
+                                let bid_status = self.bid_status.clone();
+                                let ctx = ui.ctx().clone();
+                                tokio::spawn(async move {
+                                    // The key of an auction created elsewhere is unwrapped from
+                                    // the seller's envelope in the DHT
+                                    let auction_key = match known_key {
+                                        Some(auction_key) => Some(auction_key),
                                         None => {
-                                            println!("Latest bid not found");
-                                            *latest_bid_guard = auction::bid::Bid::default();
-                                            latest_bid_guard.auction_id = auction_id;
+                                            fetch_auction_key(&routing_table, &identity, &auction_id)
+                                                .await
                                         }
-                                    }
-
-                                    let latest_bid_id = latest_bid_guard.id;
-                                    drop(latest_bid_guard); // Explicitly drop the lock before reacquiring
-
-                                    // Now fetch all previous bids
-                                    let mut bids = Vec::new();
-                                    for i in 0..=latest_bid_id {
-                                        let bid_hash = kademlia::string_to_hash_key(&format!(
-                                            "auction:{}:bid:{}",
-                                            auction_id, i
-                                        ));
-
-                                        if let Some(bytes) =
-                                            find_value_dht(&routing_table, bid_hash).await
-                                        {
-                                            let bid = auction::bid::Bid::deserialized(
-                                                from_utf8(&bytes).unwrap(),
-                                            );
-                                            bids.push(bid);
-                                        } else {
-                                            println!("Bid {} not found", i);
+                                    };
+                                    let status = match auction_key {
+                                        Some(auction_key) => {
+                                            ctx.copy_text(hex::encode(auction_key.as_bytes()));
+                                            "Auction key copied, whoever holds it can read the auction and its bids.".to_string()
                                         }
-                                    }
-
-                                    let mut bid_list_guard = bid_list.lock().await;
-                                    *bid_list_guard = bids;
+                                        None => "Auction key not found.".to_string(),
+                                    };
+                                    *bid_status.lock().await = Some(status);
+                                    ctx.request_repaint();
                                 });
-
-                                // Get Chain
+                            }
+                            screens::bid_screen::BidScreenEvent::PostFeedback {
+                                to,
+                                rating,
+                                comment,
+                            } => {
+                                let curr_auction = self.bid_screen.get_auction().unwrap().clone();
                                 let routing_table = self.routing_table.clone().unwrap();
                                 let blockchain = self.blockchain.clone();
-                                let routing_table_clone = routing_table.clone();
+
+                                // Signed with our key, only counts if we are the seller or the winner
+                                let feedback = FeedbackTransaction::new(
+                                    &self.identity,
+                                    curr_auction.id.clone(),
+                                    to,
+                                    rating,
+                                    comment,
+                                );
                                 tokio::spawn(async move {
-                                    if let Some(fetched_chain) =
-                                        fetch_full_chain(&routing_table_clone).await
-                                    {
-                                        let mut blockchain = blockchain.lock().await;
-                                        *blockchain = fetched_chain;
-                                        println!("Chain fetched successfully");
-                                    } else {
-                                        println!("Failed to fetch chain");
-                                    }
+                                    mine_transaction(
+                                        &routing_table,
+                                        &blockchain,
+                                        feedback.serialized_to_bytes().unwrap(),
+                                    )
+                                    .await;
                                 });
-
-                                // Refresh the bid screen with bids
-                                let bid_list = self.bid_list.try_lock().unwrap(); // Lock to read the auction list
-                                self.bid_screen.set_bids(bid_list.clone());
+                                self.bid_screen
+                                    .set_status("Feedback submitted.".to_string());
+                            }
+                            screens::bid_screen::BidScreenEvent::ExportCertificate => {
+                                let curr_auction = self.bid_screen.get_auction().unwrap().clone();
+                                let Ok(chain) = self.blockchain.try_lock().map(|chain| chain.clone())
+                                else {
+                                    self.bid_screen
+                                        .set_status("Chain is busy, try again.".to_string());
+                                    return;
+                                };
+                                let bids = self.bid_screen.get_bids().clone();
+
+                                let status = match auction::certificate::WinnerCertificate::new(
+                                    &chain,
+                                    &curr_auction,
+                                    bids,
+                                ) {
+                                    // Double check the bundle before handing it over
+                                    Some(certificate) if certificate.verify_against(&chain) => {
+                                        let path = format!(
+                                            "winner_certificate_{}.json",
+                                            curr_auction.id.get(0..16).unwrap_or(&curr_auction.id)
+                                        );
+                                        match certificate.export(std::path::Path::new(&path)) {
+                                            Ok(()) => format!("Certificate exported to {}", path),
+                                            Err(e) => {
+                                                format!("Failed to export certificate: {}", e)
+                                            }
+                                        }
+                                    }
+                                    Some(_) => "Certificate failed verification.".to_string(),
+                                    None => {
+                                        "No valid settlement found, get bids first.".to_string()
+                                    }
+                                };
+                                self.bid_screen.set_status(status);
                             }
                         }
                     }
@@ -858,43 +987,543 @@ impl App for AuctionApp {
             }
         });
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.shutdown.trigger();
+    }
 }
 
-pub async fn fetch_full_chain(routing_table: &RwLock<RoutingTable>) -> Option<Chain> {
-    let mut chain = Chain::new();
-    let mut current_hash = string_to_hash_key("latest_block");
+// Mines a block carrying the transaction on top of the latest chain and publishes it in the DHT
+// If another miner's block takes the tip first ours is left out of the chain, so the transaction
+// is mined again on top of the new tip
+pub async fn mine_transaction(
+    routing_table: &RwLock<RoutingTable>,
+    blockchain: &Mutex<Chain>,
+    transaction: Vec<u8>,
+) -> Option<Block> {
+    for _ in 0..MAX_MINE_ATTEMPTS {
+        let Some(block) = mine_block(routing_table, blockchain, transaction.clone()).await else {
+            continue;
+        };
+        match fetch_full_chain(routing_table, blockchain).await {
+            Some(chain)
+                if chain
+                    .get_blocks()
+                    .iter()
+                    .any(|confirmed| confirmed.get_hash() == block.get_hash()) =>
+            {
+                *blockchain.lock().await = chain;
+                return Some(block);
+            }
+            _ => println!("Block left out of the chain, mining again"),
+        }
+    }
+    println!("Failed to mine the transaction");
+    None
+}
 
-    loop {
-        // Fetch the block from DHT
-        let value = find_value_dht(routing_table, current_hash).await;
+// Mines one block on top of the latest chain, stores it and moves our pointer to it
+async fn mine_block(
+    routing_table: &RwLock<RoutingTable>,
+    blockchain: &Mutex<Chain>,
+    transaction: Vec<u8>,
+) -> Option<Block> {
+    //Fetch the latest chain
+    let Some(fetched_chain) = fetch_full_chain(routing_table, blockchain).await else {
+        println!("Failed to fetch chain, aborting mine");
+        return None;
+    };
+    *blockchain.lock().await = fetched_chain.clone();
+    println!("Chain fetched successfully");
+
+    //Prepare the new block from our copy of the chain, so the UI is not blocked while mining.
+    //Its timestamp must be past the chain's median time past
+    let height = fetched_chain.get_blocks().len() as u64 + 1;
+    let last_block_hash = fetched_chain.get_first_block().get_hash();
+    let timestamp = fetched_chain.get_next_timestamp(current_timestamp());
+
+    let header = blockchain::block::block_header::BlockHeader::new_with_timestamp(
+        last_block_hash,
+        timestamp,
+    );
+    let body = blockchain::block::block_body::BlockBody::new(transaction);
+    let mut block = blockchain::block::Block::new(header, body);
+
+    // Mine the block
+    block.mine();
+
+    // Store the block in the DHT under its hash key
+    let block_dht_key = block_key(&block.get_hash());
+
+    let stored = store_value_dht(
+        routing_table,
+        Namespace::Block,
+        block_dht_key,
+        block.serialized().as_bytes().to_vec(),
+    )
+    .await?;
+    if !stored.has_quorum(BLOCK_WRITE_QUORUM) {
+        println!("Block stored on {} nodes only", stored.acknowledged.len());
+        return None;
+    }
 
-        match value {
-            Some(bytes) => {
-                let block_str = std::str::from_utf8(&bytes).ok()?;
-                let block = Block::deserialized(block_str);
+    println!("Block stored under key {:?}", hex::encode(block_dht_key));
+
+    // Update our 'latest_block' pointer in DHT, its sequence number is the chain's height so
+    // it only moves forward
+    let stored = store_record_dht(
+        routing_table,
+        LATEST_BLOCK,
+        height,
+        block.serialized().as_bytes().to_vec(),
+    )
+    .await?;
+    if !stored.has_quorum(BLOCK_WRITE_QUORUM) {
+        println!(
+            "Latest block stored on {} nodes only",
+            stored.acknowledged.len()
+        );
+        return None;
+    }
 
-                // Add block to the chain
-                chain.add_block(block.clone());
+    println!("Latest block updated");
+    Some(block)
+}
 
-                // If we hit the genesis block, we stop
-                if block.header.get_parent_hash() == vec![0; 64] {
-                    println!("Genesis block reached");
-                    break;
+fn current_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+// Subscribes to our own node's block stream and refreshes the chain, the auctions and the bids
+// of the followed auction on new events, reconnecting if the stream ends
+async fn follow_chain_events(
+    ctx: egui::Context,
+    routing_table: Arc<RwLock<RoutingTable>>,
+    blockchain: Arc<Mutex<Chain>>,
+    auction_list: Arc<Mutex<Vec<Auction>>>,
+    bid_list: Arc<Mutex<Vec<auction::bid::Bid>>>,
+    followed: Arc<Mutex<Followed>>,
+    identity: Identity,
+) {
+    let (node, client_pool) = {
+        let routing_table = routing_table.read().await;
+        (
+            routing_table.get_curr_node().clone(),
+            routing_table.get_client_pool().clone(),
+        )
+    };
+    loop {
+        if let Some(mut client) = client_pool.client(&node.get_ip(), node.get_port()) {
+            let request = tonic::Request::new(SubscribeRequest {
+                node: None,
+                topic: pubsub::BLOCKS_TOPIC.to_string(),
+                nonce: kademlia::routing_table::node_id::generate_node_id().to_vec(),
+            });
+            if let Ok(response) = client.subscribe(request).await {
+                let mut stream = response.into_inner();
+                while let Ok(Some(_event)) = stream.message().await {
+                    // Combine the burst of events into one refresh, at most one per window
+                    let deadline = tokio::time::Instant::now() + EVENT_BATCH_WINDOW;
+                    while let Ok(Ok(Some(_event))) =
+                        tokio::time::timeout_at(deadline, stream.message()).await
+                    {
+                    }
+                    let Some(fetched_chain) = fetch_full_chain(&routing_table, &blockchain).await
+                    else {
+                        continue;
+                    };
+                    let (auction_id, query) = {
+                        let followed = followed.lock().await;
+                        (followed.auction_id.clone(), followed.query.clone())
+                    };
+                    let auctions = match query.is_empty() {
+                        true => fetch_auctions(&routing_table, &fetched_chain, &identity).await,
+                        false => search_auctions(&routing_table, &fetched_chain, &query).await,
+                    };
+                    if let Some(auction_id) = auction_id {
+                        let bids =
+                            fetch_bids(&routing_table, &fetched_chain, &identity, &auction_id)
+                                .await;
+                        *bid_list.lock().await = bids;
+                    }
+                    *auction_list.lock().await = auctions;
+                    *blockchain.lock().await = fetched_chain;
+                    ctx.request_repaint();
                 }
+            }
+        }
+        // The server isn't up yet, or the stream ended
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
 
-                // Move to the previous block
-                current_hash = {
-                    let mut hash = block.header.get_parent_hash();
-                    hash.truncate(20); // Truncate to 20 bytes
-                    string_to_hash_key(&hex::encode(hash))
-                };
+// Signs a bid with our key, caches it in the DHT and mines its commitment
+// Manual bids and proxy agents both go through here
+pub async fn submit_bid(
+    routing_table: &RwLock<RoutingTable>,
+    blockchain: &Mutex<Chain>,
+    identity: &Identity,
+    ledger_mode: LedgerMode,
+    auction: &Auction,
+    new_bid: auction::bid::Bid,
+) -> Option<auction::bid::Bid> {
+    // The bid's ID is derived from our key and a fresh nonce, signed so it can lock our funds
+    let new_bid = new_bid.signed(identity);
+
+    // Bids on private auctions are encrypted with the auction key
+    let record = match auction.is_private() {
+        true => {
+            let auction_key = fetch_auction_key(routing_table, identity, &auction.id).await?;
+            PrivateBid::seal(&auction_key, &new_bid)
+                .serialized_to_bytes()
+                .unwrap()
+        }
+        false => new_bid.serialized().as_bytes().to_vec(),
+    };
+
+    // Store new bid in DHT, it isn't committed unless enough nodes hold it for others to fetch
+    let bid_hash = auction::bid_key(&auction.id, &new_bid.id);
+    let stored = store_value_dht(routing_table, Namespace::Bid, bid_hash, record).await?;
+    if !stored.has_quorum(BID_WRITE_QUORUM) {
+        println!(
+            "Bid stored on {} nodes only, {} failed",
+            stored.acknowledged.len(),
+            stored.failed.len()
+        );
+        return None;
+    }
+    println!("Bid stored under key {:?}", hex::encode(bid_hash));
+
+    // Mine a block with the bid signature, or the full record, as transaction
+    // Bids on private auctions are sealed, their commitment is signed with our key
+    let transaction = match auction.is_private() {
+        true => BidSignature::sealed(identity, &new_bid)
+            .serialized_to_bytes()
+            .unwrap(),
+        false => ledger_mode.bid_transaction(&new_bid),
+    };
+    mine_transaction(routing_table, blockchain, transaction)
+        .await
+        .map(|_| new_bid)
+}
+
+// Stores the chunks of an attachment, each under the key derived from its hash
+pub async fn store_attachment(routing_table: &RwLock<RoutingTable>, attachment: &Attachment) {
+    for (chunk, chunk_hash) in attachment
+        .chunks
+        .iter()
+        .zip(&attachment.manifest.chunk_hashes)
+    {
+        if let Some(key) = chunk_key(chunk_hash) {
+            store_value_dht(routing_table, Namespace::Chunk, key, chunk.clone()).await;
+        }
+    }
+}
+
+// Fetches the chunks listed in a manifest and reassembles them, checking every chunk
+pub async fn fetch_attachment(
+    routing_table: &RwLock<RoutingTable>,
+    manifest: &AttachmentManifest,
+) -> Result<Vec<u8>, AttachmentError> {
+    let mut chunks = Vec::new();
+    for (index, chunk_hash) in manifest.chunks_to_fetch().enumerate() {
+        let key = chunk_key(chunk_hash).ok_or(AttachmentError::InvalidChunkHash(index))?;
+        let Some(chunk) = find_content_dht(routing_table, Namespace::Chunk, key).await else {
+            return Err(AttachmentError::MissingChunk(index));
+        };
+        chunks.push(Some(chunk));
+    }
+    manifest.reassemble(chunks)
+}
+
+// Files a new auction under its postings lists of the search index
+// Each list is read and merged before being written back, storing nodes merge it again
+// with their own copy, so concurrent writers don't lose each other's entries
+pub async fn index_auction(routing_table: &RwLock<RoutingTable>, auction: &Auction) {
+    let entry = auction::index::index_entry(auction);
+    for key in auction::index::index_keys(auction) {
+        let hash = Namespace::Index.key(&key);
+        let mut postings = match find_value_dht(routing_table, Namespace::Index, hash).await {
+            Some(bytes) => PostingsList::deserialized_from_bytes(&bytes)
+                .ok()
+                .filter(|postings| postings.is_valid(&hash))
+                .unwrap_or_else(|| PostingsList::new(&key, vec![])),
+            None => PostingsList::new(&key, vec![]),
+        };
+        postings.merge(PostingsList::new(&key, vec![entry.clone()]));
+        store_value_dht(
+            routing_table,
+            Namespace::Index,
+            hash,
+            postings.serialized().into_bytes(),
+        )
+        .await;
+    }
+}
+
+// Auctions matching a search, only the postings lists it needs and the matching auctions
+// are fetched; auctions not committed on the chain are left out
+pub async fn search_auctions(
+    routing_table: &RwLock<RoutingTable>,
+    chain: &Chain,
+    query: &AuctionQuery,
+) -> Vec<Auction> {
+    let mut groups = Vec::new();
+    for keys in query.key_groups() {
+        let mut lists = Vec::new();
+        for key in keys {
+            let hash = Namespace::Index.key(&key);
+            if let Some(bytes) = find_value_dht(routing_table, Namespace::Index, hash).await {
+                lists.extend(
+                    PostingsList::deserialized_from_bytes(&bytes)
+                        .ok()
+                        .filter(|postings| postings.is_valid(&hash)),
+                );
+            }
+        }
+        groups.push(lists);
+    }
+
+    let committed = AuctionSignature::get_auction_ids(chain);
+    let state = AuctionState::replay(chain);
+    let mut auctions = Vec::new();
+    for auction_id in query.combine(groups) {
+        if !committed.contains(&auction_id) {
+            continue;
+        }
+        if let Some(projected) = state.get_auction(&auction_id) {
+            auctions.push(projected.auction.clone());
+            continue;
+        }
+        let hash = Namespace::Auction.key(&auction_id);
+        if let Some(bytes) = find_value_dht(routing_table, Namespace::Auction, hash).await {
+            match Auction::deserialized_from_bytes(&bytes) {
+                Ok(auction) => auctions.push(auction),
+                Err(e) => println!("Skipping undecodable auction {}: {}", auction_id, e),
+            }
+        }
+    }
+    // Postings keep the ending time an auction was filed with, the chain has its extensions
+    auctions.retain(|auction| {
+        auction
+            .lifecycle(chain)
+            .is_some_and(|lifecycle| query.ends_in_range(lifecycle.ending_time))
+    });
+    auctions
+}
+
+// Auctions committed on the chain: the ones recorded in full are rebuilt from the chain,
+// the DHT is only asked for the others
+// Private auctions we aren't invited to are left out
+pub async fn fetch_auctions(
+    routing_table: &RwLock<RoutingTable>,
+    chain: &Chain,
+    identity: &Identity,
+) -> Vec<Auction> {
+    let state = AuctionState::replay(chain);
+    let mut auctions = Vec::new();
+    for auction_id in AuctionSignature::get_auction_ids(chain) {
+        if let Some(projected) = state.get_auction(&auction_id) {
+            auctions.push(projected.auction.clone());
+            continue;
+        }
+        let hash = Namespace::Auction.key(&auction_id);
+        if let Some(bytes) = find_value_dht(routing_table, Namespace::Auction, hash).await {
+            if let Ok(private_auction) = PrivateAuction::deserialized_from_bytes(&bytes) {
+                auctions.extend(
+                    private_auction
+                        .auction_key(identity)
+                        .and_then(|auction_key| private_auction.open(&auction_key)),
+                );
+                continue;
+            }
+            match Auction::deserialized_from_bytes(&bytes) {
+                Ok(auction) => auctions.push(auction),
+                Err(e) => println!("Skipping undecodable auction {}: {}", auction_id, e),
+            }
+        }
+    }
+    auctions
+}
+
+// Bids of an auction committed on the chain, from the chain when recorded in full, else the DHT
+// Bids on a private auction are decrypted with its key, if we were invited
+pub async fn fetch_bids(
+    routing_table: &RwLock<RoutingTable>,
+    chain: &Chain,
+    identity: &Identity,
+    auction_id: &str,
+) -> Vec<auction::bid::Bid> {
+    let state = AuctionState::replay(chain);
+    let recorded = state
+        .get_auction(auction_id)
+        .map_or(Vec::new(), |projected| projected.bids.clone());
+    let mut bids = Vec::new();
+    let mut auction_key: Option<AuctionKey> = None;
+    for bid_id in BidSignature::get_bid_ids(chain, auction_id) {
+        if let Some(bid) = recorded.iter().find(|bid| bid.id == bid_id) {
+            bids.push(bid.clone());
+            continue;
+        }
+        let bid_hash = auction::bid_key(auction_id, &bid_id);
+
+        if let Some(bytes) = find_value_dht(routing_table, Namespace::Bid, bid_hash).await {
+            if let Ok(private_bid) = PrivateBid::deserialized_from_bytes(&bytes) {
+                if auction_key.is_none() {
+                    auction_key = fetch_auction_key(routing_table, identity, auction_id).await;
+                }
+                bids.extend(auction_key.and_then(|auction_key| private_bid.open(&auction_key)));
+                continue;
             }
-            None => {
-                println!("Block not found for hash {:?}", hex::encode(current_hash));
-                return None; // Early exit if something is broken
+            match auction::bid::Bid::deserialized_from_bytes(&bytes) {
+                Ok(bid) => bids.push(bid),
+                Err(e) => println!("Skipping undecodable bid {}: {}", bid_id, e),
             }
+        } else {
+            println!("Bid {} not found", bid_id);
+        }
+    }
+    bids
+}
+
+// Key of a private auction, unwrapped from the envelope addressed to us
+pub async fn fetch_auction_key(
+    routing_table: &RwLock<RoutingTable>,
+    identity: &Identity,
+    auction_id: &str,
+) -> Option<AuctionKey> {
+    let hash = Namespace::Auction.key(auction_id);
+    let bytes = find_value_dht(routing_table, Namespace::Auction, hash).await?;
+    PrivateAuction::deserialized_from_bytes(&bytes)
+        .ok()?
+        .auction_key(identity)
+}
+
+// Opens a private auction and its bids with a disclosed key and checks them against the chain,
+// reporting the outcome an auditor can rely on
+pub async fn audit_private_auction(
+    routing_table: &RwLock<RoutingTable>,
+    chain: &Chain,
+    auction_id: &str,
+    auction_key: &AuctionKey,
+) -> String {
+    let hash = Namespace::Auction.key(auction_id);
+    let Some(private_auction) = find_value_dht(routing_table, Namespace::Auction, hash)
+        .await
+        .and_then(|bytes| PrivateAuction::deserialized_from_bytes(&bytes).ok())
+    else {
+        return "Private auction not found.".to_string();
+    };
+    let mut private_bids = Vec::new();
+    for bid_id in BidSignature::get_bid_ids(chain, auction_id) {
+        let bid_hash = auction::bid_key(auction_id, &bid_id);
+        if let Some(bytes) = find_value_dht(routing_table, Namespace::Bid, bid_hash).await {
+            private_bids.extend(PrivateBid::deserialized_from_bytes(&bytes).ok());
         }
     }
 
+    let Some((auction, bids)) =
+        auction::private::audit(chain, &private_auction, &private_bids, auction_key)
+    else {
+        return "Wrong key, or the auction isn't committed on the chain.".to_string();
+    };
+    let outcome = match SettlementTransaction::get_valid_settlement(chain, &auction) {
+        Some((settlement, _)) => match (settlement.winner_id, settlement.price_paid) {
+            (Some(winner_id), Some(price_paid)) => {
+                format!("won by {} for {}", hex::encode(winner_id), price_paid)
+            }
+            _ => "settled without a winner".to_string(),
+        },
+        None => "not settled".to_string(),
+    };
+    format!(
+        "{}: {} committed bids, {}",
+        auction.item_name,
+        bids.len(),
+        outcome
+    )
+}
+
+// Fetches the longest chain among the ones the miners we know point to: our own node and the
+// nodes of our routing table
+// Each miner's pointer only moves forward and only they can move it, a pointer whose chain
+// doesn't have the height it claims is skipped, equal heights go to the lowest tip hash
+// The local chain is the last verified one, its blocks aren't held to the clock again
+pub async fn fetch_full_chain(
+    routing_table: &RwLock<RoutingTable>,
+    blockchain: &Mutex<Chain>,
+) -> Option<Chain> {
+    let verified = blockchain.lock().await.clone();
+    let publisher_ids: Vec<Key> = {
+        let routing_table = routing_table.read().await;
+        std::iter::once(routing_table.get_curr_node())
+            .chain(&routing_table.get_all_nodes())
+            .map(|node| *node.get_id())
+            .collect()
+    };
+    let records = futures::future::join_all(
+        publisher_ids
+            .into_iter()
+            .map(|publisher_id| find_record_dht(routing_table, publisher_id, LATEST_BLOCK)),
+    )
+    .await;
+    let mut tips: Vec<(u64, Block)> = records
+        .into_iter()
+        .flatten()
+        .filter_map(|record| Some((record.seq, serde_json::from_slice(&record.value).ok()?)))
+        .collect();
+    tips.sort_by(|(seq, tip), (other_seq, other_tip)| {
+        other_seq
+            .cmp(seq)
+            .then_with(|| tip.get_hash().cmp(&other_tip.get_hash()))
+    });
+    tips.dedup_by(|(_, tip), (_, other_tip)| tip.get_hash() == other_tip.get_hash());
+
+    for (seq, tip) in tips {
+        match fetch_chain_from(routing_table, tip, &verified).await {
+            Some(chain) if chain.get_blocks().len() as u64 == seq => return Some(chain),
+            _ => println!("Skipping a pointer to a broken chain"),
+        }
+    }
+    None
+}
+
+// Fetches the chain ending in the tip, block by block down to the genesis block
+async fn fetch_chain_from(
+    routing_table: &RwLock<RoutingTable>,
+    tip: Block,
+    verified: &Chain,
+) -> Option<Chain> {
+    let mut chain = Chain::new();
+    let mut block = tip;
+    loop {
+        // Add block to the chain
+        chain.add_block(block.clone());
+
+        // If we hit the genesis block, we stop
+        if block.header.get_parent_hash() == vec![0; 64] {
+            println!("Genesis block reached");
+            break;
+        }
+
+        // Fetch the previous block from DHT, checked against its hash
+        let parent_key = block_key(&block.header.get_parent_hash());
+        let Some(bytes) = find_content_dht(routing_table, Namespace::Block, parent_key).await
+        else {
+            println!("Block not found for hash {:?}", hex::encode(parent_key));
+            return None; // Early exit if something is broken
+        };
+        block = serde_json::from_slice(&bytes).ok()?;
+    }
+
+    // Reject chains whose block timestamps break the consensus rules
+    if !chain.verify_timestamps(current_timestamp(), verified) {
+        println!("Chain has invalid block timestamps");
+        return None;
+    }
+
     Some(chain)
 }
@@ -0,0 +1,224 @@
+//! Proxy bidding agents, bidding on behalf of the user in the background
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{Mutex, RwLock};
+
+use crate::auction::Auction;
+use crate::auction::account::Accounts;
+use crate::auction::bid::Bid;
+use crate::auction::lifecycle::LifecycleState;
+use crate::auction::money::Money;
+use crate::auction::proxy::{ProxyBid, ProxyDecision, ProxyLogEntry};
+use crate::auction::record::LedgerMode;
+use crate::blockchain::chain::Chain;
+use crate::identity::Identity;
+use crate::kademlia::routing_table::RoutingTable;
+
+use super::{current_timestamp, fetch_bids, fetch_full_chain, submit_bid};
+
+// How often an agent checks whether it was outbid
+const PROXY_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+// A registered proxy bid, its log, and whether its task is still bidding
+#[derive(Debug, Clone)]
+pub(crate) struct ProxyAgent {
+    pub proxy_bid: ProxyBid,
+    pub log: Vec<ProxyLogEntry>,
+    pub running: bool,
+}
+
+// Agents shared between the GUI and their tasks, they outlive the screens and the window
+pub(crate) type ProxyAgents = Arc<Mutex<Vec<ProxyAgent>>>;
+
+impl ProxyAgent {
+    fn log(&mut self, message: String, placed: Option<(String, Money)>) {
+        println!(
+            "Proxy for auction {}: {}",
+            self.proxy_bid.auction_id, message
+        );
+        let (bid_id, amount) = placed.unzip();
+        self.log.push(ProxyLogEntry {
+            timestamp: current_timestamp(),
+            message,
+            bid_id,
+            amount,
+        });
+    }
+}
+
+// Registers a proxy bid and spawns the task that places its bids
+pub fn start_proxy_agent(
+    agents: ProxyAgents,
+    routing_table: Arc<RwLock<RoutingTable>>,
+    blockchain: Arc<Mutex<Chain>>,
+    identity: Identity,
+    ledger_mode: LedgerMode,
+    auction: Auction,
+    proxy_bid: ProxyBid,
+) {
+    tokio::spawn(async move {
+        let index = {
+            let mut agents = agents.lock().await;
+            // Proxies outbid a single highest bid, lot auctions rank bids per unit instead
+            if auction.has_lots() {
+                let mut agent = ProxyAgent {
+                    proxy_bid,
+                    log: Vec::new(),
+                    running: false,
+                };
+                agent.log(
+                    "Lot auctions don't take proxy bids, not started".to_string(),
+                    None,
+                );
+                agents.push(agent);
+                return;
+            }
+            // A new proxy for the same auction replaces the previous one
+            for agent in agents.iter_mut() {
+                if agent.running && agent.proxy_bid.auction_id == auction.id {
+                    agent.running = false;
+                    agent.log("Replaced by a new proxy bid".to_string(), None);
+                }
+            }
+            let mut agent = ProxyAgent {
+                proxy_bid: proxy_bid.clone(),
+                log: Vec::new(),
+                running: true,
+            };
+            agent.log(
+                format!(
+                    "Started, maximum {} in steps of {}",
+                    proxy_bid.max_amount, proxy_bid.increment
+                ),
+                None,
+            );
+            agents.push(agent);
+            agents.len() - 1
+        };
+        run_proxy_agent(
+            &agents,
+            index,
+            &routing_table,
+            &blockchain,
+            &identity,
+            ledger_mode,
+            &auction,
+        )
+        .await;
+    });
+}
+
+// Outbids other bidders until the auction closes, the cap is reached or the user stops it
+async fn run_proxy_agent(
+    agents: &Mutex<Vec<ProxyAgent>>,
+    index: usize,
+    routing_table: &RwLock<RoutingTable>,
+    blockchain: &Mutex<Chain>,
+    identity: &Identity,
+    ledger_mode: LedgerMode,
+    auction: &Auction,
+) {
+    loop {
+        if !agents.lock().await[index].running {
+            return;
+        }
+
+        if let Some(chain) = fetch_full_chain(routing_table, blockchain).await {
+            let decision = match auction.lifecycle(&chain) {
+                Some(lifecycle) if !lifecycle.is_active() => {
+                    let mut agents = agents.lock().await;
+                    agents[index].log(format!("Auction {}, stopping", lifecycle.state), None);
+                    agents[index].running = false;
+                    return;
+                }
+                Some(lifecycle) if lifecycle.state == LifecycleState::Open => {
+                    let bids = fetch_bids(routing_table, &chain, identity, &auction.id).await;
+                    // Same check as `BidSignature::verify_bids`, with our balance at hand
+                    let accounts = Accounts::replay(&chain, std::slice::from_ref(auction), &bids);
+                    let verified_bids = accounts.get_accepted_bids(&auction.id);
+                    let proxy_bid = agents.lock().await[index].proxy_bid.clone();
+                    match proxy_bid.next_bid(auction, &verified_bids) {
+                        ProxyDecision::Bid(amount)
+                            if amount
+                                > accounts.spendable_for_bid(
+                                    &identity.get_public_key(),
+                                    &auction.id,
+                                    amount.get_currency(),
+                                ) =>
+                        {
+                            let mut agents = agents.lock().await;
+                            agents[index]
+                                .log(format!("Balance too low to bid {}, stopping", amount), None);
+                            agents[index].running = false;
+                            return;
+                        }
+                        decision => Some(decision),
+                    }
+                }
+                // Not recorded on the chain yet, or not open yet
+                _ => None,
+            };
+
+            match decision {
+                Some(ProxyDecision::Bid(amount)) => {
+                    // Same path as a manual bid
+                    let placed = submit_bid(
+                        routing_table,
+                        blockchain,
+                        identity,
+                        ledger_mode,
+                        auction,
+                        Bid::new(auction.id.clone(), identity.get_public_key(), amount),
+                    )
+                    .await;
+                    let mut agents = agents.lock().await;
+                    match placed {
+                        Some(bid) => agents[index]
+                            .log("Outbid, placed a bid".to_string(), Some((bid.id, amount))),
+                        None => agents[index].log(format!("Failed to place {}", amount), None),
+                    }
+                }
+                Some(ProxyDecision::CapReached) => {
+                    let mut agents = agents.lock().await;
+                    agents[index].log("Outbid above the maximum, stopping".to_string(), None);
+                    agents[index].running = false;
+                    return;
+                }
+                Some(ProxyDecision::Leading) | None => {}
+            }
+        }
+
+        tokio::time::sleep(PROXY_POLL_INTERVAL).await;
+    }
+}
+
+// Stops the agents of an auction, their log is kept
+pub async fn stop_proxy_agents(agents: &Mutex<Vec<ProxyAgent>>, auction_id: &str) {
+    for agent in agents.lock().await.iter_mut() {
+        if agent.running && agent.proxy_bid.auction_id == auction_id {
+            agent.running = false;
+            agent.log("Stopped by the user".to_string(), None);
+        }
+    }
+}
+
+// Waits until every agent is done, so closing the window doesn't cut running proxies short
+pub async fn wait_for_proxy_agents(agents: &Mutex<Vec<ProxyAgent>>) {
+    loop {
+        let running = agents
+            .lock()
+            .await
+            .iter()
+            .filter(|agent| agent.running)
+            .count();
+        if running == 0 {
+            return;
+        }
+        println!(
+            "Window closed, waiting for {} proxy agent(s) to finish",
+            running
+        );
+        tokio::time::sleep(PROXY_POLL_INTERVAL).await;
+    }
+}
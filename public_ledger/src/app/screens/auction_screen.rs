@@ -1,28 +1,128 @@
-use egui::Ui;
+use std::collections::HashMap;
+
+use egui::{TextureHandle, Ui};
 
 use crate::auction::Auction;
+use crate::auction::account::{Accounts, MAX_FAUCET_GRANT};
+use crate::auction::attachment::{AttachmentError, AttachmentManifest};
+use crate::auction::index::{self, AuctionQuery};
+use crate::auction::lifecycle::AuctionLifecycle;
+use crate::auction::money::{Currency, Money};
+use crate::auction::private::AuctionKey;
+use crate::auction::reputation::Reputation;
 use crate::auction::signature::AuctionSignature;
+use crate::auction::state::AuctionState;
+use crate::blockchain::chain::Chain;
 
 #[derive(Default)]
 pub struct AuctionScreen {
     auction_list: Vec<Auction>,
-    chain: Option<crate::blockchain::chain::Chain>,
+    chain: Option<Chain>,
+    // Search fields
+    search_keywords: String,
+    search_category: String,
+    search_seller: String,
+    search_ending_within: String,
+    search_status: String,
+    // Attachments fetched so far by content ID, and the images decoded from them
+    attachments: HashMap<String, Result<Vec<u8>, AttachmentError>>,
+    textures: HashMap<String, Option<TextureHandle>>,
+    status: String,
+    // Our public key, and the account section fields
+    user_id: Vec<u8>,
+    account_currency: Currency,
+    transfer_to: String,
+    transfer_amount: String,
+    // Private auction audit, with a key disclosed by the seller or an invitee
+    audit_auction_id: String,
+    audit_key: String,
+    audit_report: String,
+    // Replayed from the chain and the listed auctions, cleared whenever either changes
+    chain_view: Option<ChainView>,
+}
+
+// What the screen shows from the chain, replaying it is too slow to do every frame
+struct ChainView {
+    verified_auctions: Vec<Auction>,
+    // Auctions recorded in full, with their bids and winners, straight from the chain
+    state: AuctionState,
+    accounts: Accounts,
+    reputation: Reputation,
+    // Lifecycles of the listed auctions, by auction ID
+    lifecycles: HashMap<String, AuctionLifecycle>,
+}
+
+impl ChainView {
+    fn replay(chain: &Chain, auctions: &[Auction]) -> ChainView {
+        ChainView {
+            verified_auctions: AuctionSignature::verify_auctions(
+                AuctionSignature::get_signatures(chain),
+                auctions.to_vec(),
+            ),
+            state: AuctionState::replay(chain),
+            accounts: Accounts::replay(chain, auctions, &[]),
+            reputation: Reputation::replay(chain, auctions, &[]),
+            lifecycles: auctions
+                .iter()
+                .filter_map(|auction| Some((auction.id.clone(), auction.lifecycle(chain)?)))
+                .collect(),
+        }
+    }
 }
 
 pub enum AuctionScreenEvent {
     Create,
     Back,
     GetAuctions,
-    BidMenu { auction: Auction },
+    Search {
+        query: AuctionQuery,
+    },
+    LoadAttachment {
+        manifest: AttachmentManifest,
+    },
+    SaveAttachment {
+        manifest: AttachmentManifest,
+    },
+    Faucet {
+        amount: Money,
+    },
+    Transfer {
+        to: Vec<u8>,
+        amount: Money,
+    },
+    Audit {
+        auction_id: String,
+        auction_key: AuctionKey,
+    },
+    BidMenu {
+        auction: Auction,
+    },
 }
 
 impl AuctionScreen {
     pub fn ui(&mut self, ui: &mut Ui) -> Option<AuctionScreenEvent> {
         let mut result = None;
-        let mut verified_auctions = AuctionSignature::verify_auctions(
-            AuctionSignature::get_signatures(self.chain.as_ref().unwrap()),
-            self.auction_list.clone(),
-        );
+        // The chain may still be locked by a fetch or a mine when the screen opens
+        let Some(chain) = self.chain.as_ref() else {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.label("Loading chain...");
+                if ui.button("Back").clicked() {
+                    result = Some(AuctionScreenEvent::Back);
+                }
+            });
+            return result;
+        };
+        let ChainView {
+            verified_auctions,
+            state,
+            accounts,
+            reputation,
+            lifecycles,
+        } = self
+            .chain_view
+            .get_or_insert_with(|| ChainView::replay(chain, &self.auction_list));
+        let now = chrono::Utc::now().timestamp() as u64;
 
         ui.vertical_centered(|ui| {
             ui.add_space(20.0);
@@ -44,50 +144,379 @@ impl AuctionScreen {
                 }
             });
             ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label(format!("Account: {}", hex::encode(&self.user_id)));
+                for currency in Currency::ALL {
+                    ui.label(format!(
+                        "{} available, {} in escrow",
+                        accounts.available(&self.user_id, currency),
+                        accounts.escrowed(&self.user_id, currency)
+                    ));
+                }
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("account_currency")
+                        .selected_text(self.account_currency.code())
+                        .show_ui(ui, |ui| {
+                            for currency in Currency::ALL {
+                                ui.selectable_value(
+                                    &mut self.account_currency,
+                                    currency,
+                                    currency.code(),
+                                );
+                            }
+                        });
+                    // Test networks only, the chain caps every grant
+                    if ui.button("Faucet").clicked() {
+                        result = Some(AuctionScreenEvent::Faucet {
+                            amount: Money::new(MAX_FAUCET_GRANT, self.account_currency),
+                        });
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Transfer To (hex):");
+                    ui.text_edit_singleline(&mut self.transfer_to);
+                    ui.label("Amount:");
+                    ui.text_edit_singleline(&mut self.transfer_amount);
+                    if ui.button("Transfer").clicked() {
+                        let available = accounts.available(&self.user_id, self.account_currency);
+                        match (
+                            hex::decode(self.transfer_to.trim()),
+                            Money::parse(&self.transfer_amount, self.account_currency),
+                        ) {
+                            (Ok(to), Ok(amount)) if amount <= available => {
+                                self.status = "".to_string();
+                                result = Some(AuctionScreenEvent::Transfer { to, amount });
+                            }
+                            (Ok(_), Ok(_)) => {
+                                self.status = "Transfer exceeds your available balance.".to_string()
+                            }
+                            (Err(_), _) => self.status = "Invalid recipient.".to_string(),
+                            (_, Err(e)) => self.status = format!("Invalid amount: {}", e),
+                        }
+                    }
+                });
+            });
+            ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label("Search:");
+                ui.horizontal(|ui| {
+                    ui.label("Keywords:");
+                    ui.text_edit_singleline(&mut self.search_keywords);
+                    ui.label("Category:");
+                    ui.text_edit_singleline(&mut self.search_category);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Seller (hex):");
+                    ui.text_edit_singleline(&mut self.search_seller);
+                    ui.label("Ending Within (Hours):");
+                    ui.text_edit_singleline(&mut self.search_ending_within);
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Search").clicked() {
+                        match build_query(
+                            &self.search_keywords,
+                            &self.search_category,
+                            &self.search_seller,
+                            &self.search_ending_within,
+                        ) {
+                            Ok(query) if query.is_empty() => {
+                                self.search_status = "".to_string();
+                                result = Some(AuctionScreenEvent::GetAuctions);
+                            }
+                            Ok(query) => {
+                                self.search_status = "".to_string();
+                                result = Some(AuctionScreenEvent::Search { query });
+                            }
+                            Err(status) => self.search_status = status,
+                        }
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.search_keywords.clear();
+                        self.search_category.clear();
+                        self.search_seller.clear();
+                        self.search_ending_within.clear();
+                        self.search_status = "".to_string();
+                        result = Some(AuctionScreenEvent::GetAuctions);
+                    }
+                });
+                ui.label(&self.search_status);
+            });
+            ui.add_space(10.0);
+            ui.group(|ui| {
+                ui.label("Audit Private Auction:");
+                ui.horizontal(|ui| {
+                    ui.label("Auction ID:");
+                    ui.text_edit_singleline(&mut self.audit_auction_id);
+                    ui.label("Auction Key (hex):");
+                    ui.text_edit_singleline(&mut self.audit_key);
+                    if ui.button("Audit").clicked() {
+                        match hex::decode(self.audit_key.trim())
+                            .ok()
+                            .and_then(|bytes| AuctionKey::from_bytes(&bytes))
+                        {
+                            Some(auction_key) => {
+                                result = Some(AuctionScreenEvent::Audit {
+                                    auction_id: self.audit_auction_id.trim().to_string(),
+                                    auction_key,
+                                });
+                            }
+                            None => self.audit_report = "Invalid auction key.".to_string(),
+                        }
+                    }
+                });
+                ui.label(&self.audit_report);
+            });
+            ui.add_space(10.0);
             ui.group(|ui| {
                 ui.label("Auctions:");
                 for auction in &self.auction_list {
                     ui.horizontal(|ui| {
-                        ui.label(format!("Auction ID: {}", auction.id));
+                        ui.label(format!(
+                            "Auction ID: {}",
+                            auction.id.get(0..16).unwrap_or(&auction.id)
+                        ));
                         ui.label(format!("Item: {}", auction.item_name));
+                        if auction.is_private() {
+                            ui.colored_label(egui::Color32::LIGHT_BLUE, "Private");
+                        }
+                        ui.label(format!(
+                            "Seller {}",
+                            reputation.get(&auction.seller_id).score()
+                        ))
+                        .on_hover_text(reputation.get(&auction.seller_id).to_string());
                         ui.label(format!("Starting Price: {}", auction.starting_price));
-                        ui.label(format!("End Time: {}", auction.get_ending_time_as_string()));
+                        // Deadline extensions are only known from the chain
+                        let lifecycle = lifecycles.get(&auction.id);
+                        let ending_time = lifecycle
+                            .map_or(auction.ending_time, |lifecycle| lifecycle.ending_time);
+                        ui.label(format!(
+                            "End Time: {}",
+                            Auction::format_timestamp(ending_time)
+                        ));
                         // If auction is in verified_auctions list, show "Verified" else "Not Verified"
                         if verified_auctions.iter().any(|a| a.id == auction.id) {
                             ui.colored_label(egui::Color32::GREEN, "Verified");
-                            // Check i finished
-                            if auction.finished() {
-                                ui.colored_label(egui::Color32::RED, "Finished");
-                                if ui.button("Show").clicked() {
-                                    result = Some(AuctionScreenEvent::BidMenu {
-                                        auction: auction.clone(),
-                                    });
+                            if let Some(projected) = state.get_auction(&auction.id) {
+                                ui.label(format!(
+                                    "Bids: {} valid / {} on-chain",
+                                    projected.valid_bids.len(),
+                                    projected.bids.len()
+                                ));
+                                if let Some(winning_bid) = &projected.winning_bid {
+                                    let label = if projected.lifecycle.is_active() {
+                                        "Leading Bid"
+                                    } else {
+                                        "Winning Bid"
+                                    };
+                                    ui.label(format!("{}: {}", label, winning_bid.amount));
+                                }
+                            }
+                            // State replayed from the chain
+                            match lifecycle {
+                                Some(lifecycle) if lifecycle.is_active() => {
+                                    if lifecycle.awaiting_close(now) {
+                                        ui.colored_label(egui::Color32::YELLOW, "Closing");
+                                    } else {
+                                        ui.colored_label(
+                                            egui::Color32::GREEN,
+                                            lifecycle.state.to_string(),
+                                        );
+                                    }
+                                    // Bid Button
+                                    if ui.button("Bid").clicked() {
+                                        result = Some(AuctionScreenEvent::BidMenu {
+                                            auction: auction.clone(),
+                                        });
+                                    }
                                 }
-                            } else {
-                                ui.colored_label(egui::Color32::GREEN, "Ongoing");
-                                // Bid Button
-                                if ui.button("Bid").clicked() {
-                                    result = Some(AuctionScreenEvent::BidMenu {
-                                        auction: auction.clone(),
-                                    });
+                                Some(lifecycle) => {
+                                    ui.colored_label(
+                                        egui::Color32::RED,
+                                        lifecycle.state.to_string(),
+                                    );
+                                    if ui.button("Show").clicked() {
+                                        result = Some(AuctionScreenEvent::BidMenu {
+                                            auction: auction.clone(),
+                                        });
+                                    }
                                 }
+                                None => {}
                             }
                         } else {
                             ui.colored_label(egui::Color32::RED, "Not Verified");
                         }
                     });
+                    for manifest in &auction.attachments {
+                        if let Some(event) =
+                            attachment_ui(ui, manifest, &self.attachments, &mut self.textures)
+                        {
+                            result = Some(event);
+                        }
+                    }
                 }
             });
+            // Best reputations among the sellers and bidders of the listed auctions
+            let ranking = reputation.ranking();
+            if !ranking.is_empty() {
+                ui.group(|ui| {
+                    ui.label("Top Reputations:");
+                    for (identity, stats) in ranking.iter().take(5) {
+                        ui.label(format!(
+                            "{} - {}",
+                            hex::encode(identity.get(0..8).unwrap_or(identity)),
+                            stats
+                        ));
+                    }
+                });
+            }
+            ui.label(&self.status);
         });
 
         result
     }
 
+    pub fn has_attachment(&self, content_id: &str) -> bool {
+        self.attachments.contains_key(content_id)
+    }
+
+    pub fn set_attachment(
+        &mut self,
+        content_id: String,
+        attachment: Result<Vec<u8>, AttachmentError>,
+    ) {
+        self.textures.remove(&content_id);
+        self.attachments.insert(content_id, attachment);
+    }
+
+    pub fn set_user_id(&mut self, user_id: Vec<u8>) {
+        self.user_id = user_id;
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = status;
+    }
+
+    pub fn set_audit_report(&mut self, audit_report: String) {
+        self.audit_report = audit_report;
+    }
+
     pub fn refresh_auctions(&mut self, auctions: Vec<Auction>) {
+        let changed = auctions.len() != self.auction_list.len()
+            || auctions
+                .iter()
+                .zip(&self.auction_list)
+                .any(|(auction, listed)| auction.get_hash() != listed.get_hash());
+        if changed {
+            self.chain_view = None;
+        }
         self.auction_list = auctions;
     }
 
-    pub fn set_chain(&mut self, chain: crate::blockchain::chain::Chain) {
+    pub fn set_chain(&mut self, chain: Chain) {
+        if self
+            .chain
+            .as_ref()
+            .is_none_or(|current| !current.has_same_tip(&chain))
+        {
+            self.chain_view = None;
+        }
         self.chain = Some(chain);
     }
 }
+
+// Query from the search fields, empty fields are left out
+fn build_query(
+    keywords: &str,
+    category: &str,
+    seller: &str,
+    ending_within: &str,
+) -> Result<AuctionQuery, String> {
+    let category = category.trim();
+    let seller_id = match seller.trim() {
+        "" => None,
+        seller => Some(hex::decode(seller).map_err(|_| "Invalid seller ID.".to_string())?),
+    };
+    let ending_between = match ending_within.trim() {
+        "" => None,
+        hours => {
+            let max_hours = index::MAX_ENDING_HOURS;
+            let hours = hours
+                .parse::<u64>()
+                .ok()
+                .filter(|hours| (1..=max_hours).contains(hours))
+                .ok_or_else(|| format!("Enter between 1 and {} hours.", max_hours))?;
+            let now = chrono::Utc::now().timestamp() as u64;
+            Some((now, now.saturating_add(hours * 3600)))
+        }
+    };
+    Ok(AuctionQuery {
+        keywords: index::keywords(keywords),
+        category: (!category.is_empty()).then(|| category.to_string()),
+        seller_id,
+        ending_between,
+    })
+}
+
+// One attachment of an auction: images are shown once loaded, other files can be saved
+fn attachment_ui(
+    ui: &mut Ui,
+    manifest: &AttachmentManifest,
+    attachments: &HashMap<String, Result<Vec<u8>, AttachmentError>>,
+    textures: &mut HashMap<String, Option<TextureHandle>>,
+) -> Option<AuctionScreenEvent> {
+    let mut result = None;
+    let content_id = manifest.content_id();
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "Attachment: {} ({} KiB)",
+            manifest.file_name,
+            manifest.size.div_ceil(1024)
+        ));
+        match attachments.get(&content_id) {
+            None => {
+                if ui.button("Load").clicked() {
+                    result = Some(AuctionScreenEvent::LoadAttachment {
+                        manifest: manifest.clone(),
+                    });
+                }
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e.to_string());
+                if ui.button("Retry").clicked() {
+                    result = Some(AuctionScreenEvent::LoadAttachment {
+                        manifest: manifest.clone(),
+                    });
+                }
+            }
+            Some(Ok(bytes)) => {
+                if ui.button("Save").clicked() {
+                    result = Some(AuctionScreenEvent::SaveAttachment {
+                        manifest: manifest.clone(),
+                    });
+                }
+                if manifest.is_image() {
+                    // Decoded once, None if the format can't be previewed
+                    let texture = textures.entry(content_id.clone()).or_insert_with(|| {
+                        let image = image::load_from_memory(bytes).ok()?.to_rgba8();
+                        let size = [image.width() as usize, image.height() as usize];
+                        let image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+                        Some(ui.ctx().load_texture(
+                            content_id.clone(),
+                            image,
+                            egui::TextureOptions::default(),
+                        ))
+                    });
+                    match texture {
+                        Some(texture) => {
+                            ui.add(egui::Image::new(&*texture).max_height(120.0));
+                        }
+                        None => {
+                            ui.label("Preview unavailable");
+                        }
+                    }
+                }
+            }
+        }
+    });
+    result
+}
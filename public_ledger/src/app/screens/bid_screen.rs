@@ -2,33 +2,122 @@ use std::collections::HashSet;
 
 use egui::Ui;
 
-use crate::auction;
+use crate::app::proxy_agent::ProxyAgent;
+use crate::auction::Auction;
+use crate::auction::account::Accounts;
+use crate::auction::bid::Bid;
+use crate::auction::lifecycle::{
+    AuctionLifecycle, LifecycleState, MAX_DEADLINE_EXTENSIONS, MAX_TOTAL_EXTENSION,
+};
+use crate::auction::lot::LotResult;
+use crate::auction::money::Money;
+use crate::auction::reputation::{FeedbackRating, Reputation};
+use crate::auction::seller::SellerAction;
+use crate::auction::settlement::SettlementTransaction;
 use crate::auction::signature::BidSignature;
+use crate::blockchain::chain::{Chain, Confirmation};
 
 #[derive(Default)]
 pub struct BidScreen {
     curr_auction: Option<crate::auction::Auction>,
     bid_amount: String,
+    // Lot and number of units to bid for, on lot auctions
+    bid_lot: u32,
+    bid_quantity: String,
     status: String,
     bids: Vec<crate::auction::bid::Bid>,
     chain: Option<crate::blockchain::chain::Chain>,
     toggle_valid: bool,
+    // Our public key, seller actions are only offered on our own auctions
+    user_id: Vec<u8>,
+    amended_description: String,
+    extension_hours: String,
+    proxy_max_amount: String,
+    proxy_increment: String,
+    proxy_agents: Vec<ProxyAgent>,
+    feedback_rating: FeedbackRating,
+    feedback_comment: String,
+    // Replayed from the chain, the auction and its bids, cleared whenever any of them changes
+    chain_view: Option<ChainView>,
+}
+
+// What the screen shows from the chain, replaying it is too slow to do every frame
+struct ChainView {
+    // Same bids as `BidSignature::verify_bids`, with the balances they were checked against
+    accounts: Accounts,
+    verified_bids: Vec<Bid>,
+    reputation: Reputation,
+    // Where the auction stands, None until it is recorded on the chain
+    lifecycle: Option<AuctionLifecycle>,
+    settlement: Option<(SettlementTransaction, Confirmation)>,
+}
+
+impl ChainView {
+    fn replay(chain: &Chain, auction: &Auction, bids: &[Bid]) -> ChainView {
+        let accounts = Accounts::replay(chain, std::slice::from_ref(auction), bids);
+        ChainView {
+            verified_bids: accounts.get_accepted_bids(&auction.id),
+            accounts,
+            reputation: Reputation::replay(chain, std::slice::from_ref(auction), bids),
+            lifecycle: AuctionLifecycle::replay(chain, auction)
+                .map(|lifecycle| lifecycle.with_settlement(chain, auction)),
+            settlement: SettlementTransaction::get_valid_settlement(chain, auction),
+        }
+    }
 }
 
 pub enum BidScreenEvent {
     GetBids,
-    SubmitBid { amount: u64 },
+    SubmitBid {
+        amount: Money,
+        lot_index: u32,
+        quantity: u32,
+    },
+    Settle,
+    ExportCertificate,
+    SellerAction {
+        action: SellerAction,
+    },
+    StartProxy {
+        max_amount: Money,
+        increment: Money,
+    },
+    StopProxy,
+    DiscloseKey,
+    PostFeedback {
+        to: Vec<u8>,
+        rating: FeedbackRating,
+        comment: String,
+    },
     Back,
 }
 
 impl BidScreen {
     pub fn ui(&mut self, ui: &mut Ui) -> Option<BidScreenEvent> {
         let mut result = None;
-        let mut verified_bids = BidSignature::verify_bids(
-            BidSignature::get_signatures(self.chain.as_ref().unwrap()),
-            self.bids.clone(),
-            self.curr_auction.as_ref().unwrap().clone(),
-        );
+        // The chain may still be locked by a fetch or a mine when the screen opens
+        let Some(chain) = self.chain.as_ref() else {
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.label("Loading chain...");
+                if ui.button("Back").clicked() {
+                    result = Some(BidScreenEvent::Back);
+                }
+            });
+            return result;
+        };
+        let auction = self.curr_auction.as_ref().unwrap();
+        let ChainView {
+            accounts,
+            verified_bids,
+            reputation,
+            lifecycle,
+            settlement,
+        } = self
+            .chain_view
+            .get_or_insert_with(|| ChainView::replay(chain, auction, &self.bids));
+        let verified_bids = verified_bids.clone();
+        let now = chrono::Utc::now().timestamp() as u64;
 
         ui.vertical_centered(|ui| {
             ui.add_space(20.0);
@@ -36,59 +125,424 @@ impl BidScreen {
             ui.add_space(10.0);
             // Auction Info
             if let Some(auction) = &self.curr_auction {
-                ui.label(format!("Auction ID: {}", auction.id));
+                ui.label(format!(
+                    "Auction ID: {}",
+                    auction.id.get(0..16).unwrap_or(&auction.id)
+                ));
                 ui.label(format!("Item: {}", auction.item_name));
+                ui.label(format!(
+                    "Seller: {} - {}",
+                    hex::encode(&auction.seller_id),
+                    reputation.get(&auction.seller_id)
+                ));
                 ui.label(format!("Starting Price: {}", auction.starting_price));
-                ui.label(format!("End Time: {}", auction.get_ending_time_as_string()));
-                // Check if finished
-                if auction.finished() {
-                    ui.colored_label(egui::Color32::RED, "Finished");
-                    let winning_bid = BidSignature::winning_bid(verified_bids.clone());
-                    if let Some(winning_bid) = winning_bid {
+                for (index, lot) in auction.lots.iter().enumerate() {
+                    ui.label(format!("Lot {}: {} x {}", index, lot.name, lot.quantity));
+                }
+                if auction.is_private() {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(
+                            egui::Color32::LIGHT_BLUE,
+                            format!("Private, {} invited bidders", auction.invitees.len()),
+                        );
+                        // Handing the key to an auditor lets them check the outcome
+                        if auction.seller_id == self.user_id
+                            && ui.button("Copy Auction Key").clicked()
+                        {
+                            result = Some(BidScreenEvent::DiscloseKey);
+                        }
+                    });
+                }
+
+                let Some(lifecycle) = &lifecycle else {
+                    ui.colored_label(egui::Color32::YELLOW, "Pending");
+                    ui.label("Waiting for the auction to be recorded on the chain.");
+                    if ui.button("Back").clicked() {
+                        result = Some(BidScreenEvent::Back);
+                    }
+                    return;
+                };
+                if !lifecycle.description.is_empty() {
+                    ui.label(format!("Description: {}", lifecycle.description));
+                }
+                ui.label(format!(
+                    "End Time: {}",
+                    Auction::format_timestamp(lifecycle.ending_time)
+                ));
+                let state_color = match lifecycle.state {
+                    LifecycleState::Scheduled => egui::Color32::YELLOW,
+                    LifecycleState::Open | LifecycleState::Settled => egui::Color32::GREEN,
+                    LifecycleState::Closed => egui::Color32::RED,
+                    LifecycleState::Cancelled | LifecycleState::Lapsed => egui::Color32::GRAY,
+                };
+                ui.colored_label(state_color, lifecycle.state.to_string());
+
+                // Every transition, with the block that caused it
+                ui.group(|ui| {
+                    ui.label("Lifecycle:");
+                    for transition in &lifecycle.transitions {
                         ui.label(format!(
-                            "Winning Bidder: {}",
-                            hex::encode(winning_bid.bidder_id)
+                            "{} - block {} (height {})",
+                            transition.event,
+                            hex::encode(&transition.confirmation.block_hash[0..10]),
+                            transition.confirmation.height
                         ));
-                        ui.label(format!("Winning Amount: {}", winning_bid.amount));
-                    } else {
-                        ui.label("No winner");
                     }
+                });
+
+                // Proxy agents of this auction and the bids they placed
+                let proxy_agents: Vec<&ProxyAgent> = self
+                    .proxy_agents
+                    .iter()
+                    .filter(|agent| agent.proxy_bid.auction_id == auction.id)
+                    .collect();
+                if !proxy_agents.is_empty() {
+                    ui.group(|ui| {
+                        ui.label("Proxy Log:");
+                        for agent in &proxy_agents {
+                            for entry in &agent.log {
+                                let placed = match (&entry.bid_id, &entry.amount) {
+                                    (Some(bid_id), Some(amount)) => format!(
+                                        " (bid {} for {})",
+                                        bid_id.get(0..16).unwrap_or(bid_id),
+                                        amount
+                                    ),
+                                    _ => "".to_string(),
+                                };
+                                ui.label(format!(
+                                    "{} - {}{}",
+                                    Auction::format_timestamp(entry.timestamp),
+                                    entry.message,
+                                    placed
+                                ));
+                            }
+                        }
+                        if proxy_agents.iter().any(|agent| agent.running)
+                            && ui.button("Stop Proxy").clicked()
+                        {
+                            result = Some(BidScreenEvent::StopProxy);
+                        }
+                    });
+                }
+
+                // Check if finished, according to the chain
+                if lifecycle.state == LifecycleState::Cancelled {
+                    ui.label("The seller cancelled this auction before any bid.");
+                } else if lifecycle.state == LifecycleState::Lapsed {
+                    ui.label("Nobody settled this auction in time, the escrows went back to the bidders.");
+                } else if !lifecycle.is_active() || lifecycle.awaiting_close(now) {
+                    if lifecycle.is_active() {
+                        // Bids are no longer accepted, the outcome is final once the chain's median time passes the end time
+                        ui.colored_label(egui::Color32::YELLOW, "Closing");
+                        ui.label("Waiting for the chain to pass the end time to close the auction.");
+                    }
+                    // Once settled, show the outcome recorded on the chain instead of recomputing it
+                    match settlement {
+                        Some((settlement, confirmation)) => {
+                            ui.label(format!(
+                                "Settlement Block: {} (height {})",
+                                hex::encode(&confirmation.block_hash[0..10]),
+                                confirmation.height
+                            ));
+                            match (&settlement.winner_id, &settlement.price_paid) {
+                                _ if auction.has_lots() => {
+                                    lot_results_ui(ui, auction, &settlement.lot_results);
+                                }
+                                (Some(winner_id), Some(price_paid)) => {
+                                    ui.label(format!("Winning Bidder: {}", hex::encode(winner_id)));
+                                    ui.label(format!("Price Paid: {}", price_paid));
+                                }
+                                _ => {
+                                    ui.label("No winner");
+                                }
+                            }
+                            ui.label(format!("Rule Set: {}", settlement.rule_set));
+                            if ui.button("Export Winner Certificate").clicked() {
+                                result = Some(BidScreenEvent::ExportCertificate);
+                            }
+
+                            // Feedback Section, between the seller and the winners who paid
+                            let winner_ids = accounts
+                                .get_settled(&auction.id)
+                                .map(|settled| settled.winner_ids())
+                                .unwrap_or_default();
+                            let counterparties = if self.user_id == auction.seller_id {
+                                winner_ids
+                            } else if winner_ids.contains(&self.user_id) {
+                                vec![auction.seller_id.clone()]
+                            } else {
+                                Vec::new()
+                            };
+                            if !counterparties.is_empty() {
+                                ui.group(|ui| {
+                                    ui.label("Feedback:");
+                                    ui.horizontal(|ui| {
+                                        egui::ComboBox::from_id_salt("feedback_rating")
+                                            .selected_text(self.feedback_rating.to_string())
+                                            .show_ui(ui, |ui| {
+                                                for rating in [
+                                                    FeedbackRating::Positive,
+                                                    FeedbackRating::Neutral,
+                                                    FeedbackRating::Negative,
+                                                ] {
+                                                    ui.selectable_value(
+                                                        &mut self.feedback_rating,
+                                                        rating,
+                                                        rating.to_string(),
+                                                    );
+                                                }
+                                            });
+                                        ui.text_edit_singleline(&mut self.feedback_comment);
+                                    });
+                                    for to in counterparties {
+                                        let label = format!(
+                                            "Post Feedback to {}",
+                                            hex::encode(&to[0..8])
+                                        );
+                                        if ui.button(label).clicked() {
+                                            result = Some(BidScreenEvent::PostFeedback {
+                                                to,
+                                                rating: self.feedback_rating,
+                                                comment: self.feedback_comment.clone(),
+                                            });
+                                        }
+                                    }
+                                });
+                            }
+                        }
+                        None => {
+                            let winning_bid = BidSignature::winning_bid(verified_bids.clone());
+                            if auction.has_lots() {
+                                ui.label("Provisional Results:");
+                                lot_results_ui(ui, auction, &accounts.get_lot_results(auction));
+                            } else if let Some(winning_bid) = winning_bid {
+                                ui.label(format!(
+                                    "Provisional Winning Bidder: {}",
+                                    hex::encode(winning_bid.bidder_id)
+                                ));
+                                ui.label(format!(
+                                    "Provisional Winning Amount: {}",
+                                    winning_bid.amount
+                                ));
+                            } else {
+                                ui.label("No winner");
+                            }
+                            if ui.button("Settle Auction").clicked() {
+                                result = Some(BidScreenEvent::Settle);
+                            }
+                        }
+                    }
+                    ui.label(&self.status);
                 } else {
-                    ui.colored_label(egui::Color32::GREEN, "Ongoing");
                     let winning_bid = BidSignature::winning_bid(verified_bids.clone());
-                    if let Some(winning_bid) = winning_bid {
+                    if auction.has_lots() {
+                        ui.label("Current Results:");
+                        lot_results_ui(ui, auction, &accounts.get_lot_results(auction));
+                    } else if let Some(winning_bid) = winning_bid {
                         ui.label(format!(
                             "Current Winning Bidder: {}",
                             hex::encode(winning_bid.bidder_id)
                         ));
                         ui.label(format!("Current Winning Amount: {}", winning_bid.amount));
                     }
-                    // Bid Section
-                    ui.group(|ui| {
-                        ui.label("Bid:");
-                        ui.horizontal(|ui| {
-                            ui.label("Enter your bid amount:");
-                            ui.text_edit_singleline(&mut self.bid_amount);
-                            if ui.button("Submit Bid").clicked() {
-                                if let Ok(amount) = self.bid_amount.parse::<u64>() {
-                                    if amount
-                                        > BidSignature::winning_bid(verified_bids.clone())
-                                            .map_or(0, |bid| bid.amount as u64)
-                                    {
-                                        self.status = "".to_string();
-                                        result = Some(BidScreenEvent::SubmitBid { amount });
-                                    } else {
-                                        self.status =
-                                            "Bid amount must be greater than current winning bid."
-                                                .to_string();
+                    let escrows = accounts.get_escrows(&auction.id);
+                    if !escrows.is_empty() {
+                        let locked: u64 = escrows
+                            .iter()
+                            .map(|escrow| escrow.amount.get_minor_units())
+                            .sum();
+                        ui.label(format!(
+                            "Locked in Escrow: {}",
+                            Money::new(locked, auction.starting_price.get_currency())
+                        ));
+                    }
+                    if !auction.accepts_bidder(&self.user_id) {
+                        ui.label("Only invited bidders can bid on this auction.");
+                    } else if lifecycle.state == LifecycleState::Scheduled {
+                        ui.label(format!(
+                            "Bidding opens at {}",
+                            Auction::format_timestamp(auction.starting_time)
+                        ));
+                    } else {
+                        // Bid Section
+                        let currency = auction.starting_price.get_currency();
+                        let spendable =
+                            accounts.spendable_for_bid(&self.user_id, &auction.id, currency);
+                        ui.group(|ui| {
+                            ui.label("Bid:");
+                            ui.label(format!("You can bid up to {}", spendable));
+                            // Lot auctions take a price per unit for some units of one lot
+                            let lots = auction.get_lots();
+                            if auction.has_lots() {
+                                ui.horizontal(|ui| {
+                                    ui.label("Lot:");
+                                    egui::ComboBox::from_id_salt("bid_lot")
+                                        .selected_text(self.bid_lot.to_string())
+                                        .show_ui(ui, |ui| {
+                                            for (index, lot) in lots.iter().enumerate() {
+                                                ui.selectable_value(
+                                                    &mut self.bid_lot,
+                                                    index as u32,
+                                                    format!("{}: {}", index, lot.name),
+                                                );
+                                            }
+                                        });
+                                    ui.label("Units (empty for one):");
+                                    ui.text_edit_singleline(&mut self.bid_quantity);
+                                });
+                            }
+                            ui.horizontal(|ui| {
+                                match auction.has_lots() {
+                                    true => ui.label("Enter your price per unit:"),
+                                    false => ui.label("Enter your bid amount:"),
+                                };
+                                ui.text_edit_singleline(&mut self.bid_amount);
+                                if ui.button("Submit Bid").clicked() {
+                                    let quantity = match self.bid_quantity.trim() {
+                                        "" => Ok(1),
+                                        quantity => quantity.parse::<u32>(),
+                                    };
+                                    let lot_quantity = lots
+                                        .get(self.bid_lot as usize)
+                                        .map_or(0, |lot| lot.quantity);
+                                    match (Money::parse(&self.bid_amount, currency), quantity) {
+                                        (Ok(amount), Ok(quantity))
+                                            if quantity > 0 && quantity <= lot_quantity =>
+                                        {
+                                            let current = match auction.has_lots() {
+                                                true => Money::zero(currency),
+                                                false => {
+                                                    BidSignature::winning_bid(verified_bids.clone())
+                                                        .map_or(Money::zero(currency), |bid| {
+                                                            bid.amount
+                                                        })
+                                                }
+                                            };
+                                            let cost = amount.checked_mul(u64::from(quantity));
+                                            if cost.is_ok_and(|cost| cost > spendable) {
+                                                self.status =
+                                                    "Bid amount exceeds your available balance."
+                                                        .to_string();
+                                            } else if amount > current {
+                                                self.status = "".to_string();
+                                                result = Some(BidScreenEvent::SubmitBid {
+                                                    amount,
+                                                    lot_index: self.bid_lot,
+                                                    quantity,
+                                                });
+                                            } else {
+                                                self.status =
+                                                    "Bid amount must be greater than current winning bid."
+                                                        .to_string();
+                                            }
+                                        }
+                                        (Ok(_), _) => {
+                                            self.status = format!(
+                                                "Bid for 1 to {} units of the lot.",
+                                                lot_quantity
+                                            );
+                                        }
+                                        (Err(_), _) => {
+                                            self.status = "Invalid bid amount.".to_string();
+                                        }
                                     }
-                                } else {
-                                    self.status = "Invalid bid amount.".to_string();
                                 }
+                            });
+                        });
+
+                        // Proxy Section, bids for us up to a maximum even after leaving this screen
+                        // Proxies follow the single highest bid, so they only bid on single items
+                        if !auction.has_lots() {
+                            ui.group(|ui| {
+                                ui.label("Proxy Bidding:");
+                                ui.horizontal(|ui| {
+                                    ui.label("Maximum:");
+                                    ui.text_edit_singleline(&mut self.proxy_max_amount);
+                                    ui.label("Increment:");
+                                    ui.text_edit_singleline(&mut self.proxy_increment);
+                                    if ui.button("Start Proxy").clicked() {
+                                        let currency = auction.starting_price.get_currency();
+                                        match (
+                                            Money::parse(&self.proxy_max_amount, currency),
+                                            Money::parse(&self.proxy_increment, currency),
+                                        ) {
+                                            (Ok(max_amount), Ok(increment))
+                                                if increment.get_minor_units() > 0 =>
+                                            {
+                                                self.status = "".to_string();
+                                                result = Some(BidScreenEvent::StartProxy {
+                                                    max_amount,
+                                                    increment,
+                                                });
+                                            }
+                                            _ => {
+                                                self.status =
+                                                    "Invalid proxy maximum or increment.".to_string()
+                                            }
+                                        }
+                                    }
+                                });
+                            });
+                        }
+                    }
+
+                    // Seller Section, the chain only accepts these when signed with the seller's key
+                    if auction.seller_id == self.user_id {
+                        ui.group(|ui| {
+                            ui.label("Seller Actions:");
+                            // Amending is only allowed before the first bid
+                            if !lifecycle.has_bids {
+                                ui.horizontal(|ui| {
+                                    ui.text_edit_singleline(&mut self.amended_description);
+                                    if ui.button("Amend Description").clicked() {
+                                        result = Some(BidScreenEvent::SellerAction {
+                                            action: SellerAction::AmendDescription {
+                                                description: self.amended_description.clone(),
+                                            },
+                                        });
+                                    }
+                                });
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label("Extend by (Hours):");
+                                ui.text_edit_singleline(&mut self.extension_hours);
+                                if ui.button("Extend Deadline").clicked() {
+                                    match self.extension_hours.parse::<u64>() {
+                                        Ok(hours) if hours > 0 => {
+                                            let new_ending_time =
+                                                lifecycle.ending_time + hours * 3600;
+                                            if lifecycle.extensions >= MAX_DEADLINE_EXTENSIONS
+                                                || new_ending_time - auction.ending_time
+                                                    > MAX_TOTAL_EXTENSION
+                                            {
+                                                self.status = format!(
+                                                    "At most {} extensions, up to {} days in total.",
+                                                    MAX_DEADLINE_EXTENSIONS,
+                                                    MAX_TOTAL_EXTENSION / (24 * 3600)
+                                                );
+                                            } else {
+                                                self.status = "".to_string();
+                                                result = Some(BidScreenEvent::SellerAction {
+                                                    action: SellerAction::ExtendDeadline {
+                                                        new_ending_time,
+                                                    },
+                                                });
+                                            }
+                                        }
+                                        _ => self.status = "Invalid extension.".to_string(),
+                                    }
+                                }
+                            });
+                            // Cancelling is only allowed before the first bid
+                            if !lifecycle.has_bids && ui.button("Cancel Auction").clicked()
+                            {
+                                result = Some(BidScreenEvent::SellerAction {
+                                    action: SellerAction::Cancel,
+                                });
                             }
-                            ui.label(&self.status);
                         });
-                    });
+                    }
+                    ui.label(&self.status);
                 }
                 ui.add_space(10.0);
                 // Get bids
@@ -105,28 +559,32 @@ impl BidScreen {
                     ui.label("Valid Bids:");
                     for bid in verified_bids {
                         ui.horizontal(|ui| {
-                            ui.label(format!("Bidder: {}", hex::encode(bid.bidder_id)));
-                            ui.label(format!("Amount: {}", bid.amount));
+                            ui.label(format!("Bidder: {}", hex::encode(&bid.bidder_id)));
+                            ui.label(format!("Amount: {}", bid_amount_label(auction, &bid)));
+                            ui.label(reputation.get(&bid.bidder_id).to_string());
                         });
                     }
                 } else {
                     ui.label("All Bids:");
 
                     // Create a HashSet of verified bid IDs for quick lookup
-                    let verified_bid_ids: HashSet<_> = verified_bids.iter().map(|b| b.id).collect();
+                    let verified_bid_ids: HashSet<_> =
+                        verified_bids.iter().map(|b| &b.id).collect();
 
                     for bid in self.bids.iter() {
                         // Check if this bid's ID is in the verified bid IDs set
                         if verified_bid_ids.contains(&bid.id) {
                             ui.horizontal(|ui| {
                                 ui.label(format!("Bidder: {}", hex::encode(bid.bidder_id.clone())));
-                                ui.label(format!("Amount: {}", bid.amount));
+                                ui.label(format!("Amount: {}", bid_amount_label(auction, bid)));
+                                ui.label(reputation.get(&bid.bidder_id).to_string());
                                 ui.colored_label(egui::Color32::GREEN, "Valid");
                             });
                         } else {
                             ui.horizontal(|ui| {
                                 ui.label(format!("Bidder: {}", hex::encode(bid.bidder_id.clone())));
-                                ui.label(format!("Amount: {}", bid.amount));
+                                ui.label(format!("Amount: {}", bid_amount_label(auction, bid)));
+                                ui.label(reputation.get(&bid.bidder_id).to_string());
                                 ui.colored_label(egui::Color32::RED, "Not Valid");
                             });
                         }
@@ -147,6 +605,7 @@ impl BidScreen {
     }
 
     pub fn set_auction(&mut self, auction: crate::auction::Auction) {
+        self.chain_view = None;
         self.curr_auction = Some(auction);
     }
 
@@ -155,10 +614,73 @@ impl BidScreen {
     }
 
     pub fn set_bids(&mut self, bids: Vec<crate::auction::bid::Bid>) {
+        let changed = bids.len() != self.bids.len()
+            || bids
+                .iter()
+                .zip(&self.bids)
+                .any(|(bid, listed)| bid.get_hash() != listed.get_hash());
+        if changed {
+            self.chain_view = None;
+        }
         self.bids = bids;
     }
 
+    pub fn get_bids(&self) -> &Vec<crate::auction::bid::Bid> {
+        &self.bids
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = status;
+    }
+
     pub fn set_chain(&mut self, chain: crate::blockchain::chain::Chain) {
+        if self
+            .chain
+            .as_ref()
+            .is_none_or(|current| !current.has_same_tip(&chain))
+        {
+            self.chain_view = None;
+        }
         self.chain = Some(chain);
     }
+
+    pub fn set_user_id(&mut self, user_id: Vec<u8>) {
+        self.user_id = user_id;
+    }
+
+    pub fn set_proxy_agents(&mut self, proxy_agents: Vec<ProxyAgent>) {
+        self.proxy_agents = proxy_agents;
+    }
+}
+
+// Amount of a bid, with its units and lot on a lot auction
+fn bid_amount_label(auction: &Auction, bid: &Bid) -> String {
+    match auction.has_lots() {
+        true => format!("{} x {} (lot {})", bid.quantity, bid.amount, bid.lot_index),
+        false => bid.amount.to_string(),
+    }
+}
+
+// Units of each lot, who gets them and the uniform price they pay
+fn lot_results_ui(ui: &mut Ui, auction: &Auction, lot_results: &[LotResult]) {
+    let lots = auction.get_lots();
+    for result in lot_results {
+        let name = lots
+            .get(result.lot_index as usize)
+            .map_or("", |lot| lot.name.as_str());
+        match result.clearing_price {
+            Some(price) => ui.label(format!(
+                "Lot {} ({}): clears at {} per unit",
+                result.lot_index, name, price
+            )),
+            None => ui.label(format!("Lot {} ({}): no bids", result.lot_index, name)),
+        };
+        for fill in &result.fills {
+            ui.label(format!(
+                "{} units to {}",
+                fill.quantity,
+                hex::encode(&fill.winner_id)
+            ));
+        }
+    }
 }
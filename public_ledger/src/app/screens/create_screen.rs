@@ -1,14 +1,39 @@
+use std::path::Path;
+
 use egui::Ui;
 
+use crate::auction::attachment::Attachment;
+use crate::auction::lot::Lot;
+use crate::auction::money::{Currency, Money};
+use crate::identity;
+
 #[derive(Default)]
 pub struct CreateScreen {
     item_name: String,
+    description: String,
+    category: String,
+    attachments: String,
+    invitees: String,
+    lots: String,
     starting_price: String,
+    currency: Currency,
+    start_delay: String,
     duration: String,
+    status: String,
 }
 
 pub enum CreateScreenEvent {
-    Submitted(String, f64, u64),
+    Submitted {
+        item_name: String,
+        description: String,
+        category: String,
+        attachments: Vec<Attachment>,
+        invitees: Vec<Vec<u8>>,
+        lots: Vec<Lot>,
+        starting_price: Money,
+        start_delay_hours: u64,
+        duration_hours: u64,
+    },
     Back,
 }
 
@@ -25,8 +50,41 @@ impl CreateScreen {
             ui.text_edit_singleline(&mut self.item_name);
             ui.add_space(10.0);
 
+            ui.label("Description:");
+            ui.text_edit_multiline(&mut self.description);
+            ui.add_space(10.0);
+
+            ui.label("Category:");
+            ui.text_edit_singleline(&mut self.category);
+            ui.add_space(10.0);
+
+            ui.label("Attachments (File Paths, One per Line):");
+            ui.text_edit_multiline(&mut self.attachments);
+            ui.add_space(10.0);
+
+            ui.label("Invited Bidders (Public Keys in Hex, One per Line, Empty for Public):");
+            ui.text_edit_multiline(&mut self.invitees);
+            ui.add_space(10.0);
+
+            ui.label("Lots (Name x Units, One per Line, Empty for a Single Item):");
+            ui.text_edit_multiline(&mut self.lots);
+            ui.add_space(10.0);
+
             ui.label("Starting Price:");
-            ui.text_edit_singleline(&mut self.starting_price);
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.starting_price);
+                egui::ComboBox::from_id_salt("currency")
+                    .selected_text(self.currency.code())
+                    .show_ui(ui, |ui| {
+                        for currency in Currency::ALL {
+                            ui.selectable_value(&mut self.currency, currency, currency.code());
+                        }
+                    });
+            });
+            ui.add_space(10.0);
+
+            ui.label("Starts In (Hours, empty to start now):");
+            ui.text_edit_singleline(&mut self.start_delay);
             ui.add_space(10.0);
 
             ui.label("Duration (Hours):");
@@ -38,20 +96,108 @@ impl CreateScreen {
                     result = Some(CreateScreenEvent::Back);
                 }
                 if ui.button("Submit").clicked() {
-                    if let (Ok(price), Ok(time)) = (
-                        self.starting_price.parse::<f64>(),
+                    let start_delay = match self.start_delay.trim() {
+                        "" => Ok(0),
+                        delay => delay.parse::<u64>(),
+                    };
+                    let attachments: Result<Vec<Attachment>, String> = self
+                        .attachments
+                        .lines()
+                        .map(str::trim)
+                        .filter(|path| !path.is_empty())
+                        .map(|path| {
+                            Attachment::from_file(Path::new(path))
+                                .map_err(|e| format!("{}: {}", path, e))
+                        })
+                        .collect();
+                    // Auction keys are encrypted to the invitees, so their keys must be valid
+                    let invitees: Result<Vec<Vec<u8>>, String> = self
+                        .invitees
+                        .lines()
+                        .map(str::trim)
+                        .filter(|invitee| !invitee.is_empty())
+                        .map(|invitee| match hex::decode(invitee) {
+                            Ok(key) if identity::agreement_key(&key).is_some() => Ok(key),
+                            _ => Err(invitee.to_string()),
+                        })
+                        .collect();
+                    // A line without units is a lot of one
+                    let lots: Result<Vec<Lot>, String> = self
+                        .lots
+                        .lines()
+                        .map(str::trim)
+                        .filter(|lot| !lot.is_empty())
+                        .map(|lot| match lot.rsplit_once(" x ") {
+                            Some((name, quantity)) => match quantity.trim().parse::<u32>() {
+                                Ok(quantity) if quantity > 0 => Ok(Lot {
+                                    name: name.trim().to_string(),
+                                    quantity,
+                                }),
+                                _ => Err(lot.to_string()),
+                            },
+                            None => Ok(Lot {
+                                name: lot.to_string(),
+                                quantity: 1,
+                            }),
+                        })
+                        .collect();
+                    match (
+                        Money::parse(&self.starting_price, self.currency),
+                        start_delay,
                         self.duration.parse::<u64>(),
+                        attachments,
+                        invitees,
+                        lots,
                     ) {
-                        result = Some(CreateScreenEvent::Submitted(
-                            self.item_name.clone(),
-                            price,
-                            time,
-                        ));
+                        // Attachment chunks are stored in plaintext, they would leak the item
+                        (_, _, _, Ok(attachments), Ok(invitees), _)
+                            if !attachments.is_empty() && !invitees.is_empty() =>
+                        {
+                            self.status = "Private auctions can't have attachments.".to_string();
+                        }
+                        (
+                            Ok(price),
+                            Ok(delay),
+                            Ok(time),
+                            Ok(attachments),
+                            Ok(invitees),
+                            Ok(lots),
+                        ) => {
+                            self.status = "".to_string();
+                            result = Some(CreateScreenEvent::Submitted {
+                                item_name: self.item_name.clone(),
+                                description: self.description.clone(),
+                                category: self.category.clone(),
+                                attachments,
+                                invitees,
+                                lots,
+                                starting_price: price,
+                                start_delay_hours: delay,
+                                duration_hours: time,
+                            });
+                        }
+                        (Err(e), _, _, _, _, _) => {
+                            self.status = format!("Invalid starting price: {}", e)
+                        }
+                        (_, Err(_), _, _, _, _) => self.status = "Invalid start delay.".to_string(),
+                        (_, _, Err(_), _, _, _) => self.status = "Invalid duration.".to_string(),
+                        (_, _, _, Err(e), _, _) => {
+                            self.status = format!("Invalid attachment {}", e)
+                        }
+                        (_, _, _, _, Err(e), _) => {
+                            self.status = format!("Invalid invitee key {}", e)
+                        }
+                        (_, _, _, _, _, Err(e)) => self.status = format!("Invalid lot {}", e),
                     }
                 }
             });
+            ui.label(&self.status);
         });
 
         result
     }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = status;
+    }
 }
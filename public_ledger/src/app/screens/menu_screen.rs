@@ -2,6 +2,7 @@ use std::result;
 
 use egui::Ui;
 
+use crate::auction::record::LedgerMode;
 use crate::kademlia;
 
 #[derive(Default)]
@@ -14,6 +15,8 @@ pub struct MenuScreen {
     search_key: String,
     search_value: String,
     routing_table: Option<kademlia::routing_table::RoutingTable>,
+    // Record full auctions and bids on the chain instead of their hashes
+    full_records: bool,
 }
 
 pub enum MenuScreenEvent {
@@ -49,6 +52,15 @@ impl MenuScreen {
             });
         });
 
+        ui.add_space(10.0);
+        ui.group(|ui| {
+            ui.label("Ledger:");
+            ui.checkbox(
+                &mut self.full_records,
+                "Record full auctions and bids on-chain (the DHT is only a cache)",
+            );
+        });
+
         ui.add_space(10.0);
         ui.group(|ui| {
             ui.label("Store Key-Value Pair:");
@@ -121,6 +133,21 @@ impl MenuScreen {
                     ui.label(format!("Bucket {}: ", index));
                     for node in bucket.get_nodes() {
                         ui.label(format!("Node ID: {}", hex::encode(node.get_id())));
+                        // Liveness of the node: when it last answered, how fast, and failed RPCs since
+                        let last_seen = match node.get_last_seen() {
+                            Some(last_seen) => format!("{}s ago", last_seen.elapsed().as_secs()),
+                            None => "never".to_string(),
+                        };
+                        let rtt = match node.get_rtt() {
+                            Some(rtt) => format!("{} ms", rtt.as_millis()),
+                            None => "-".to_string(),
+                        };
+                        ui.label(format!(
+                            "Last seen: {}, RTT: {}, Failures: {}",
+                            last_seen,
+                            rtt,
+                            node.get_failures()
+                        ));
                     }
                 }
             });
@@ -139,8 +166,12 @@ impl MenuScreen {
         self.search_value = value;
     }
 
-    pub fn get_node_id(&self) -> Vec<u8> {
-        self.node_id.clone()
+    pub fn get_ledger_mode(&self) -> LedgerMode {
+        if self.full_records {
+            LedgerMode::FullRecords
+        } else {
+            LedgerMode::HashOnly
+        }
     }
 
     pub fn set_routing_table(&mut self, routing_table: kademlia::routing_table::RoutingTable) {
@@ -0,0 +1,564 @@
+use std::collections::{HashMap, HashSet};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::chain::Chain;
+use crate::identity::{Identity, verify_signature};
+
+use super::{
+    Auction,
+    bid::Bid,
+    lifecycle::AuctionLifecycle,
+    lot::{self, LotResult},
+    money::{Currency, Money},
+    record::{AuctionRecord, BidRecord},
+    settlement::{self, SettlementTransaction},
+    signature::{AuctionSignature, BidSignature},
+};
+
+// Largest amount (in minor units) a faucet transaction can grant each recipient
+pub const MAX_FAUCET_GRANT: u64 = 1_000_000;
+
+// Faucet transaction, creates money
+// It is only valid in the genesis block (genesis allocation), unless the genesis allocation
+// starts a test network, where it is valid in any block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FaucetTransaction {
+    pub faucet_recipients: Vec<Vec<u8>>,
+    // Granted to each recipient, at most MAX_FAUCET_GRANT
+    pub faucet_amount: Money,
+    // Only counts in the genesis allocation, keeps the faucet open in the later blocks
+    #[serde(default)]
+    pub test_network: bool,
+}
+
+impl FaucetTransaction {
+    pub fn new(recipients: Vec<Vec<u8>>, amount: Money) -> Self {
+        FaucetTransaction {
+            faucet_recipients: recipients,
+            faucet_amount: amount,
+            test_network: false,
+        }
+    }
+
+    // Genesis allocation of a test network, anyone can use the faucet afterwards
+    pub fn test_network(recipients: Vec<Vec<u8>>, amount: Money) -> Self {
+        FaucetTransaction {
+            test_network: true,
+            ..FaucetTransaction::new(recipients, amount)
+        }
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+}
+
+// Transfer transaction, moves available money between accounts, signed by the sender
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct TransferTransaction {
+    pub transfer_from: Vec<u8>,
+    pub transfer_to: Vec<u8>,
+    pub transfer_amount: Money,
+    // Makes every transfer unique, so identical transfers get distinct signatures
+    pub transfer_nonce: u64,
+    pub sender_signature: Vec<u8>,
+}
+
+impl TransferTransaction {
+    pub fn new(identity: &Identity, to: Vec<u8>, amount: Money) -> Self {
+        let from = identity.get_public_key();
+        let nonce = rand::rng().random();
+        let sender_signature = identity.sign(&TransferTransaction::signed_message(
+            &from, &to, &amount, nonce,
+        ));
+        TransferTransaction {
+            transfer_from: from,
+            transfer_to: to,
+            transfer_amount: amount,
+            transfer_nonce: nonce,
+            sender_signature,
+        }
+    }
+
+    // Bytes covered by the sender's signature
+    fn signed_message(from: &[u8], to: &[u8], amount: &Money, nonce: u64) -> Vec<u8> {
+        serde_json::to_vec(&("transfer", from, to, amount, nonce)).unwrap()
+    }
+
+    pub fn verify_signature(&self) -> bool {
+        let message = TransferTransaction::signed_message(
+            &self.transfer_from,
+            &self.transfer_to,
+            &self.transfer_amount,
+            self.transfer_nonce,
+        );
+        verify_signature(&self.transfer_from, &message, &self.sender_signature)
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+}
+
+// Money locked by a bid still in the running for units of a lot, released when it is outranked
+// and paid to the seller (up to the clearing price) by the settlement, or released if the
+// auction lapses without one
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Escrow {
+    pub bid_id: String,
+    pub bid_hash: Vec<u8>,
+    pub bidder_id: Vec<u8>,
+    pub quantity: u32,
+    pub unit_price: Money,
+    // Unit price times quantity
+    pub amount: Money,
+}
+
+// An auction known to the replay, with what it needs to check bids against it
+struct TrackedAuction {
+    auction: Auction,
+    confirmation_height: u64,
+    lifecycle: AuctionLifecycle,
+}
+
+// An auction paid out by its settlement
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SettledAuction {
+    pub seller_id: Vec<u8>,
+    // Height of the settlement block
+    pub height: u64,
+    // What each lot cleared at and who paid for its units, no fills if there were no valid bids
+    pub lot_results: Vec<LotResult>,
+}
+
+impl SettledAuction {
+    // Bidders who got units and paid for them
+    pub fn winner_ids(&self) -> Vec<Vec<u8>> {
+        let mut winner_ids: Vec<Vec<u8>> = Vec::new();
+        for fill in self.lot_results.iter().flat_map(|result| &result.fills) {
+            if !winner_ids.contains(&fill.winner_id) {
+                winner_ids.push(fill.winner_id.clone());
+            }
+        }
+        winner_ids
+    }
+}
+
+// Balances and escrows computed by replaying the chain from the genesis block
+// Bids are checked against the auction rules and the bidder's available balance in the order
+// they were confirmed, so this also decides which bids are valid (see `verify_bids`)
+// Auctions and bids committed by hash only are taken into account when their records are given,
+// until the auction has a settlement on the chain: from then on its bids only count if their
+// records are on the chain, recorded in full or revealed by a settlement, so every node checks
+// the settlement against the same bids
+//
+// Conflicts between bids are settled by the chain alone, never by the bidders' clocks:
+// - Order: bids count in the order of the blocks confirming them
+// - Equal bids: the first confirmed ranks first, so on a single item a later bid of the same
+//   amount doesn't beat it and is rejected
+// - Duplicate IDs: a bid ID is claimed by the first confirmed commitment of a signed record with
+//   that ID, later commitments of the same ID are ignored, whatever record they point to
+//   Commitments without a known, signed record don't claim the ID
+// - Own bids: a bidder's bids are ranked like anyone else's, a new bid that outranks the bidder's
+//   own may reuse its escrow, and one that doesn't is rejected
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Accounts {
+    // Available (unlocked) money of every account
+    balances: HashMap<(Vec<u8>, Currency), u64>,
+    // Escrows of every lot of an auction, by auction ID then lot index, each lot's ranked
+    // highest unit price first (see `lot::rank_position`)
+    escrows: HashMap<String, Vec<Vec<Escrow>>>,
+    // Bids that passed the rules and locked the bidder's funds, in confirmation order
+    accepted_bids: Vec<Bid>,
+    // Auctions settled so far, by auction ID
+    settled: HashMap<String, SettledAuction>,
+}
+
+impl Accounts {
+    pub fn replay(chain: &Chain, auctions: &[Auction], bids: &[Bid]) -> Accounts {
+        let settled_ids: HashSet<String> = chain
+            .blocks_from_genesis()
+            .filter_map(|(_, block)| {
+                SettlementTransaction::deserialized_from_bytes(block.get_transactions()).ok()
+            })
+            .map(|settlement| settlement.settled_auction_id)
+            .collect();
+        let unsettled_bids: Vec<Bid> = bids
+            .iter()
+            .filter(|bid| !settled_ids.contains(&bid.auction_id))
+            .cloned()
+            .collect();
+        Accounts::replay_with_bids(chain, auctions, &unsettled_bids)
+    }
+
+    // Replays the chain with the given bids counting even once a settlement is on the chain,
+    // to compute a new settlement
+    pub fn replay_with_bids(chain: &Chain, auctions: &[Auction], bids: &[Bid]) -> Accounts {
+        let mut accounts = Accounts::default();
+
+        // Every auction and bid whose content is known, given or recorded in full
+        let mut tracked: HashMap<String, TrackedAuction> = HashMap::new();
+        for auction in auctions
+            .iter()
+            .cloned()
+            .chain(AuctionRecord::get_auctions(chain))
+        {
+            if !auction.has_valid_id() || tracked.contains_key(&auction.id) {
+                continue; // Skip forged IDs and auctions already tracked
+            }
+            let Some(confirmation) = AuctionSignature::get_confirmation(chain, &auction) else {
+                continue;
+            };
+            let Some(lifecycle) = AuctionLifecycle::replay(chain, &auction) else {
+                continue;
+            };
+            tracked.insert(
+                auction.id.clone(),
+                TrackedAuction {
+                    auction,
+                    confirmation_height: confirmation.height,
+                    lifecycle,
+                },
+            );
+        }
+        let mut known_bids: HashMap<(String, Vec<u8>), Bid> = HashMap::new();
+        for bid in bids
+            .iter()
+            .cloned()
+            .chain(BidRecord::get_all_bids(chain))
+            .chain(SettlementTransaction::get_revealed_bids(chain))
+        {
+            known_bids.insert((bid.id.clone(), bid.get_hash()), bid);
+        }
+
+        let mut claimed_bid_ids: HashSet<String> = HashSet::new();
+        // Transfers applied so far, by sender and nonce, so a signed transfer mined again is
+        // ignored instead of paying the recipient twice
+        let mut applied_transfers: HashSet<(Vec<u8>, u64)> = HashSet::new();
+        // Whether the genesis allocation started a test network
+        let mut faucet_open = false;
+        for (height, block) in chain.blocks_from_genesis() {
+            // Auctions nobody settled in time lapse before the block's transaction
+            for tracked in tracked.values() {
+                if tracked.lifecycle.settlement_deadline() == Some(height) {
+                    accounts.lapse(&tracked.auction);
+                }
+            }
+
+            let transaction = block.get_transactions();
+            if let Ok(faucet) = FaucetTransaction::deserialized_from_bytes(transaction) {
+                if height == 0 {
+                    faucet_open = faucet.test_network;
+                }
+                if (height == 0 || faucet_open)
+                    && faucet.faucet_amount.get_minor_units() <= MAX_FAUCET_GRANT
+                {
+                    for recipient in faucet.faucet_recipients {
+                        accounts.credit(recipient, faucet.faucet_amount);
+                    }
+                }
+            } else if let Ok(transfer) = TransferTransaction::deserialized_from_bytes(transaction) {
+                let transfer_id = (transfer.transfer_from.clone(), transfer.transfer_nonce);
+                if transfer.verify_signature()
+                    && !applied_transfers.contains(&transfer_id)
+                    && accounts.debit(&transfer.transfer_from, transfer.transfer_amount)
+                {
+                    applied_transfers.insert(transfer_id);
+                    accounts.credit(transfer.transfer_to, transfer.transfer_amount);
+                }
+            } else if let Some(signature) = BidSignature::from_transaction(transaction) {
+                let Some(bid) = known_bids.get(&(signature.bid_id, signature.bid_hash)) else {
+                    continue; // Skip bids whose record we don't have
+                };
+                if !bid.has_valid_id() || !bid.has_valid_signature() {
+                    continue; // Skip forged bids, they don't claim the ID
+                }
+                if !claimed_bid_ids.insert(bid.id.clone()) {
+                    continue; // Only the first confirmation of a bid ID counts
+                }
+                if let Some(auction) = tracked.get(&bid.auction_id) {
+                    accounts.place_bid(auction, bid, height);
+                }
+            } else if let Ok(settlement) =
+                SettlementTransaction::deserialized_from_bytes(transaction)
+                && let Some(auction) = tracked.get(&settlement.settled_auction_id)
+            {
+                accounts.settle(auction, &settlement, height);
+            }
+        }
+
+        accounts
+    }
+
+    // Checks a bid confirmed at the given height against the auction's rules and the bidder's
+    // balance, and locks its escrow if it is accepted, releasing the bids it outranks
+    fn place_bid(&mut self, tracked: &TrackedAuction, bid: &Bid, height: u64) {
+        let auction = &tracked.auction;
+        if height <= tracked.confirmation_height {
+            return; // Skip bids confirmed before the auction
+        }
+        if !tracked.lifecycle.accepts_bids_at(height) {
+            return; // Skip bids confirmed before it opened, or once closed or cancelled
+        }
+        if !auction.admits_bid(bid) {
+            // Skip forged or unsigned bids, bidders missing from a private auction's allowlist,
+            // other currencies, bids for nothing and lots or quantities the auction doesn't have
+            return;
+        }
+        let lots = auction.get_lots();
+        let lot_quantity = lots[bid.lot_index as usize].quantity;
+        let Ok(amount) = bid.amount.checked_mul(u64::from(bid.quantity)) else {
+            return;
+        };
+
+        // Rank the bid among the ones still holding escrow on the lot
+        let mut ranked = self
+            .escrows
+            .get(&auction.id)
+            .map(|escrows| escrows[bid.lot_index as usize].clone())
+            .unwrap_or_default();
+        let position = lot::rank_position(&ranked, bid.amount);
+        ranked.insert(
+            position,
+            Escrow {
+                bid_id: bid.id.clone(),
+                bid_hash: bid.get_hash(),
+                bidder_id: bid.bidder_id.clone(),
+                quantity: bid.quantity,
+                unit_price: bid.amount,
+                amount,
+            },
+        );
+        let units = lot::allocate(&ranked, lot_quantity);
+        if units[position] == 0 {
+            return; // Skip bids outranked for every unit, on a single item: not beating the highest
+        }
+        let mut standing = Vec::new();
+        let mut outranked = Vec::new();
+        for (escrow, units) in ranked.into_iter().zip(units) {
+            match units {
+                0 => outranked.push(escrow),
+                _ => standing.push(escrow),
+            }
+        }
+
+        // The bidder's own bids the new one outranks are released, so they count towards it
+        let released_own: u64 = outranked
+            .iter()
+            .filter(|escrow| escrow.bidder_id == bid.bidder_id)
+            .map(|escrow| escrow.amount.get_minor_units())
+            .sum();
+        let available = self.available(&bid.bidder_id, amount.get_currency());
+        if amount.get_minor_units() > available.get_minor_units().saturating_add(released_own) {
+            return; // Skip bids the bidder can't pay for
+        }
+
+        // Release the outranked escrows and lock the new one
+        for escrow in outranked {
+            self.credit(escrow.bidder_id, escrow.amount);
+        }
+        self.debit(&bid.bidder_id, amount);
+        self.escrows
+            .entry(auction.id.clone())
+            .or_insert_with(|| vec![Vec::new(); lots.len()])[bid.lot_index as usize] = standing;
+        self.accepted_bids.push(bid.clone());
+    }
+
+    // Pays the winners' escrows to the seller, if the settlement is the first valid one:
+    // recorded once the auction closed and naming the lots as cleared from the escrows
+    // Winners are refunded what they locked above the clearing price
+    fn settle(
+        &mut self,
+        tracked: &TrackedAuction,
+        settlement: &SettlementTransaction,
+        height: u64,
+    ) {
+        let auction = &tracked.auction;
+        if self.settled.contains_key(&auction.id)
+            || settlement.rule_set != settlement::rule_set(auction)
+            || tracked
+                .lifecycle
+                .closing_height
+                .is_none_or(|closing_height| height < closing_height)
+            || tracked
+                .lifecycle
+                .settlement_deadline()
+                .is_some_and(|deadline| height >= deadline)
+        {
+            return;
+        }
+        let lot_results = self.get_lot_results(auction);
+        if !settlement.records(auction, lot_results.clone()) {
+            return;
+        }
+
+        // Every escrow left holds units, so every one of them has a fill
+        let escrows: Vec<Escrow> = self
+            .escrows
+            .remove(&auction.id)
+            .unwrap_or_default()
+            .into_iter()
+            .flatten()
+            .collect();
+        for result in &lot_results {
+            for fill in &result.fills {
+                let (Some(escrow), Some(paid)) = (
+                    escrows.iter().find(|escrow| escrow.bid_id == fill.bid_id),
+                    result.price_of(fill),
+                ) else {
+                    continue;
+                };
+                // A fill is never priced above its escrow, if it somehow is the escrow goes back
+                // to the bidder rather than paying the seller money that was never locked
+                match escrow.amount.checked_sub(&paid) {
+                    Ok(refund) => {
+                        self.credit(auction.seller_id.clone(), paid);
+                        self.credit(escrow.bidder_id.clone(), refund);
+                    }
+                    Err(_) => self.credit(escrow.bidder_id.clone(), escrow.amount),
+                }
+            }
+        }
+        self.settled.insert(
+            auction.id.clone(),
+            SettledAuction {
+                seller_id: auction.seller_id.clone(),
+                height,
+                lot_results,
+            },
+        );
+    }
+
+    // Gives every escrow of an auction left unsettled back to its bidder
+    fn lapse(&mut self, auction: &Auction) {
+        if self.settled.contains_key(&auction.id) {
+            return;
+        }
+        let escrows = self.escrows.remove(&auction.id).unwrap_or_default();
+        for escrow in escrows.into_iter().flatten() {
+            self.credit(escrow.bidder_id, escrow.amount);
+        }
+    }
+
+    fn credit(&mut self, account: Vec<u8>, amount: Money) {
+        let balance = self
+            .balances
+            .entry((account, amount.get_currency()))
+            .or_insert(0);
+        *balance = balance.saturating_add(amount.get_minor_units());
+    }
+
+    // Takes the amount from the account's available money, false if there isn't enough
+    fn debit(&mut self, account: &[u8], amount: Money) -> bool {
+        let Some(balance) = self
+            .balances
+            .get_mut(&(account.to_vec(), amount.get_currency()))
+        else {
+            return amount.get_minor_units() == 0;
+        };
+        match balance.checked_sub(amount.get_minor_units()) {
+            Some(remaining) => {
+                *balance = remaining;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Money the account can spend or bid
+    pub fn available(&self, account: &[u8], currency: Currency) -> Money {
+        let minor_units = self
+            .balances
+            .get(&(account.to_vec(), currency))
+            .copied()
+            .unwrap_or(0);
+        Money::new(minor_units, currency)
+    }
+
+    // Money the account has locked in the escrow of its bids still in the running
+    pub fn escrowed(&self, account: &[u8], currency: Currency) -> Money {
+        let minor_units = self
+            .escrows
+            .values()
+            .flatten()
+            .flatten()
+            .filter(|escrow| {
+                escrow.bidder_id == account && escrow.amount.get_currency() == currency
+            })
+            .map(|escrow| escrow.amount.get_minor_units())
+            .sum();
+        Money::new(minor_units, currency)
+    }
+
+    // Largest bid the account can place on the auction: its available money, plus its own
+    // bids on the auction, released when the new bid outranks them
+    // Exact on a single item, where raising always outranks the own leading bid
+    pub fn spendable_for_bid(&self, account: &[u8], auction_id: &str, currency: Currency) -> Money {
+        let own_escrow: u64 = self
+            .get_escrows(auction_id)
+            .into_iter()
+            .filter(|escrow| {
+                escrow.bidder_id == account && escrow.amount.get_currency() == currency
+            })
+            .map(|escrow| escrow.amount.get_minor_units())
+            .sum();
+        let available = self.available(account, currency).get_minor_units();
+        Money::new(available.saturating_add(own_escrow), currency)
+    }
+
+    // Escrows of the bids still in the running on every lot of the auction
+    pub fn get_escrows(&self, auction_id: &str) -> Vec<&Escrow> {
+        self.escrows
+            .get(auction_id)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .collect()
+    }
+
+    // Outcome of every lot: as settled, or as the escrows would clear if the auction closed now
+    pub fn get_lot_results(&self, auction: &Auction) -> Vec<LotResult> {
+        if let Some(settled) = self.settled.get(&auction.id) {
+            return settled.lot_results.clone();
+        }
+        let escrows = self.escrows.get(&auction.id);
+        auction
+            .get_lots()
+            .iter()
+            .enumerate()
+            .map(|(index, lot)| {
+                let ranked = escrows.and_then(|escrows| escrows.get(index));
+                lot::clear(
+                    index as u32,
+                    lot.quantity,
+                    ranked.map_or(&[], Vec::as_slice),
+                )
+            })
+            .collect()
+    }
+
+    pub fn get_settled(&self, auction_id: &str) -> Option<&SettledAuction> {
+        self.settled.get(auction_id)
+    }
+
+    // Accepted bids of the auction, lowest (first confirmed) first
+    pub fn get_accepted_bids(&self, auction_id: &str) -> Vec<Bid> {
+        self.accepted_bids
+            .iter()
+            .filter(|bid| bid.auction_id == auction_id)
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+use std::path::Path;
+
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+
+use crate::kademlia::key::Key;
+
+// Attachments are split in chunks of this size, well under what storing nodes accept
+// (`MAX_VALUE_SIZE`)
+pub const CHUNK_SIZE: usize = 64 * 1024;
+// Largest attachment a seller can add to an auction
+pub const MAX_ATTACHMENT_SIZE: usize = 8 * 1024 * 1024;
+// Chunk hashes are SHA-256 digests
+pub const CHUNK_HASH_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachmentError {
+    // The file is larger than MAX_ATTACHMENT_SIZE
+    TooLarge(usize),
+    // The file could not be read
+    Io(String),
+    // A chunk could not be found in the DHT
+    MissingChunk(usize),
+    // A chunk doesn't match the hash recorded in the manifest
+    CorruptChunk(usize),
+    // The reassembled file doesn't have the size recorded in the manifest
+    SizeMismatch { expected: u64, found: u64 },
+    // A chunk hash of the manifest isn't a SHA-256 digest
+    InvalidChunkHash(usize),
+    // The manifest doesn't list one chunk per CHUNK_SIZE bytes of its size
+    ChunkCountMismatch { expected: u64, found: usize },
+}
+
+impl fmt::Display for AttachmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttachmentError::TooLarge(size) => write!(
+                f,
+                "attachment too large: {} bytes (max {})",
+                size, MAX_ATTACHMENT_SIZE
+            ),
+            AttachmentError::Io(reason) => write!(f, "could not read attachment: {}", reason),
+            AttachmentError::MissingChunk(index) => write!(f, "chunk {} not found", index),
+            AttachmentError::CorruptChunk(index) => write!(f, "chunk {} is corrupt", index),
+            AttachmentError::SizeMismatch { expected, found } => {
+                write!(f, "expected {} bytes, found {}", expected, found)
+            }
+            AttachmentError::InvalidChunkHash(index) => {
+                write!(f, "chunk {} has an invalid hash", index)
+            }
+            AttachmentError::ChunkCountMismatch { expected, found } => {
+                write!(f, "expected {} chunks, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AttachmentError {}
+
+// Manifest of an attachment, referenced by the auction record (and so covered by its hash)
+// The chunks are content addressed: each one is stored under its own hash, see `chunk_key`
+// Manifests come from other nodes, their size, chunk count and chunk hashes are checked as they
+// are decoded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedManifest")]
+pub(crate) struct AttachmentManifest {
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: u64,
+    pub chunk_hashes: Vec<Vec<u8>>,
+}
+
+// Manifest as decoded, before its chunk hashes are checked
+#[derive(Deserialize)]
+struct UncheckedManifest {
+    file_name: String,
+    mime_type: String,
+    size: u64,
+    chunk_hashes: Vec<Vec<u8>>,
+}
+
+impl TryFrom<UncheckedManifest> for AttachmentManifest {
+    type Error = AttachmentError;
+
+    fn try_from(manifest: UncheckedManifest) -> Result<Self, AttachmentError> {
+        if manifest.size > MAX_ATTACHMENT_SIZE as u64 {
+            return Err(AttachmentError::TooLarge(
+                usize::try_from(manifest.size).unwrap_or(usize::MAX),
+            ));
+        }
+        let expected = AttachmentManifest::chunk_count(manifest.size);
+        if manifest.chunk_hashes.len() as u64 != expected {
+            return Err(AttachmentError::ChunkCountMismatch {
+                expected,
+                found: manifest.chunk_hashes.len(),
+            });
+        }
+        if let Some(index) = manifest
+            .chunk_hashes
+            .iter()
+            .position(|chunk_hash| chunk_hash.len() != CHUNK_HASH_LEN)
+        {
+            return Err(AttachmentError::InvalidChunkHash(index));
+        }
+        Ok(AttachmentManifest {
+            file_name: manifest.file_name,
+            mime_type: manifest.mime_type,
+            size: manifest.size,
+            chunk_hashes: manifest.chunk_hashes,
+        })
+    }
+}
+
+impl AttachmentManifest {
+    // Identifies the content, two manifests with the same chunks are the same attachment
+    pub fn content_id(&self) -> String {
+        let mut context = Context::new(&SHA256);
+        for chunk_hash in &self.chunk_hashes {
+            context.update(chunk_hash);
+        }
+        hex::encode(context.finish().as_ref())
+    }
+
+    // How many chunks an attachment of the size is split in
+    pub fn chunk_count(size: u64) -> u64 {
+        size.div_ceil(CHUNK_SIZE as u64)
+    }
+
+    // Chunk hashes to fetch, never more than the size calls for
+    pub fn chunks_to_fetch(&self) -> impl Iterator<Item = &Vec<u8>> {
+        let count = AttachmentManifest::chunk_count(self.size);
+        self.chunk_hashes
+            .iter()
+            .take(usize::try_from(count).unwrap_or(usize::MAX))
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime_type.starts_with("image/")
+    }
+
+    // Puts the chunks fetched for the manifest (same order) back together, checking each one
+    // against its hash and the total size
+    pub fn reassemble(&self, chunks: Vec<Option<Vec<u8>>>) -> Result<Vec<u8>, AttachmentError> {
+        let mut bytes = Vec::new();
+        for (index, expected_hash) in self.chunks_to_fetch().enumerate() {
+            let Some(Some(chunk)) = chunks.get(index) else {
+                return Err(AttachmentError::MissingChunk(index));
+            };
+            if &chunk_hash(chunk) != expected_hash {
+                return Err(AttachmentError::CorruptChunk(index));
+            }
+            bytes.extend_from_slice(chunk);
+        }
+        if bytes.len() as u64 != self.size {
+            return Err(AttachmentError::SizeMismatch {
+                expected: self.size,
+                found: bytes.len() as u64,
+            });
+        }
+        Ok(bytes)
+    }
+}
+
+// An attachment ready to be stored: its manifest and the chunks it lists
+#[derive(Debug, Clone)]
+pub(crate) struct Attachment {
+    pub manifest: AttachmentManifest,
+    pub chunks: Vec<Vec<u8>>,
+}
+
+impl Attachment {
+    pub fn new(file_name: String, bytes: &[u8]) -> Result<Attachment, AttachmentError> {
+        if bytes.len() > MAX_ATTACHMENT_SIZE {
+            return Err(AttachmentError::TooLarge(bytes.len()));
+        }
+        let chunks: Vec<Vec<u8>> = bytes.chunks(CHUNK_SIZE).map(|c| c.to_vec()).collect();
+        Ok(Attachment {
+            manifest: AttachmentManifest {
+                mime_type: mime_type(&file_name).to_string(),
+                file_name,
+                size: bytes.len() as u64,
+                chunk_hashes: chunks.iter().map(|chunk| chunk_hash(chunk)).collect(),
+            },
+            chunks,
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Attachment, AttachmentError> {
+        let size = std::fs::metadata(path)
+            .map_err(|e| AttachmentError::Io(e.to_string()))?
+            .len() as usize;
+        if size > MAX_ATTACHMENT_SIZE {
+            return Err(AttachmentError::TooLarge(size));
+        }
+        let bytes = std::fs::read(path).map_err(|e| AttachmentError::Io(e.to_string()))?;
+        let file_name = path.file_name().map_or("attachment".to_string(), |name| {
+            name.to_string_lossy().to_string()
+        });
+        Attachment::new(file_name, &bytes)
+    }
+}
+
+pub fn chunk_hash(chunk: &[u8]) -> Vec<u8> {
+    let mut context = Context::new(&SHA256);
+    context.update(chunk);
+    context.finish().as_ref().to_vec()
+}
+
+// DHT key of a chunk, derived from its hash so a chunk can't be replaced by another one
+// None if the hash is too short to be one
+pub fn chunk_key(chunk_hash: &[u8]) -> Option<Key> {
+    Key::from_slice(chunk_hash.get(..20)?)
+}
+
+// Media type from the file extension, images are shown in the auction list
+pub fn mime_type(file_name: &str) -> &'static str {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
@@ -1,50 +1,118 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use rand::Rng;
 use ring::digest::{Context, SHA256};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use super::deserialize_id;
+use super::money::Money;
+use crate::identity::{Identity, verify_signature};
+
+// Bids without a quantity are for a single unit
+fn default_quantity() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Bid {
-    pub id: u32,
-    pub auction_id: u32,
+    // Content derived identifier, see `Bid::derive_id`
+    // Older records have no nonce, so their IDs never pass `has_valid_id`
+    #[serde(deserialize_with = "deserialize_id")]
+    pub id: String,
+    #[serde(deserialize_with = "deserialize_id")]
+    pub auction_id: String,
     pub bidder_id: Vec<u8>,
-    pub amount: f64,
+    #[serde(default)]
+    pub nonce: u64,
+    // Price of one unit, the bid is for `quantity` units of the auction's lot `lot_index`
+    pub amount: Money,
+    #[serde(default)]
+    pub lot_index: u32,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    // Bidder's local clock, informational only: ordering and deadlines use the confirming block
     pub timestamp: u64,
+    // Signed with the bidder's key, only signed bids can lock the bidder's funds in escrow
+    #[serde(default)]
+    pub bidder_signature: Vec<u8>,
 }
 
 impl Bid {
-    pub fn default() -> Self {
-        Bid {
-            id: 0,
-            auction_id: 0,
-            bidder_id: Vec::new(),
-            amount: 0.0,
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards")
-                .as_secs(),
-        }
-    }
-
-    pub fn new(id: u32, auction_id: u32, bidder_id: Vec<u8>, amount: f64) -> Self {
+    pub fn new(auction_id: String, bidder_id: Vec<u8>, amount: Money) -> Self {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards")
             .as_secs();
+        let nonce = rand::rng().random();
         Bid {
-            id,
+            id: Bid::derive_id(&auction_id, &bidder_id, nonce),
             auction_id,
             bidder_id,
+            nonce,
             amount,
+            lot_index: 0,
+            quantity: 1,
             timestamp,
+            bidder_signature: Vec::new(),
         }
     }
 
+    // Bids on a lot auction name the lot and how many units they want, before being signed
+    pub fn with_lot(mut self, lot_index: u32, quantity: u32) -> Self {
+        self.lot_index = lot_index;
+        self.quantity = quantity;
+        self
+    }
+
+    // Signs the bid with the bidder's key, the identity must be the one in `bidder_id`
+    pub fn signed(mut self, identity: &Identity) -> Self {
+        self.bidder_signature = identity.sign(&self.signed_message());
+        self
+    }
+
+    // Bytes covered by the bidder's signature, the ID already binds the auction and the bidder
+    fn signed_message(&self) -> Vec<u8> {
+        serde_json::to_vec(&(
+            "bid",
+            &self.id,
+            &self.auction_id,
+            self.amount,
+            self.lot_index,
+            self.quantity,
+        ))
+        .unwrap()
+    }
+
+    pub fn has_valid_signature(&self) -> bool {
+        verify_signature(
+            &self.bidder_id,
+            &self.signed_message(),
+            &self.bidder_signature,
+        )
+    }
+
+    // Bid IDs are the hash of the auction, the bidder's key and a random nonce, so concurrent
+    // bidders never overwrite each other's `auction:<id>:bid:<bid_id>` key
+    pub fn derive_id(auction_id: &str, bidder_id: &[u8], nonce: u64) -> String {
+        let mut context = Context::new(&SHA256);
+        context.update(b"bid");
+        context.update(auction_id.as_bytes());
+        context.update(bidder_id);
+        context.update(&nonce.to_be_bytes());
+        hex::encode(context.finish().as_ref())
+    }
+
+    // Checks that the ID was derived from the auction, bidder and nonce it claims
+    pub fn has_valid_id(&self) -> bool {
+        self.id == Bid::derive_id(&self.auction_id, &self.bidder_id, self.nonce)
+    }
+
     pub fn serialized(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
-    pub fn deserialized(serialized: &str) -> Self {
-        serde_json::from_str(serialized).unwrap()
+    // Bid fetched from another node, which may send anything
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
     }
 
     pub fn get_hash(&self) -> Vec<u8> {
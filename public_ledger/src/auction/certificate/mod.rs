@@ -0,0 +1,157 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::block::Block;
+use crate::blockchain::chain::Chain;
+
+use super::{
+    Auction,
+    bid::Bid,
+    settlement::SettlementTransaction,
+    signature::{AuctionSignature, BidSignature},
+};
+
+// Winner certificate, a self contained proof of an auction's outcome that can be exported
+// and checked later without access to the DHT
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct WinnerCertificate {
+    pub auction: Auction,
+    // None on lot auctions too, their winners are in `settlement.lot_results`
+    pub winning_bid: Option<Bid>,
+    pub auction_signature: AuctionSignature,
+    pub bid_signature: Option<BidSignature>,
+    pub settlement: SettlementTransaction,
+    // Heights of the blocks that recorded each transaction
+    pub auction_height: u64,
+    pub bid_height: Option<u64>,
+    pub settlement_height: u64,
+    // Every block from the one recording the auction to the one recording the settlement,
+    // genesis side first, so the hash links between them can be checked
+    pub chain_proof: Vec<Block>,
+}
+
+impl WinnerCertificate {
+    // Builds the certificate of a settled auction, None if no valid settlement is on the chain
+    // The winning bid is taken from the given records, or the ones the settlement revealed
+    pub fn new(chain: &Chain, auction: &Auction, bids: Vec<Bid>) -> Option<WinnerCertificate> {
+        let (settlement, settlement_confirmation) =
+            SettlementTransaction::get_valid_settlement(chain, auction)?;
+        let auction_confirmation = AuctionSignature::get_confirmation(chain, auction)?;
+
+        let winning_bid = match &settlement.winning_bid_id {
+            Some(bid_id) => Some(
+                bids.into_iter()
+                    .chain(settlement.revealed_bids.clone())
+                    .find(|bid| {
+                        &bid.id == bid_id
+                            && settlement.winning_bid_hash.as_ref() == Some(&bid.get_hash())
+                    })?,
+            ),
+            None => None,
+        };
+        let bid_height = match &winning_bid {
+            Some(bid) => Some(BidSignature::get_confirmation(chain, bid)?.height),
+            None => None,
+        };
+
+        Some(WinnerCertificate {
+            auction_signature: AuctionSignature::new(auction.id.clone(), auction.get_hash()),
+            bid_signature: winning_bid.as_ref().map(|bid| {
+                BidSignature::new(bid.id.clone(), bid.auction_id.clone(), bid.get_hash())
+            }),
+            auction: auction.clone(),
+            winning_bid,
+            settlement,
+            auction_height: auction_confirmation.height,
+            bid_height,
+            settlement_height: settlement_confirmation.height,
+            chain_proof: chain
+                .get_blocks_between(auction_confirmation.height, settlement_confirmation.height),
+        })
+    }
+
+    // Checks the certificate on its own: proof of work and hash links of the proof blocks,
+    // and that each transaction sits in the block it claims and matches the bundled records
+    pub fn verify(&self) -> bool {
+        let expected_len = self.settlement_height.checked_sub(self.auction_height);
+        if expected_len.map(|len| len + 1) != Some(self.chain_proof.len() as u64) {
+            return false;
+        }
+
+        // Every block is mined and points to the previous one
+        if !self.chain_proof.iter().all(|block| block.is_valid()) {
+            return false;
+        }
+        let linked = self
+            .chain_proof
+            .windows(2)
+            .all(|pair| pair[1].header.get_parent_hash() == pair[0].get_hash());
+        if !linked {
+            return false;
+        }
+
+        // The auction commitment is in the first block and matches the bundled auction
+        let auction_recorded =
+            AuctionSignature::from_transaction(self.chain_proof[0].get_transactions())
+                .is_some_and(|signature| signature == self.auction_signature);
+        if !auction_recorded
+            || !self.auction.has_valid_id()
+            || self.auction_signature.auction_id != self.auction.id
+            || self.auction_signature.auction_hash != self.auction.get_hash()
+        {
+            return false;
+        }
+
+        // The settlement is in the last block
+        let settlement_recorded = SettlementTransaction::deserialized_from_bytes(
+            self.chain_proof[self.chain_proof.len() - 1].get_transactions(),
+        )
+        .is_ok_and(|settlement| settlement == self.settlement);
+        if !settlement_recorded || self.settlement.settled_auction_id != self.auction.id {
+            return false;
+        }
+
+        // The winning bid commitment is in between and matches what the settlement names
+        match (&self.winning_bid, &self.bid_signature, self.bid_height) {
+            (Some(bid), Some(signature), Some(bid_height)) => {
+                let Some(block) = bid_height
+                    .checked_sub(self.auction_height)
+                    .and_then(|index| self.chain_proof.get(index as usize))
+                else {
+                    return false;
+                };
+                BidSignature::from_transaction(block.get_transactions())
+                    .is_some_and(|recorded| recorded.commits_to(bid))
+                    && signature.commits_to(bid)
+                    && signature.auction_id == self.auction.id
+                    && self.settlement.winning_bid_id.as_ref() == Some(&bid.id)
+                    && self.settlement.winning_bid_hash.as_ref() == Some(&bid.get_hash())
+                    && self.settlement.winner_id.as_ref() == Some(&bid.bidder_id)
+                    && self.settlement.price_paid == Some(bid.amount)
+            }
+            (None, None, None) => self.settlement.winning_bid_id.is_none(),
+            _ => false,
+        }
+    }
+
+    // Checks the certificate and that its settlement block is part of the given chain
+    pub fn verify_against(&self, chain: &Chain) -> bool {
+        let Some(last_block) = self.chain_proof.last() else {
+            return false;
+        };
+        self.verify()
+            && chain
+                .get_block_at_height(self.settlement_height)
+                .is_some_and(|block| block.get_hash() == last_block.get_hash())
+    }
+
+    pub fn serialized(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // Writes the certificate as JSON, for the accounting team
+    pub fn export(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.serialized())
+    }
+}
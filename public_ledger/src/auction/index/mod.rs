@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+
+use super::Auction;
+
+// Ending times are indexed by UTC day, so "ending today" is a single lookup
+pub const ENDING_BUCKET_SECONDS: u64 = 24 * 60 * 60;
+// A search covers at most this many days of ending times, each is a lookup
+pub const MAX_ENDING_BUCKETS: u64 = 30;
+// Longest ending range a search may ask for, covered in full by MAX_ENDING_BUCKETS days
+// whatever hour of the day it starts at
+pub const MAX_ENDING_HOURS: u64 = (MAX_ENDING_BUCKETS - 1) * 24;
+// Words shorter than this aren't indexed
+const MIN_KEYWORD_LEN: usize = 2;
+
+// One auction in a postings list, with its ending time so time ranges can be filtered
+// without fetching the auction
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) struct IndexEntry {
+    pub auction_id: String,
+    pub ending_time: u64,
+}
+
+// Postings list, the auctions filed under one index key (see `index_keys`)
+// Lists only grow, so two copies are merged by taking their union
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PostingsList {
+    // Index key the list is stored under, like `keyword:laptop`
+    pub name: String,
+    pub postings: Vec<IndexEntry>,
+}
+
+impl PostingsList {
+    pub fn new(name: &str, postings: Vec<IndexEntry>) -> Self {
+        PostingsList {
+            name: name.to_string(),
+            postings,
+        }
+    }
+
+    // Whether the list belongs under the DHT key, lists of an ending day only hold auctions
+    // ending that day
+    pub fn is_valid(&self, key: &Key) -> bool {
+        let bucket = self
+            .name
+            .strip_prefix("ending:")
+            .map(|bucket| bucket.parse::<u64>());
+        Namespace::Index.key(&self.name) == *key
+            && match bucket {
+                Some(Ok(bucket)) => self
+                    .postings
+                    .iter()
+                    .all(|entry| entry.ending_time / ENDING_BUCKET_SECONDS == bucket),
+                Some(Err(_)) => false,
+                None => true,
+            }
+    }
+
+    pub fn serialized(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    // Adds the entries of another copy of the list
+    pub fn merge(&mut self, other: PostingsList) {
+        for entry in other.postings {
+            if !self.postings.contains(&entry) {
+                self.postings.push(entry);
+            }
+        }
+    }
+
+    // Value a node should keep when a postings list is stored over another one,
+    // so concurrent writers never drop each other's entries
+    pub fn merge_stored(existing: Option<&Vec<u8>>, incoming: Vec<u8>) -> Vec<u8> {
+        let (Some(existing), Ok(incoming_list)) = (
+            existing.and_then(|bytes| PostingsList::deserialized_from_bytes(bytes).ok()),
+            PostingsList::deserialized_from_bytes(&incoming),
+        ) else {
+            return incoming;
+        };
+        // Lists of two keys are never merged into one
+        if existing.name != incoming_list.name {
+            return incoming;
+        }
+        let mut merged = existing;
+        merged.merge(incoming_list);
+        merged.serialized().into_bytes()
+    }
+}
+
+// Lowercase words of a text, without duplicates
+pub fn keywords(text: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .map(|word| word.to_lowercase())
+    {
+        if word.chars().count() >= MIN_KEYWORD_LEN && !keywords.contains(&word) {
+            keywords.push(word);
+        }
+    }
+    keywords
+}
+
+// Names of the postings lists, their DHT keys are `Namespace::Index.key(name)`
+pub fn keyword_key(keyword: &str) -> String {
+    format!("keyword:{}", keyword)
+}
+
+pub fn category_key(category: &str) -> String {
+    format!("category:{}", category.trim().to_lowercase())
+}
+
+pub fn seller_key(seller_id: &[u8]) -> String {
+    format!("seller:{}", hex::encode(seller_id))
+}
+
+pub fn ending_key(bucket: u64) -> String {
+    format!("ending:{}", bucket)
+}
+
+// Every postings list an auction is filed under
+pub fn index_keys(auction: &Auction) -> Vec<String> {
+    let mut keys: Vec<String> = keywords(&format!("{} {}", auction.item_name, auction.description))
+        .iter()
+        .map(|keyword| keyword_key(keyword))
+        .collect();
+    if !auction.category.trim().is_empty() {
+        keys.push(category_key(&auction.category));
+    }
+    keys.push(seller_key(&auction.seller_id));
+    keys.push(ending_key(auction.ending_time / ENDING_BUCKET_SECONDS));
+    keys
+}
+
+pub fn index_entry(auction: &Auction) -> IndexEntry {
+    IndexEntry {
+        auction_id: auction.id.clone(),
+        ending_time: auction.ending_time,
+    }
+}
+
+// Search over the index, every criterion given must match
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct AuctionQuery {
+    pub keywords: Vec<String>,
+    pub category: Option<String>,
+    pub seller_id: Option<Vec<u8>>,
+    // Ending time range, both ends included
+    pub ending_between: Option<(u64, u64)>,
+}
+
+impl AuctionQuery {
+    pub fn is_empty(&self) -> bool {
+        self.keywords.is_empty()
+            && self.category.is_none()
+            && self.seller_id.is_none()
+            && self.ending_between.is_none()
+    }
+
+    // Keys of the postings lists to fetch, grouped: the lists of a group are united
+    // (the days of the ending range), and the groups intersected
+    pub fn key_groups(&self) -> Vec<Vec<String>> {
+        let mut groups: Vec<Vec<String>> = self
+            .keywords
+            .iter()
+            .map(|keyword| vec![keyword_key(keyword)])
+            .collect();
+        if let Some(category) = &self.category {
+            groups.push(vec![category_key(category)]);
+        }
+        if let Some(seller_id) = &self.seller_id {
+            groups.push(vec![seller_key(seller_id)]);
+        }
+        if let Some((from, to)) = self.ending_between {
+            let first = from / ENDING_BUCKET_SECONDS;
+            let last = (to / ENDING_BUCKET_SECONDS).min(first + MAX_ENDING_BUCKETS - 1);
+            groups.push((first..=last).map(ending_key).collect());
+        }
+        groups
+    }
+
+    // Whether an ending time is within the searched range, if any
+    pub fn ends_in_range(&self, ending_time: u64) -> bool {
+        self.ending_between
+            .is_none_or(|(from, to)| (from..=to).contains(&ending_time))
+    }
+
+    // Combines the postings lists fetched for `key_groups` (same order) into the matching
+    // auction IDs, in the order of the first group
+    pub fn combine(&self, groups: Vec<Vec<PostingsList>>) -> Vec<String> {
+        let mut sets = groups
+            .into_iter()
+            .map(|lists| lists.into_iter().flat_map(|list| list.postings));
+        let Some(first) = sets.next() else {
+            return Vec::new();
+        };
+        let others: Vec<HashSet<String>> = sets
+            .map(|postings| postings.into_iter().map(|entry| entry.auction_id).collect())
+            .collect();
+
+        let mut auction_ids: Vec<String> = Vec::new();
+        for entry in first {
+            if self.ends_in_range(entry.ending_time)
+                && others.iter().all(|set| set.contains(&entry.auction_id))
+                && !auction_ids.contains(&entry.auction_id)
+            {
+                auction_ids.push(entry.auction_id);
+            }
+        }
+        auction_ids
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+
+use ring::digest::SHA256_OUTPUT_LEN;
+
+use crate::blockchain::chain::{Chain, Confirmation};
+
+use super::{
+    Auction,
+    record::BidRecord,
+    seller::{SellerAction, SellerTransaction},
+    settlement::{SETTLEMENT_PERIOD, SettlementTransaction},
+    signature::{AuctionSignature, BidSignature},
+};
+
+// Rules on deadline extensions: how many a seller can make, and how far past the original
+// ending time they can push the deadline (in seconds)
+pub const MAX_DEADLINE_EXTENSIONS: u32 = 3;
+pub const MAX_TOTAL_EXTENSION: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LifecycleState {
+    // Recorded on the chain, bidding has not started yet
+    Scheduled,
+    // Bids confirmed now are accepted
+    Open,
+    // The median time past went beyond the ending time, the outcome is fixed
+    Closed,
+    // The outcome was recorded by a valid settlement transaction
+    Settled,
+    // Nobody settled it within the settlement period, the escrows went back to the bidders
+    Lapsed,
+    // Withdrawn by the seller before any bid
+    Cancelled,
+}
+
+impl fmt::Display for LifecycleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LifecycleState::Scheduled => "Scheduled",
+            LifecycleState::Open => "Open",
+            LifecycleState::Closed => "Closed",
+            LifecycleState::Settled => "Settled",
+            LifecycleState::Lapsed => "Lapsed",
+            LifecycleState::Cancelled => "Cancelled",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Something that happened to the auction, and the block where it happened
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LifecycleEvent {
+    Entered(LifecycleState),
+    DescriptionAmended,
+    DeadlineExtended { new_ending_time: u64 },
+}
+
+impl fmt::Display for LifecycleEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LifecycleEvent::Entered(state) => write!(f, "{}", state),
+            LifecycleEvent::DescriptionAmended => write!(f, "Description amended"),
+            LifecycleEvent::DeadlineExtended { new_ending_time } => write!(
+                f,
+                "Deadline extended to {}",
+                Auction::format_timestamp(*new_ending_time)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Transition {
+    pub event: LifecycleEvent,
+    pub confirmation: Confirmation,
+}
+
+// State of an auction computed by replaying the chain from the block that recorded it,
+// so every node derives the same state, description and deadline from the same chain
+#[derive(Debug, Clone)]
+pub(crate) struct AuctionLifecycle {
+    pub state: LifecycleState,
+    // Description and ending time after the seller's amendments and extensions
+    pub description: String,
+    pub ending_time: u64,
+    pub extensions: u32,
+    pub open_height: Option<u64>,
+    pub closing_height: Option<u64>,
+    pub cancel_height: Option<u64>,
+    // Whether a bid was committed while open, the seller can no longer cancel or amend
+    pub has_bids: bool,
+    pub transitions: Vec<Transition>,
+}
+
+impl AuctionLifecycle {
+    // Replays the chain for the auction, None if the auction isn't recorded on it
+    // Settlements are not replayed here, see `with_settlement`
+    pub fn replay(chain: &Chain, auction: &Auction) -> Option<AuctionLifecycle> {
+        let auction_confirmation = AuctionSignature::get_confirmation(chain, auction)?;
+        let mut lifecycle = AuctionLifecycle {
+            state: LifecycleState::Scheduled,
+            description: auction.description.clone(),
+            ending_time: auction.ending_time,
+            extensions: 0,
+            open_height: None,
+            closing_height: None,
+            cancel_height: None,
+            has_bids: false,
+            transitions: vec![Transition {
+                event: LifecycleEvent::Entered(LifecycleState::Scheduled),
+                confirmation: auction_confirmation.clone(),
+            }],
+        };
+        for (height, block) in chain
+            .blocks_from_genesis()
+            .skip(auction_confirmation.height as usize)
+        {
+            let confirmation = Confirmation::new(height, block);
+            // Deadlines are checked against the median time past rather than the block's own
+            // timestamp, a miner can stamp a single block up to MAX_FUTURE_BLOCK_TIME ahead but
+            // can't move the median on its own
+            let median_time = chain.median_time_past(height);
+
+            // Time based transitions apply before the block's transaction
+            if lifecycle.state == LifecycleState::Scheduled && median_time >= auction.starting_time
+            {
+                lifecycle.enter(LifecycleState::Open, &confirmation);
+                lifecycle.open_height = Some(height);
+            }
+            if lifecycle.is_active() && median_time > lifecycle.ending_time {
+                lifecycle.enter(LifecycleState::Closed, &confirmation);
+                lifecycle.closing_height = Some(height);
+            }
+            if height == auction_confirmation.height || !lifecycle.is_active() {
+                continue;
+            }
+
+            let transaction = block.get_transactions();
+            if let Some(signature) = BidSignature::from_transaction(transaction) {
+                if signature.auction_id == auction.id
+                    && lifecycle.state == LifecycleState::Open
+                    && counts_as_bid(auction, transaction, &signature)
+                {
+                    lifecycle.has_bids = true;
+                }
+            } else if let Ok(seller_transaction) =
+                SellerTransaction::deserialized_from_bytes(transaction)
+            {
+                if seller_transaction.target_auction_id != auction.id
+                    || !seller_transaction.verify_signature(&auction.seller_id)
+                {
+                    continue; // Skip actions on other auctions and ones not signed by the seller
+                }
+                lifecycle.apply(auction, seller_transaction.action, &confirmation);
+            }
+        }
+
+        Some(lifecycle)
+    }
+
+    // Adds the settlement to a closed auction's lifecycle, if a valid one is on the chain,
+    // or the lapse of the auction once the settlement period is over without one
+    pub fn with_settlement(mut self, chain: &Chain, auction: &Auction) -> Self {
+        if self.state != LifecycleState::Closed {
+            return self;
+        }
+        if let Some((_, confirmation)) = SettlementTransaction::get_valid_settlement(chain, auction)
+        {
+            self.enter(LifecycleState::Settled, &confirmation);
+        } else if let Some(deadline) = self.settlement_deadline()
+            && let Some(block) = chain.get_block_at_height(deadline)
+        {
+            self.enter(LifecycleState::Lapsed, &Confirmation::new(deadline, block));
+        }
+        self
+    }
+
+    // Height of the first block too late to settle the auction, None until it closes
+    pub fn settlement_deadline(&self) -> Option<u64> {
+        self.closing_height
+            .map(|closing_height| closing_height + SETTLEMENT_PERIOD)
+    }
+
+    // Whether the seller can still act on the auction and bidding hasn't ended
+    pub fn is_active(&self) -> bool {
+        matches!(self.state, LifecycleState::Scheduled | LifecycleState::Open)
+    }
+
+    // The local clock says the auction should be over, but no closing block was mined yet
+    pub fn awaiting_close(&self, now: u64) -> bool {
+        self.is_active() && now > self.ending_time
+    }
+
+    // Whether a bid confirmed at the given height falls inside the bidding window
+    pub fn accepts_bids_at(&self, height: u64) -> bool {
+        self.open_height.is_some_and(|open| height >= open)
+            && self.closing_height.is_none_or(|closing| height < closing)
+            && self.cancel_height.is_none_or(|cancel| height < cancel)
+    }
+
+    fn enter(&mut self, state: LifecycleState, confirmation: &Confirmation) {
+        self.state = state;
+        self.transitions.push(Transition {
+            event: LifecycleEvent::Entered(state),
+            confirmation: confirmation.clone(),
+        });
+    }
+
+    // Applies a seller action to an active auction, actions breaking the rules are ignored
+    fn apply(&mut self, auction: &Auction, action: SellerAction, confirmation: &Confirmation) {
+        match action {
+            SellerAction::Cancel => {
+                if !self.has_bids {
+                    self.enter(LifecycleState::Cancelled, confirmation);
+                    self.cancel_height = Some(confirmation.height);
+                }
+            }
+            // Bidders committed to the item as described, so it can't change under them
+            SellerAction::AmendDescription { description } => {
+                if self.has_bids {
+                    return;
+                }
+                self.description = description;
+                self.transitions.push(Transition {
+                    event: LifecycleEvent::DescriptionAmended,
+                    confirmation: confirmation.clone(),
+                });
+            }
+            SellerAction::ExtendDeadline { new_ending_time } => {
+                let within_rules = new_ending_time > self.ending_time
+                    && new_ending_time - auction.ending_time <= MAX_TOTAL_EXTENSION
+                    && self.extensions < MAX_DEADLINE_EXTENSIONS;
+                if within_rules {
+                    self.ending_time = new_ending_time;
+                    self.extensions += 1;
+                    self.transitions.push(Transition {
+                        event: LifecycleEvent::DeadlineExtended { new_ending_time },
+                        confirmation: confirmation.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+// Whether a bid transaction stops the seller's cancellations and amendments, decided from the
+// chain alone so that nodes knowing different bid records agree: a full record the auction
+// admits, a sealed commitment signed by a bidder it accepts, or a well-formed hash commitment,
+// whose record can't be checked from the chain
+fn counts_as_bid(auction: &Auction, transaction: &[u8], signature: &BidSignature) -> bool {
+    if let Ok(record) = BidRecord::deserialized_from_bytes(transaction) {
+        return auction.admits_bid(&record.recorded_bid);
+    }
+    if !signature.sealed_bidder_id.is_empty() {
+        return signature
+            .get_sealed_bidder()
+            .is_some_and(|bidder_id| auction.accepts_bidder(bidder_id));
+    }
+    !signature.bid_id.is_empty() && signature.bid_hash.len() == SHA256_OUTPUT_LEN
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+
+use super::{account::Escrow, money::Money};
+
+// Multi-unit and lot auctions: an auction sells one or more lots of identical units, bids name a
+// lot, a number of units and a price per unit, and every lot clears at a uniform price
+// A plain auction is a single lot of one unit, where clearing picks the highest bid at its own
+// price, the English first-price outcome
+
+// Identical units sold together under one auction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lot {
+    pub name: String,
+    pub quantity: u32,
+}
+
+// Units of a lot given to one bid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Fill {
+    pub bid_id: String,
+    pub bid_hash: Vec<u8>,
+    pub winner_id: Vec<u8>,
+    // At most the bid's quantity, less when the last units ran out (partial fill)
+    pub quantity: u32,
+}
+
+// Outcome of one lot, every fill pays the clearing price for each of its units
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct LotResult {
+    pub lot_index: u32,
+    // Unit price of the lowest ranked bid that got units, None if no bid did
+    pub clearing_price: Option<Money>,
+    pub fills: Vec<Fill>,
+}
+
+impl LotResult {
+    // What the fill pays, the clearing price times its units
+    pub fn price_of(&self, fill: &Fill) -> Option<Money> {
+        self.clearing_price?
+            .checked_mul(u64::from(fill.quantity))
+            .ok()
+    }
+}
+
+// Where a new bid goes among the bids of a lot, ranked highest unit price first
+// It goes after every bid with the same price, so ties go to the bid confirmed first
+pub fn rank_position(ranked: &[Escrow], unit_price: Money) -> usize {
+    ranked
+        .iter()
+        .take_while(|escrow| escrow.unit_price >= unit_price)
+        .count()
+}
+
+// Units each ranked bid gets, filling bids in rank order until the lot runs out
+pub fn allocate(ranked: &[Escrow], lot_quantity: u32) -> Vec<u32> {
+    let mut remaining = lot_quantity;
+    ranked
+        .iter()
+        .map(|escrow| {
+            let units = escrow.quantity.min(remaining);
+            remaining -= units;
+            units
+        })
+        .collect()
+}
+
+// Clears a lot from its ranked bids: the bids that get units pay the unit price of the lowest one
+pub fn clear(lot_index: u32, lot_quantity: u32, ranked: &[Escrow]) -> LotResult {
+    let filled: Vec<(&Escrow, u32)> = ranked
+        .iter()
+        .zip(allocate(ranked, lot_quantity))
+        .filter(|(_, units)| *units > 0)
+        .collect();
+    LotResult {
+        lot_index,
+        clearing_price: filled.last().map(|(escrow, _)| escrow.unit_price),
+        fills: filled
+            .into_iter()
+            .map(|(escrow, units)| Fill {
+                bid_id: escrow.bid_id.clone(),
+                bid_hash: escrow.bid_hash.clone(),
+                winner_id: escrow.bidder_id.clone(),
+                quantity: units,
+            })
+            .collect(),
+    }
+}
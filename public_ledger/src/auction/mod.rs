@@ -1,19 +1,86 @@
+pub(crate) mod account;
+pub(crate) mod attachment;
 pub(crate) mod bid;
+pub(crate) mod certificate;
+pub(crate) mod index;
+pub(crate) mod lifecycle;
+pub(crate) mod lot;
+pub(crate) mod money;
+pub(crate) mod private;
+pub(crate) mod proxy;
+pub(crate) mod record;
+pub(crate) mod reputation;
+pub(crate) mod seller;
+pub(crate) mod settlement;
 pub(crate) mod signature;
+pub(crate) mod state;
 
 use chrono::{DateTime, TimeZone};
 use chrono_tz::{Europe, Tz};
 
+use crate::blockchain::chain::Chain;
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+use attachment::AttachmentManifest;
 use bid::Bid;
+use lifecycle::AuctionLifecycle;
+use lot::Lot;
+use money::Money;
+use rand::Rng;
 use ring::digest::{Context, SHA256};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+// Accepts both current content derived IDs and the sequential numbers of older versions
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IdRepr {
+    Derived(String),
+    Legacy(u32),
+}
+
+// ID older records are read under, "legacy-<n>" never collides with a derived (hex) ID
+pub fn legacy_id(id: u32) -> String {
+    format!("legacy-{}", id)
+}
+
+pub(crate) fn deserialize_id<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match IdRepr::deserialize(deserializer)? {
+        IdRepr::Derived(id) => Ok(id),
+        IdRepr::Legacy(id) => Ok(legacy_id(id)),
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Auction {
-    pub id: u32,
+    // Content derived identifier, see `Auction::derive_id`
+    // Older records have no seller or nonce, so their IDs never pass `has_valid_id`
+    #[serde(deserialize_with = "deserialize_id")]
+    pub id: String,
+    #[serde(default)]
+    pub seller_id: Vec<u8>,
+    #[serde(default)]
+    pub nonce: u64,
     pub item_name: String,
-    pub starting_price: f64,
+    // Description at creation, the current one comes from the lifecycle (seller amendments)
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub category: String,
+    // Photos and documents, stored in chunks in the DHT
+    #[serde(default)]
+    pub attachments: Vec<AttachmentManifest>,
+    // Public keys of the bidders allowed to bid, empty for a public auction
+    // Private auctions are stored encrypted, see `private::PrivateAuction`
+    #[serde(default)]
+    pub invitees: Vec<Vec<u8>>,
+    // Lots on sale, empty for a single item, see `lot`
+    #[serde(default)]
+    pub lots: Vec<Lot>,
+    pub starting_price: Money,
     pub starting_time: u64,
     pub ending_time: u64,
     pub bids: Vec<Bid>,
@@ -21,15 +88,23 @@ pub struct Auction {
 
 impl Auction {
     pub fn new(
-        id: u32,
+        seller_id: Vec<u8>,
         item_name: String,
-        starting_price: f64,
+        starting_price: Money,
         starting_time: u64,
         ending_time: u64,
     ) -> Self {
+        let nonce = rand::rng().random();
         Auction {
-            id,
+            id: Auction::derive_id(&seller_id, nonce),
+            seller_id,
+            nonce,
             item_name,
+            description: String::new(),
+            category: String::new(),
+            attachments: Vec::new(),
+            invitees: Vec::new(),
+            lots: Vec::new(),
             starting_price,
             starting_time,
             ending_time,
@@ -38,27 +113,109 @@ impl Auction {
     }
 
     pub fn new_with_duration(
-        id: u32,
+        seller_id: Vec<u8>,
         item_name: String,
-        starting_price: f64,
+        starting_price: Money,
+        start_delay_hours: u64,
         duration_hours: u64,
     ) -> Self {
         let starting_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("Time went backwards")
-            .as_secs();
+            .as_secs()
+            + start_delay_hours * 3600;
         let ending_time = starting_time + duration_hours * 3600;
 
-        Auction {
-            id,
+        Auction::new(
+            seller_id,
             item_name,
             starting_price,
             starting_time,
             ending_time,
-            bids: Vec::new(),
+        )
+    }
+
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_category(mut self, category: String) -> Self {
+        self.category = category;
+        self
+    }
+
+    pub fn with_attachments(mut self, attachments: Vec<AttachmentManifest>) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    pub fn with_invitees(mut self, invitees: Vec<Vec<u8>>) -> Self {
+        self.invitees = invitees;
+        self
+    }
+
+    pub fn with_lots(mut self, lots: Vec<Lot>) -> Self {
+        self.lots = lots;
+        self
+    }
+
+    pub fn has_lots(&self) -> bool {
+        !self.lots.is_empty()
+    }
+
+    // Lots bids can be placed on, a single item is one lot of one unit
+    pub fn get_lots(&self) -> Vec<Lot> {
+        match self.has_lots() {
+            true => self.lots.clone(),
+            false => vec![Lot {
+                name: self.item_name.clone(),
+                quantity: 1,
+            }],
         }
     }
 
+    pub fn is_private(&self) -> bool {
+        !self.invitees.is_empty()
+    }
+
+    // Whether the bidder may bid, anyone on a public auction
+    pub fn accepts_bidder(&self, bidder_id: &[u8]) -> bool {
+        !self.is_private() || self.invitees.iter().any(|invitee| invitee == bidder_id)
+    }
+
+    // Whether the bid follows the auction's rules: signed by a bidder it accepts, in its currency,
+    // for units of a lot it has. Whether the bidder can pay is left to `Accounts`
+    pub fn admits_bid(&self, bid: &Bid) -> bool {
+        let lot_quantity = self
+            .get_lots()
+            .get(bid.lot_index as usize)
+            .map(|lot| lot.quantity);
+        bid.auction_id == self.id
+            && bid.has_valid_id()
+            && bid.has_valid_signature()
+            && self.accepts_bidder(&bid.bidder_id)
+            && bid.amount.get_currency() == self.starting_price.get_currency()
+            && bid.amount.get_minor_units() > 0
+            && lot_quantity
+                .is_some_and(|lot_quantity| bid.quantity > 0 && bid.quantity <= lot_quantity)
+    }
+
+    // Auction IDs are the hash of the creator's key and a random nonce, so two sellers
+    // creating auctions at the same time never end up under the same DHT key
+    pub fn derive_id(seller_id: &[u8], nonce: u64) -> String {
+        let mut context = Context::new(&SHA256);
+        context.update(b"auction");
+        context.update(seller_id);
+        context.update(&nonce.to_be_bytes());
+        hex::encode(context.finish().as_ref())
+    }
+
+    // Checks that the ID was derived from the seller and nonce it claims
+    pub fn has_valid_id(&self) -> bool {
+        self.id == Auction::derive_id(&self.seller_id, self.nonce)
+    }
+
     pub fn add_bid(&mut self, bid: Bid) {
         self.bids.push(bid);
     }
@@ -67,8 +224,9 @@ impl Auction {
         serde_json::to_string(self).unwrap()
     }
 
-    pub fn deserialized(serialized: &str) -> Self {
-        serde_json::from_str(serialized).unwrap()
+    // Auction fetched from another node, which may send anything
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
     }
 
     pub fn get_hash(&self) -> Vec<u8> {
@@ -77,20 +235,33 @@ impl Auction {
         context.finish().as_ref().to_vec()
     }
 
-    pub fn finished(&self) -> bool {
-        let current_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs();
-        current_time > self.ending_time
+    // State of the auction replayed from the chain, None if the auction isn't recorded on it
+    pub fn lifecycle(&self, chain: &Chain) -> Option<AuctionLifecycle> {
+        AuctionLifecycle::replay(chain, self)
+    }
+
+    // Height of the block that closed the auction: the first block whose median time past is after
+    // its ending time, extensions included, None if it is still running or was cancelled
+    pub fn closing_height(&self, chain: &Chain) -> Option<u64> {
+        self.lifecycle(chain)?.closing_height
     }
 
-    pub fn get_ending_time_as_string(&self) -> String {
-        // Assuming `self.ending_time` is a timestamp (u64) in seconds
+    pub fn format_timestamp(timestamp: u64) -> String {
+        // Assuming `timestamp` is in seconds
         let datetime = Europe::Lisbon
-            .timestamp_opt(self.ending_time as i64, 0)
+            .timestamp_opt(timestamp as i64, 0)
             .single()
             .expect("Invalid timestamp");
         datetime.format("%Y-%m-%d %H:%M:%S").to_string()
     }
 }
+
+// Topic of an auction's events, the auction itself is stored under `Namespace::Auction.key(id)`
+pub fn auction_key(auction_id: &str) -> String {
+    format!("auction:{}", auction_id)
+}
+
+// DHT key under which a bid of an auction is stored
+pub fn bid_key(auction_id: &str, bid_id: &str) -> Key {
+    Namespace::Bid.key(&format!("{}:{}", auction_id, bid_id))
+}
@@ -0,0 +1,225 @@
+//! Fixed-point monetary amounts
+use std::cmp::Ordering;
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+// Currencies accepted by auctions and bids, every amount is stored in the currency's minor unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum Currency {
+    #[default]
+    #[serde(rename = "EUR")]
+    Eur,
+    #[serde(rename = "USD")]
+    Usd,
+    #[serde(rename = "GBP")]
+    Gbp,
+}
+
+impl Currency {
+    pub const ALL: [Currency; 3] = [Currency::Eur, Currency::Usd, Currency::Gbp];
+
+    // ISO 4217 code of the currency
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Eur => "EUR",
+            Currency::Usd => "USD",
+            Currency::Gbp => "GBP",
+        }
+    }
+
+    // Number of decimal places between the major and the minor unit (cents)
+    pub fn exponent(&self) -> u32 {
+        match self {
+            Currency::Eur | Currency::Usd | Currency::Gbp => 2,
+        }
+    }
+
+    fn minor_per_major(&self) -> u64 {
+        10u64.pow(self.exponent())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    // Both operands must be in the same currency
+    CurrencyMismatch(Currency, Currency),
+    // The result does not fit in the minor unit counter (or went below zero)
+    Overflow,
+    // The text or legacy float could not be turned into an amount
+    Invalid(String),
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::CurrencyMismatch(a, b) => {
+                write!(f, "currency mismatch: {} vs {}", a.code(), b.code())
+            }
+            MoneyError::Overflow => write!(f, "amount out of range"),
+            MoneyError::Invalid(reason) => write!(f, "invalid amount: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+// An amount of money as an integer number of minor units (e.g. cents) in a given currency
+// Amounts in different currencies are never equal and cannot be ordered or combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Default)]
+pub struct Money {
+    minor_units: u64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(minor_units: u64, currency: Currency) -> Money {
+        Money {
+            minor_units,
+            currency,
+        }
+    }
+
+    pub fn zero(currency: Currency) -> Money {
+        Money::new(0, currency)
+    }
+
+    pub fn get_minor_units(&self) -> u64 {
+        self.minor_units
+    }
+
+    pub fn get_currency(&self) -> Currency {
+        self.currency
+    }
+
+    // Parses a decimal amount in major units ("12", "12.5", "12.50") without going through floats
+    pub fn parse(input: &str, currency: Currency) -> Result<Money, MoneyError> {
+        let input = input.trim();
+        let (major, minor) = match input.split_once('.') {
+            Some((major, minor)) => (major, minor),
+            None => (input, ""),
+        };
+
+        let exponent = currency.exponent() as usize;
+        if major.is_empty() || !major.chars().all(|c| c.is_ascii_digit()) {
+            return Err(MoneyError::Invalid(input.to_string()));
+        }
+        if minor.len() > exponent || !minor.chars().all(|c| c.is_ascii_digit()) {
+            return Err(MoneyError::Invalid(input.to_string()));
+        }
+
+        let major: u64 = major.parse().map_err(|_| MoneyError::Overflow)?;
+        let minor: u64 = if minor.is_empty() {
+            0
+        } else {
+            // Right pad so "12.5" means 12.50
+            format!("{:0<width$}", minor, width = exponent)
+                .parse()
+                .map_err(|_| MoneyError::Invalid(input.to_string()))?
+        };
+
+        major
+            .checked_mul(currency.minor_per_major())
+            .and_then(|units| units.checked_add(minor))
+            .map(|units| Money::new(units, currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    // Converts an amount serialized by older versions as an f64 in major units
+    // Older records carried no currency, so they are read in the default one
+    pub fn from_legacy_f64(value: f64) -> Result<Money, MoneyError> {
+        let currency = Currency::default();
+        if !value.is_finite() || value < 0.0 {
+            return Err(MoneyError::Invalid(value.to_string()));
+        }
+        let units = (value * currency.minor_per_major() as f64).round();
+        if units >= u64::MAX as f64 {
+            return Err(MoneyError::Overflow);
+        }
+        Ok(Money::new(units as u64, currency))
+    }
+
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        self.minor_units
+            .checked_add(other.minor_units)
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        self.minor_units
+            .checked_sub(other.minor_units)
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_mul(&self, factor: u64) -> Result<Money, MoneyError> {
+        self.minor_units
+            .checked_mul(factor)
+            .map(|units| Money::new(units, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    // Exact comparison, fails instead of guessing when the currencies differ
+    pub fn checked_cmp(&self, other: &Money) -> Result<Ordering, MoneyError> {
+        self.ensure_same_currency(other)?;
+        Ok(self.minor_units.cmp(&other.minor_units))
+    }
+
+    fn ensure_same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch(self.currency, other.currency));
+        }
+        Ok(())
+    }
+}
+
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.checked_cmp(other).ok()
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per_major = self.currency.minor_per_major();
+        write!(
+            f,
+            "{}.{:0width$} {}",
+            self.minor_units / per_major,
+            self.minor_units % per_major,
+            self.currency.code(),
+            width = self.currency.exponent() as usize
+        )
+    }
+}
+
+// Accepts both the current {minor_units, currency} form and the legacy bare float
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MoneyRepr {
+    Current {
+        minor_units: u64,
+        currency: Currency,
+    },
+    Legacy(f64),
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match MoneyRepr::deserialize(deserializer)? {
+            MoneyRepr::Current {
+                minor_units,
+                currency,
+            } => Ok(Money::new(minor_units, currency)),
+            MoneyRepr::Legacy(value) => {
+                Money::from_legacy_f64(value).map_err(serde::de::Error::custom)
+            }
+        }
+    }
+}
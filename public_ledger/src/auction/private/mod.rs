@@ -0,0 +1,250 @@
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::digest::{Context, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::chain::Chain;
+use crate::identity::{self, Identity};
+use crate::kademlia::key::Key;
+
+use super::{
+    Auction,
+    bid::Bid,
+    bid_key,
+    signature::{AuctionSignature, BidSignature},
+};
+
+// Private auctions: the auction and its bids are stored in the DHT encrypted to a per-auction key,
+// which is wrapped for the seller and every invitee. The chain only carries the usual hash
+// commitments, so once the key is handed to an auditor the outcome can be checked against it
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to generate random bytes");
+    bytes
+}
+
+// Ciphertext with the nonce it was sealed under
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct EncryptedPayload {
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+// ChaCha20-Poly1305 key of a private auction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct AuctionKey([u8; 32]);
+
+impl AuctionKey {
+    pub fn generate() -> AuctionKey {
+        AuctionKey(random_bytes())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<AuctionKey> {
+        Some(AuctionKey(bytes.try_into().ok()?))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.0).unwrap())
+    }
+
+    // Encrypts the plaintext, the associated data (a record ID) must match when opening
+    pub fn seal(&self, associated_data: &[u8], plaintext: &[u8]) -> EncryptedPayload {
+        let nonce: [u8; NONCE_LEN] = random_bytes();
+        let mut ciphertext = plaintext.to_vec();
+        self.aead_key()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(associated_data),
+                &mut ciphertext,
+            )
+            .expect("Plaintext too large");
+        EncryptedPayload {
+            nonce: nonce.to_vec(),
+            ciphertext,
+        }
+    }
+
+    // Decrypts the payload, None if it was sealed under another key or tampered with
+    pub fn open(&self, associated_data: &[u8], payload: &EncryptedPayload) -> Option<Vec<u8>> {
+        let nonce = Nonce::try_assume_unique_for_key(&payload.nonce).ok()?;
+        let mut plaintext = payload.ciphertext.clone();
+        let length = self
+            .aead_key()
+            .open_in_place(nonce, Aad::from(associated_data), &mut plaintext)
+            .ok()?
+            .len();
+        plaintext.truncate(length);
+        Some(plaintext)
+    }
+}
+
+// Auction key encrypted to one invitee, through an X25519 exchange between a one-time key
+// and the agreement key matching the invitee's public key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct KeyEnvelope {
+    pub invitee_id: Vec<u8>,
+    pub ephemeral_key: Vec<u8>,
+    pub wrapped_key: EncryptedPayload,
+}
+
+impl KeyEnvelope {
+    // None if the invitee ID isn't a valid public key
+    pub fn wrap(auction_key: &AuctionKey, invitee_id: &[u8]) -> Option<KeyEnvelope> {
+        let agreement_key = identity::agreement_key(invitee_id)?;
+        let ephemeral_secret: [u8; 32] = random_bytes();
+        let ephemeral_key = MontgomeryPoint::mul_base_clamped(ephemeral_secret).to_bytes();
+        let shared = MontgomeryPoint(agreement_key)
+            .mul_clamped(ephemeral_secret)
+            .to_bytes();
+        if shared == [0; 32] {
+            return None; // Low order key, the exchange would be public
+        }
+        let wrapping_key = KeyEnvelope::wrapping_key(&shared, &ephemeral_key, &agreement_key);
+        Some(KeyEnvelope {
+            invitee_id: invitee_id.to_vec(),
+            ephemeral_key: ephemeral_key.to_vec(),
+            wrapped_key: wrapping_key.seal(invitee_id, auction_key.as_bytes()),
+        })
+    }
+
+    // Recovers the auction key with the invitee's identity
+    pub fn unwrap_key(&self, identity: &Identity) -> Option<AuctionKey> {
+        let ephemeral_key: [u8; 32] = self.ephemeral_key.as_slice().try_into().ok()?;
+        let agreement_key = identity::agreement_key(&identity.get_public_key())?;
+        let shared = identity.agree(&ephemeral_key)?;
+        let wrapping_key = KeyEnvelope::wrapping_key(&shared, &ephemeral_key, &agreement_key);
+        AuctionKey::from_bytes(&wrapping_key.open(&self.invitee_id, &self.wrapped_key)?)
+    }
+
+    fn wrapping_key(shared: &[u8], ephemeral_key: &[u8], agreement_key: &[u8]) -> AuctionKey {
+        let mut context = Context::new(&SHA256);
+        context.update(b"auction key envelope");
+        context.update(shared);
+        context.update(ephemeral_key);
+        context.update(agreement_key);
+        AuctionKey::from_bytes(context.finish().as_ref()).unwrap()
+    }
+}
+
+// What the DHT stores under the key of a private auction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PrivateAuction {
+    pub private_auction_id: String,
+    // One per invitee, and one for the seller
+    pub envelopes: Vec<KeyEnvelope>,
+    pub sealed_auction: EncryptedPayload,
+}
+
+impl PrivateAuction {
+    // Encrypts the auction to a fresh key wrapped for its invitees and seller,
+    // None if one of their IDs isn't a valid public key
+    pub fn seal(auction: &Auction) -> Option<(PrivateAuction, AuctionKey)> {
+        let auction_key = AuctionKey::generate();
+        let mut envelopes = Vec::new();
+        for invitee_id in auction.invitees.iter().chain([&auction.seller_id]) {
+            envelopes.push(KeyEnvelope::wrap(&auction_key, invitee_id)?);
+        }
+        let private_auction = PrivateAuction {
+            private_auction_id: auction.id.clone(),
+            envelopes,
+            sealed_auction: auction_key
+                .seal(auction.id.as_bytes(), auction.serialized().as_bytes()),
+        };
+        Some((private_auction, auction_key))
+    }
+
+    // Auction key, if the identity was invited
+    pub fn auction_key(&self, identity: &Identity) -> Option<AuctionKey> {
+        let public_key = identity.get_public_key();
+        self.envelopes
+            .iter()
+            .filter(|envelope| envelope.invitee_id == public_key)
+            .find_map(|envelope| envelope.unwrap_key(identity))
+    }
+
+    // Decrypted auction, None if the key is wrong or the content doesn't match the record's ID
+    pub fn open(&self, auction_key: &AuctionKey) -> Option<Auction> {
+        let plaintext =
+            auction_key.open(self.private_auction_id.as_bytes(), &self.sealed_auction)?;
+        let auction: Auction = serde_json::from_slice(&plaintext).ok()?;
+        (auction.id == self.private_auction_id && auction.has_valid_id()).then_some(auction)
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+}
+
+// What the DHT stores under the key of a bid on a private auction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PrivateBid {
+    pub private_auction_id: String,
+    pub private_bid_id: String,
+    pub sealed_bid: EncryptedPayload,
+}
+
+impl PrivateBid {
+    pub fn seal(auction_key: &AuctionKey, bid: &Bid) -> PrivateBid {
+        let key = bid_key(&bid.auction_id, &bid.id);
+        PrivateBid {
+            private_auction_id: bid.auction_id.clone(),
+            private_bid_id: bid.id.clone(),
+            sealed_bid: auction_key.seal(key.as_ref(), bid.serialized().as_bytes()),
+        }
+    }
+
+    // DHT key the record belongs under, the bid key of its auction's ID and its own
+    pub fn get_key(&self) -> Key {
+        bid_key(&self.private_auction_id, &self.private_bid_id)
+    }
+
+    // Decrypted bid, None if the key is wrong or the content doesn't match the record's IDs
+    // The record's key is authenticated along with the bid, so it can't be moved to another one
+    pub fn open(&self, auction_key: &AuctionKey) -> Option<Bid> {
+        let plaintext = auction_key.open(self.get_key().as_ref(), &self.sealed_bid)?;
+        let bid: Bid = serde_json::from_slice(&plaintext).ok()?;
+        (bid.id == self.private_bid_id && bid.auction_id == self.private_auction_id).then_some(bid)
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+}
+
+// Opens a private auction and its bids with a disclosed key, keeping only the records committed
+// on the chain, so the settlement can then be checked like a public one
+pub fn audit(
+    chain: &Chain,
+    private_auction: &PrivateAuction,
+    private_bids: &[PrivateBid],
+    auction_key: &AuctionKey,
+) -> Option<(Auction, Vec<Bid>)> {
+    let auction = private_auction.open(auction_key)?;
+    AuctionSignature::get_confirmation(chain, &auction)?;
+    let bids = private_bids
+        .iter()
+        .filter_map(|private_bid| private_bid.open(auction_key))
+        .filter(|bid| {
+            bid.auction_id == auction.id && BidSignature::get_confirmation(chain, bid).is_some()
+        })
+        .collect();
+    Some((auction, bids))
+}
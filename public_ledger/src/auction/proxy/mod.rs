@@ -0,0 +1,83 @@
+use super::{Auction, bid::Bid, money::Money, signature::BidSignature};
+
+// Proxy bid registered by a buyer: outbid others by `increment`, never above `max_amount`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ProxyBid {
+    pub auction_id: String,
+    pub bidder_id: Vec<u8>,
+    pub max_amount: Money,
+    pub increment: Money,
+}
+
+// What the proxy should do given the valid bids so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProxyDecision {
+    // Our bid is the highest, nothing to do
+    Leading,
+    // Bid this amount, the minimum that takes the lead
+    Bid(Money),
+    // Taking the lead would cost more than the maximum
+    CapReached,
+}
+
+impl ProxyBid {
+    pub fn new(
+        auction_id: String,
+        bidder_id: Vec<u8>,
+        max_amount: Money,
+        increment: Money,
+    ) -> Self {
+        ProxyBid {
+            auction_id,
+            bidder_id,
+            max_amount,
+            increment,
+        }
+    }
+
+    // Decides the next bid from the bids `BidSignature::verify_bids` accepted, in chain order
+    // The leader is the winner the rule set would pick, the earliest of the highest bids
+    // The first bid is the starting price, later ones the highest bid plus the increment,
+    // capped at the maximum as long as that still beats the highest bid
+    pub fn next_bid(&self, auction: &Auction, verified_bids: &[Bid]) -> ProxyDecision {
+        let highest_bid = BidSignature::winning_bid(verified_bids.to_vec());
+        let highest_bid = highest_bid.as_ref();
+        if highest_bid.is_some_and(|bid| bid.bidder_id == self.bidder_id) {
+            return ProxyDecision::Leading;
+        }
+
+        let target = match highest_bid {
+            Some(bid) => bid.amount.checked_add(&self.increment),
+            None if auction.starting_price.get_minor_units() == 0 => Ok(self.increment),
+            None => Ok(auction.starting_price),
+        };
+        let Ok(target) = target else {
+            return ProxyDecision::CapReached; // Overflow or a currency other than the auction's
+        };
+
+        let amount = match target.checked_cmp(&self.max_amount) {
+            Ok(std::cmp::Ordering::Greater) => self.max_amount,
+            Ok(_) => target,
+            Err(_) => return ProxyDecision::CapReached,
+        };
+        let beats_highest = match highest_bid {
+            Some(bid) => amount > bid.amount,
+            None => amount == target,
+        };
+        if beats_highest {
+            ProxyDecision::Bid(amount)
+        } else {
+            ProxyDecision::CapReached
+        }
+    }
+}
+
+// A line of the proxy's log, kept for the buyer to review
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ProxyLogEntry {
+    pub timestamp: u64,
+    pub message: String,
+    // Set when the entry records a bid the proxy placed
+    pub bid_id: Option<String>,
+    pub amount: Option<Money>,
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::chain::Chain;
+
+use super::{
+    Auction,
+    bid::Bid,
+    signature::{AuctionSignature, BidSignature},
+};
+
+// What auction and bid transactions put on the chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum LedgerMode {
+    // Only the hash of the record, the record itself lives in the DHT
+    #[default]
+    HashOnly,
+    // The complete record, the DHT copy is only a cache
+    FullRecords,
+}
+
+impl LedgerMode {
+    // Mode for the auction and its bids, private auctions are only ever committed by hash
+    // since their records are encrypted in the DHT
+    pub fn for_auction(self, auction: &Auction) -> LedgerMode {
+        match auction.is_private() {
+            true => LedgerMode::HashOnly,
+            false => self,
+        }
+    }
+
+    // Transaction committing to a new auction
+    pub fn auction_transaction(&self, auction: &Auction) -> Vec<u8> {
+        match self {
+            LedgerMode::HashOnly => AuctionSignature::new(auction.id.clone(), auction.get_hash())
+                .serialized_to_bytes()
+                .unwrap(),
+            LedgerMode::FullRecords => AuctionRecord::new(auction.clone())
+                .serialized_to_bytes()
+                .unwrap(),
+        }
+    }
+
+    // Transaction committing to a new bid
+    pub fn bid_transaction(&self, bid: &Bid) -> Vec<u8> {
+        match self {
+            LedgerMode::HashOnly => {
+                BidSignature::new(bid.id.clone(), bid.auction_id.clone(), bid.get_hash())
+                    .serialized_to_bytes()
+                    .unwrap()
+            }
+            LedgerMode::FullRecords => BidRecord::new(bid.clone()).serialized_to_bytes().unwrap(),
+        }
+    }
+}
+
+// Auction Record, the whole auction stored in a block
+// It commits to the auction like an `AuctionSignature` does, see `AuctionSignature::from_transaction`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AuctionRecord {
+    pub recorded_auction: Auction,
+}
+
+impl AuctionRecord {
+    pub fn new(auction: Auction) -> Self {
+        AuctionRecord {
+            recorded_auction: auction,
+        }
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+
+    pub fn get_signature(&self) -> AuctionSignature {
+        AuctionSignature::new(
+            self.recorded_auction.id.clone(),
+            self.recorded_auction.get_hash(),
+        )
+    }
+
+    // Every auction recorded in full on the chain, from the oldest to the newest
+    pub fn get_auctions(chain: &Chain) -> Vec<Auction> {
+        chain
+            .blocks_from_genesis()
+            .filter_map(|(_, block)| {
+                AuctionRecord::deserialized_from_bytes(block.get_transactions()).ok()
+            })
+            .map(|record| record.recorded_auction)
+            .collect()
+    }
+}
+
+// Bid Record, the whole bid stored in a block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BidRecord {
+    pub recorded_bid: Bid,
+}
+
+impl BidRecord {
+    pub fn new(bid: Bid) -> Self {
+        BidRecord { recorded_bid: bid }
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+
+    pub fn get_signature(&self) -> BidSignature {
+        BidSignature::new(
+            self.recorded_bid.id.clone(),
+            self.recorded_bid.auction_id.clone(),
+            self.recorded_bid.get_hash(),
+        )
+    }
+
+    // Every bid recorded in full on the chain, from the oldest to the newest
+    pub fn get_all_bids(chain: &Chain) -> Vec<Bid> {
+        chain
+            .blocks_from_genesis()
+            .filter_map(|(_, block)| {
+                BidRecord::deserialized_from_bytes(block.get_transactions()).ok()
+            })
+            .map(|record| record.recorded_bid)
+            .collect()
+    }
+
+    // Every bid of the auction recorded in full on the chain, from the oldest to the newest
+    pub fn get_bids(chain: &Chain, auction_id: &str) -> Vec<Bid> {
+        BidRecord::get_all_bids(chain)
+            .into_iter()
+            .filter(|bid| bid.auction_id == auction_id)
+            .collect()
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::chain::Chain;
+use crate::identity::{Identity, verify_signature};
+
+use super::{
+    Auction,
+    account::Accounts,
+    bid::Bid,
+    lifecycle::LifecycleState,
+    record::{AuctionRecord, BidRecord},
+    settlement::SettlementTransaction,
+    signature::{AuctionSignature, BidSignature},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub(crate) enum FeedbackRating {
+    #[default]
+    Positive,
+    Neutral,
+    Negative,
+}
+
+impl fmt::Display for FeedbackRating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FeedbackRating::Positive => "Positive",
+            FeedbackRating::Neutral => "Neutral",
+            FeedbackRating::Negative => "Negative",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Feedback transaction, posted by the seller or a winner of a settled auction about the other
+// Only the first feedback of each party about the other on an auction counts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FeedbackTransaction {
+    pub feedback_auction_id: String,
+    pub feedback_from: Vec<u8>,
+    pub feedback_to: Vec<u8>,
+    pub rating: FeedbackRating,
+    pub comment: String,
+    pub feedback_signature: Vec<u8>,
+}
+
+impl FeedbackTransaction {
+    pub fn new(
+        identity: &Identity,
+        auction_id: String,
+        to: Vec<u8>,
+        rating: FeedbackRating,
+        comment: String,
+    ) -> Self {
+        let from = identity.get_public_key();
+        let feedback_signature = identity.sign(&FeedbackTransaction::signed_message(
+            &auction_id,
+            &from,
+            &to,
+            rating,
+            &comment,
+        ));
+        FeedbackTransaction {
+            feedback_auction_id: auction_id,
+            feedback_from: from,
+            feedback_to: to,
+            rating,
+            comment,
+            feedback_signature,
+        }
+    }
+
+    // Bytes covered by the author's signature
+    fn signed_message(
+        auction_id: &str,
+        from: &[u8],
+        to: &[u8],
+        rating: FeedbackRating,
+        comment: &str,
+    ) -> Vec<u8> {
+        serde_json::to_vec(&("feedback", auction_id, from, to, rating, comment)).unwrap()
+    }
+
+    pub fn verify_signature(&self) -> bool {
+        let message = FeedbackTransaction::signed_message(
+            &self.feedback_auction_id,
+            &self.feedback_from,
+            &self.feedback_to,
+            self.rating,
+            &self.comment,
+        );
+        verify_signature(&self.feedback_from, &message, &self.feedback_signature)
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+}
+
+// Track record of an identity, as a seller and as a bidder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ReputationStats {
+    // As a seller: auctions settled, and auctions withdrawn
+    pub auctions_completed: u32,
+    pub auctions_cancelled: u32,
+    // As a bidder: auctions won and paid for from escrow, and closed auctions where the highest
+    // bid was theirs but their balance couldn't back it
+    pub auctions_won: u32,
+    pub unpaid_wins: u32,
+    // Sealed bids they committed to, whose record was still unknown once the auction was settled
+    pub unrevealed_sealed_bids: u32,
+    // Feedback received from counterparties
+    pub positive_feedback: u32,
+    pub neutral_feedback: u32,
+    pub negative_feedback: u32,
+}
+
+impl ReputationStats {
+    // Completed trades and positive feedback add a point, cancellations, unrevealed sealed bids
+    // and negative feedback take one, unpaid wins take two
+    pub fn score(&self) -> i64 {
+        (self.auctions_completed + self.auctions_won + self.positive_feedback) as i64
+            - (self.auctions_cancelled + self.unrevealed_sealed_bids + self.negative_feedback)
+                as i64
+            - 2 * self.unpaid_wins as i64
+    }
+}
+
+impl fmt::Display for ReputationStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "score {} ({} completed, {} cancelled, {} won, {} unpaid, {} unrevealed, feedback +{} ={} -{})",
+            self.score(),
+            self.auctions_completed,
+            self.auctions_cancelled,
+            self.auctions_won,
+            self.unpaid_wins,
+            self.unrevealed_sealed_bids,
+            self.positive_feedback,
+            self.neutral_feedback,
+            self.negative_feedback
+        )
+    }
+}
+
+// Reputation of every identity, computed from the confirmed transactions alone
+// Like `Accounts`, auctions and bids committed by hash only count when their records are given
+// It is queried in process (`get` and `ranking`), there is no RPC for it: any node can replay
+// the chain itself, and a peer's answer would have to be trusted
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Reputation {
+    stats: HashMap<Vec<u8>, ReputationStats>,
+}
+
+impl Reputation {
+    pub fn replay(chain: &Chain, auctions: &[Auction], bids: &[Bid]) -> Reputation {
+        let mut reputation = Reputation::default();
+        let accounts = Accounts::replay(chain, auctions, bids);
+        let known_bids: Vec<Bid> = bids
+            .iter()
+            .cloned()
+            .chain(BidRecord::get_all_bids(chain))
+            .chain(SettlementTransaction::get_revealed_bids(chain))
+            .collect();
+
+        let mut seen_auctions: HashSet<String> = HashSet::new();
+        for auction in auctions
+            .iter()
+            .cloned()
+            .chain(AuctionRecord::get_auctions(chain))
+        {
+            if !auction.has_valid_id() || !seen_auctions.insert(auction.id.clone()) {
+                continue; // Skip forged IDs and auctions already counted
+            }
+            let Some(lifecycle) = auction.lifecycle(chain) else {
+                continue;
+            };
+
+            if lifecycle.state == LifecycleState::Cancelled {
+                reputation.entry(&auction.seller_id).auctions_cancelled += 1;
+            }
+            if let Some(settled) = accounts.get_settled(&auction.id) {
+                reputation.entry(&auction.seller_id).auctions_completed += 1;
+                for winner_id in settled.winner_ids() {
+                    reputation.entry(&winner_id).auctions_won += 1;
+                }
+
+                // Sealed bids placed in the bidding window, which the settlement had to open,
+                // whose record is still nowhere to be found
+                let auction_height = AuctionSignature::get_confirmation(chain, &auction)
+                    .map_or(0, |confirmation| confirmation.height);
+                let mut judged: HashSet<String> = HashSet::new();
+                for (height, block) in chain.blocks_from_genesis() {
+                    let Some(signature) = BidSignature::from_transaction(block.get_transactions())
+                    else {
+                        continue;
+                    };
+                    // Only the first commitment to a bid counts, like its confirmation
+                    if signature.auction_id != auction.id
+                        || !judged.insert(signature.bid_id.clone())
+                        || height <= auction_height
+                        || !lifecycle.accepts_bids_at(height)
+                    {
+                        continue;
+                    }
+                    let revealed = known_bids.iter().any(|bid| signature.commits_to(bid));
+                    if let Some(bidder_id) = signature.get_sealed_bidder()
+                        && !revealed
+                    {
+                        reputation.entry(bidder_id).unrevealed_sealed_bids += 1;
+                    }
+                }
+            }
+
+            // The highest bid placed in the bidding window, whether its bidder could pay or not
+            // Only single items have one winner to compare it with
+            if lifecycle.closing_height.is_none() || auction.has_lots() {
+                continue;
+            }
+            let Some(auction_height) =
+                AuctionSignature::get_confirmation(chain, &auction).map(|c| c.height)
+            else {
+                continue;
+            };
+            let mut placed: Vec<(u64, &Bid)> = known_bids
+                .iter()
+                .filter(|bid| {
+                    bid.auction_id == auction.id
+                        && bid.has_valid_id()
+                        && bid.has_valid_signature()
+                        && auction.accepts_bidder(&bid.bidder_id)
+                        && bid.amount.get_currency() == auction.starting_price.get_currency()
+                })
+                .filter_map(|bid| {
+                    let height = BidSignature::get_confirmation(chain, bid)?.height;
+                    (height > auction_height && lifecycle.accepts_bids_at(height))
+                        .then_some((height, bid))
+                })
+                .collect();
+            placed.sort_by_key(|(height, _)| *height);
+            let highest_placed = placed
+                .into_iter()
+                .map(|(_, bid)| bid)
+                .reduce(|highest, bid| {
+                    if bid.amount > highest.amount {
+                        bid
+                    } else {
+                        highest
+                    }
+                });
+            let winning_amount = accounts
+                .get_accepted_bids(&auction.id)
+                .last()
+                .map(|bid| bid.amount);
+            if let Some(highest) = highest_placed
+                && winning_amount.is_none_or(|amount| highest.amount > amount)
+            {
+                reputation.entry(&highest.bidder_id).unpaid_wins += 1;
+            }
+        }
+
+        // Feedback between the seller and the winners, once the auction is settled
+        let mut given: HashSet<(String, Vec<u8>, Vec<u8>)> = HashSet::new();
+        for (height, block) in chain.blocks_from_genesis() {
+            let Ok(feedback) =
+                FeedbackTransaction::deserialized_from_bytes(block.get_transactions())
+            else {
+                continue;
+            };
+            let Some(settled) = accounts.get_settled(&feedback.feedback_auction_id) else {
+                continue;
+            };
+            let winner_ids = settled.winner_ids();
+            let counterparties = (feedback.feedback_from == settled.seller_id
+                && winner_ids.contains(&feedback.feedback_to))
+                || (winner_ids.contains(&feedback.feedback_from)
+                    && feedback.feedback_to == settled.seller_id);
+            if height <= settled.height
+                || !counterparties
+                || feedback.feedback_from == feedback.feedback_to
+                || !feedback.verify_signature()
+                || !given.insert((
+                    feedback.feedback_auction_id.clone(),
+                    feedback.feedback_from.clone(),
+                    feedback.feedback_to.clone(),
+                ))
+            {
+                continue;
+            }
+            let stats = reputation.entry(&feedback.feedback_to);
+            match feedback.rating {
+                FeedbackRating::Positive => stats.positive_feedback += 1,
+                FeedbackRating::Neutral => stats.neutral_feedback += 1,
+                FeedbackRating::Negative => stats.negative_feedback += 1,
+            }
+        }
+
+        reputation
+    }
+
+    fn entry(&mut self, identity: &[u8]) -> &mut ReputationStats {
+        self.stats.entry(identity.to_vec()).or_default()
+    }
+
+    // Stats of an identity, all zero if it has no history
+    pub fn get(&self, identity: &[u8]) -> ReputationStats {
+        self.stats.get(identity).copied().unwrap_or_default()
+    }
+
+    // Every identity with a history, best score first
+    pub fn ranking(&self) -> Vec<(Vec<u8>, ReputationStats)> {
+        let mut ranking: Vec<(Vec<u8>, ReputationStats)> = self
+            .stats
+            .iter()
+            .map(|(identity, stats)| (identity.clone(), *stats))
+            .collect();
+        ranking.sort_by(|a, b| b.1.score().cmp(&a.1.score()).then_with(|| a.0.cmp(&b.0)));
+        ranking
+    }
+}
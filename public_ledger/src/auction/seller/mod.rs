@@ -0,0 +1,63 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::identity::{Identity, verify_signature};
+
+// Changes a seller can make to their own auction, see `AuctionLifecycle` for when they apply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum SellerAction {
+    // Withdraws the auction, only before the first bid
+    Cancel,
+    // Replaces the item description, only before the first bid
+    AmendDescription { description: String },
+    // Moves the ending time later, within `MAX_DEADLINE_EXTENSIONS` and `MAX_TOTAL_EXTENSION`
+    ExtendDeadline { new_ending_time: u64 },
+}
+
+// Seller transaction, an action on an auction signed with the key the auction was created with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SellerTransaction {
+    pub target_auction_id: String,
+    pub action: SellerAction,
+    // Makes every transaction unique, so identical actions get distinct signatures
+    pub nonce: u64,
+    pub seller_signature: Vec<u8>,
+}
+
+impl SellerTransaction {
+    pub fn new(identity: &Identity, auction_id: String, action: SellerAction) -> Self {
+        let nonce = rand::rng().random();
+        let seller_signature = identity.sign(&SellerTransaction::signed_message(
+            &auction_id,
+            &action,
+            nonce,
+        ));
+        SellerTransaction {
+            target_auction_id: auction_id,
+            action,
+            nonce,
+            seller_signature,
+        }
+    }
+
+    // Bytes covered by the seller's signature
+    fn signed_message(auction_id: &str, action: &SellerAction, nonce: u64) -> Vec<u8> {
+        serde_json::to_vec(&("seller", auction_id, action, nonce)).unwrap()
+    }
+
+    // Checks the transaction was signed by the given seller (`Auction::seller_id`)
+    pub fn verify_signature(&self, seller_id: &[u8]) -> bool {
+        let message =
+            SellerTransaction::signed_message(&self.target_auction_id, &self.action, self.nonce);
+        verify_signature(seller_id, &message, &self.seller_signature)
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::chain::{Chain, Confirmation};
+
+use super::{
+    Auction, account::Accounts, bid::Bid, lot::LotResult, money::Money, record::BidRecord,
+    signature::BidSignature,
+};
+
+// Rule set applied by `BidSignature::verify_bids`: ascending bids ordered by confirming block,
+// each backed by the bidder's balance in escrow, closed once the median time past passes the
+// ending time, the highest bid wins and pays its own amount
+pub const ENGLISH_AUCTION_RULES: &str = "english-first-price-v1";
+
+// Rule set of lot auctions: bids on a lot are ranked by unit price, then by confirming block,
+// each backed by its quantity times its unit price in escrow, and outranked bids are released
+// Once closed, units go to the bids in rank order (the last one may get fewer than it asked for)
+// and every unit of the lot pays the price of the lowest bid that got units
+pub const UNIFORM_PRICE_RULES: &str = "uniform-price-v1";
+
+// Blocks after the closing block within which a settlement must be confirmed, past them the
+// auction lapses and every escrow goes back to its bidder, so a seller who never settles can't
+// keep the winner's money locked
+pub const SETTLEMENT_PERIOD: u64 = 100;
+
+// Rule set an auction is settled under
+pub fn rule_set(auction: &Auction) -> &'static str {
+    match auction.has_lots() {
+        true => UNIFORM_PRICE_RULES,
+        false => ENGLISH_AUCTION_RULES,
+    }
+}
+
+// Settlement transaction, finalises an auction by recording its winner on the chain
+// Anyone can mine it once the auction is over, and anyone can check it against the chain alone:
+// it carries the records of the bids that are only committed by hash or sealed, and once it is
+// mined the auction's bids only count from the chain (see `Accounts::replay`), so a node missing
+// a bid record from the DHT reaches the same verdict as the others
+// A bidder left out can still record their bid in full, which overturns the settlement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SettlementTransaction {
+    pub settled_auction_id: String,
+    // Winning bid, None if the auction closed without valid bids
+    pub winning_bid_id: Option<String>,
+    pub winning_bid_hash: Option<Vec<u8>>,
+    pub winner_id: Option<Vec<u8>>,
+    pub price_paid: Option<Money>,
+    pub rule_set: String,
+    // Outcome of every lot of a lot auction, the winning bid fields stay empty for those
+    #[serde(default)]
+    pub lot_results: Vec<LotResult>,
+    // Records of the auction's committed bids not recorded in full on the chain
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revealed_bids: Vec<Bid>,
+}
+
+impl SettlementTransaction {
+    // Computes the settlement of an auction from the chain and the bids fetched from the DHT
+    // The settlement block must be mined after the ending time, so the auction is closed by the
+    // time (or in the block where) it gets recorded
+    pub fn new(chain: &Chain, auction: &Auction, bids: Vec<Bid>) -> SettlementTransaction {
+        let accounts = Accounts::replay_with_bids(chain, std::slice::from_ref(auction), &bids);
+        let recorded_bids = BidRecord::get_bids(chain, &auction.id);
+        let mut revealed_bids: Vec<Bid> = Vec::new();
+        for bid in bids {
+            if bid.auction_id == auction.id
+                && !recorded_bids.contains(&bid)
+                && !revealed_bids.contains(&bid)
+                && BidSignature::get_confirmation(chain, &bid).is_some()
+            {
+                revealed_bids.push(bid);
+            }
+        }
+        SettlementTransaction {
+            revealed_bids,
+            ..SettlementTransaction::from_lot_results(auction, accounts.get_lot_results(auction))
+        }
+    }
+
+    // Settlement recording the cleared lots, a single item auction names its only fill as the
+    // winning bid, which paid its own amount
+    pub fn from_lot_results(auction: &Auction, lot_results: Vec<LotResult>) -> Self {
+        let mut settlement = SettlementTransaction {
+            settled_auction_id: auction.id.clone(),
+            winning_bid_id: None,
+            winning_bid_hash: None,
+            winner_id: None,
+            price_paid: None,
+            rule_set: rule_set(auction).to_string(),
+            lot_results: Vec::new(),
+            revealed_bids: Vec::new(),
+        };
+        if auction.has_lots() {
+            settlement.lot_results = lot_results;
+        } else if let Some(result) = lot_results.first()
+            && let Some(fill) = result.fills.first()
+        {
+            settlement.winning_bid_id = Some(fill.bid_id.clone());
+            settlement.winning_bid_hash = Some(fill.bid_hash.clone());
+            settlement.winner_id = Some(fill.winner_id.clone());
+            settlement.price_paid = result.clearing_price;
+        }
+        settlement
+    }
+
+    // Whether the settlement records the given outcome, whatever bid records it reveals
+    pub fn records(&self, auction: &Auction, lot_results: Vec<LotResult>) -> bool {
+        *self
+            == SettlementTransaction {
+                revealed_bids: self.revealed_bids.clone(),
+                ..SettlementTransaction::from_lot_results(auction, lot_results)
+            }
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+
+    // Every settlement recorded for the auction, from the oldest to the newest
+    pub fn get_settlements(
+        chain: &Chain,
+        auction_id: &str,
+    ) -> Vec<(SettlementTransaction, Confirmation)> {
+        chain
+            .blocks_from_genesis()
+            .filter_map(|(height, block)| {
+                let settlement =
+                    SettlementTransaction::deserialized_from_bytes(block.get_transactions())
+                        .ok()?;
+                (settlement.settled_auction_id == auction_id)
+                    .then(|| (settlement, Confirmation::new(height, block)))
+            })
+            .collect()
+    }
+
+    // Every bid record revealed by a settlement on the chain, from the oldest to the newest
+    pub fn get_revealed_bids(chain: &Chain) -> Vec<Bid> {
+        chain
+            .blocks_from_genesis()
+            .filter_map(|(_, block)| {
+                SettlementTransaction::deserialized_from_bytes(block.get_transactions()).ok()
+            })
+            .flat_map(|settlement| settlement.revealed_bids)
+            .collect()
+    }
+
+    // Checks the settlement against the chain: it must be confirmed from the closing block on,
+    // within the settlement period, and name the winner the rule set picks from the bids known
+    // from the chain
+    pub fn verify(&self, chain: &Chain, confirmation: &Confirmation, auction: &Auction) -> bool {
+        if self.rule_set != rule_set(auction) || self.settled_auction_id != auction.id {
+            return false;
+        }
+        match auction.closing_height(chain) {
+            Some(closing_height)
+                if confirmation.height >= closing_height
+                    && confirmation.height < closing_height + SETTLEMENT_PERIOD => {}
+            _ => return false,
+        }
+        let accounts = Accounts::replay(chain, std::slice::from_ref(auction), &[]);
+        self.records(auction, accounts.get_lot_results(auction))
+    }
+
+    // The first settlement of the auction that verifies, later ones are ignored
+    pub fn get_valid_settlement(
+        chain: &Chain,
+        auction: &Auction,
+    ) -> Option<(SettlementTransaction, Confirmation)> {
+        SettlementTransaction::get_settlements(chain, &auction.id)
+            .into_iter()
+            .find(|(settlement, confirmation)| settlement.verify(chain, confirmation, auction))
+    }
+}
@@ -1,9 +1,15 @@
-use crate::blockchain::chain::Chain;
+use crate::blockchain::chain::{Chain, Confirmation};
+use crate::identity::{Identity, verify_signature};
 use serde::{Deserialize, Serialize};
 
-use super::{Auction, bid::Bid};
+use super::{
+    Auction,
+    account::Accounts,
+    bid::Bid,
+    record::{AuctionRecord, BidRecord},
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub(crate) struct AuctionSignature {
     pub auction_id: String,
     pub auction_hash: Vec<u8>,
@@ -25,24 +31,57 @@ impl AuctionSignature {
         serde_json::to_vec(self)
     }
 
+    // Commitment to an auction carried by a transaction, the signature itself or the one
+    // implied by a full `AuctionRecord`
+    pub fn from_transaction(bytes: &[u8]) -> Option<AuctionSignature> {
+        match AuctionSignature::deserialized_from_bytes(bytes) {
+            Ok(signature) => Some(signature),
+            Err(_) => AuctionRecord::deserialized_from_bytes(bytes)
+                .ok()
+                .map(|record| record.get_signature()),
+        }
+    }
+
     pub fn get_signatures(chain: &Chain) -> Vec<AuctionSignature> {
         chain
             .get_blocks()
             .iter()
-            .filter_map(|block| {
-                AuctionSignature::deserialized_from_bytes(block.get_transactions()).ok()
-            })
+            .filter_map(|block| AuctionSignature::from_transaction(block.get_transactions()))
             .collect()
     }
 
+    // IDs of every auction recorded on the chain, in the order they appear
+    pub fn get_auction_ids(chain: &Chain) -> Vec<String> {
+        let mut auction_ids: Vec<String> = Vec::new();
+        for signature in AuctionSignature::get_signatures(chain) {
+            if !auction_ids.contains(&signature.auction_id) {
+                auction_ids.push(signature.auction_id);
+            }
+        }
+        auction_ids
+    }
+
+    // Block that first recorded this exact auction
+    pub fn get_confirmation(chain: &Chain, auction: &Auction) -> Option<Confirmation> {
+        let auction_hash = auction.get_hash();
+        chain.find_confirmation(|block| {
+            AuctionSignature::from_transaction(block.get_transactions()).is_some_and(|signature| {
+                signature.auction_id == auction.id && signature.auction_hash == auction_hash
+            })
+        })
+    }
+
     pub fn verify_auctions(
         signatures: Vec<AuctionSignature>,
         auctions: Vec<Auction>,
     ) -> Vec<Auction> {
         let mut verified_auctions = Vec::new();
-        for mut auction in auctions {
+        for auction in auctions {
+            if !auction.has_valid_id() {
+                continue; // Skip auctions whose ID wasn't derived from their seller and nonce
+            }
             for signature in &signatures {
-                if auction.id.to_string() == signature.auction_id
+                if auction.id == signature.auction_id
                     && auction.get_hash() == signature.auction_hash
                 {
                     verified_auctions.push(auction.clone());
@@ -55,15 +94,59 @@ impl AuctionSignature {
 }
 
 // Bid Signature
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub(crate) struct BidSignature {
     pub bid_id: String,
+    pub auction_id: String,
     pub bid_hash: Vec<u8>,
+    // Bidder of a sealed bid and their signature of the commitment, empty for other bids
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sealed_bidder_id: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sealed_bidder_signature: Vec<u8>,
 }
 
 impl BidSignature {
-    pub fn new(bid_id: String, bid_hash: Vec<u8>) -> Self {
-        BidSignature { bid_id, bid_hash }
+    pub fn new(bid_id: String, auction_id: String, bid_hash: Vec<u8>) -> Self {
+        BidSignature {
+            bid_id,
+            auction_id,
+            bid_hash,
+            sealed_bidder_id: Vec::new(),
+            sealed_bidder_signature: Vec::new(),
+        }
+    }
+
+    // Commitment to a sealed bid, one whose record stays encrypted in the DHT (bids on private
+    // auctions), signed by the bidder so a bid they never reveal counts against them
+    pub fn sealed(identity: &Identity, bid: &Bid) -> Self {
+        let mut signature =
+            BidSignature::new(bid.id.clone(), bid.auction_id.clone(), bid.get_hash());
+        signature.sealed_bidder_id = identity.get_public_key();
+        signature.sealed_bidder_signature = identity.sign(&signature.sealed_message());
+        signature
+    }
+
+    // Bytes covered by the bidder's signature of a sealed bid commitment
+    fn sealed_message(&self) -> Vec<u8> {
+        serde_json::to_vec(&("sealed bid", &self.bid_id, &self.auction_id, &self.bid_hash)).unwrap()
+    }
+
+    // Bidder who signed the commitment to a sealed bid, None for other bids
+    pub fn get_sealed_bidder(&self) -> Option<&[u8]> {
+        let signed = verify_signature(
+            &self.sealed_bidder_id,
+            &self.sealed_message(),
+            &self.sealed_bidder_signature,
+        );
+        (!self.sealed_bidder_id.is_empty() && signed).then_some(self.sealed_bidder_id.as_slice())
+    }
+
+    // Whether this commits to exactly the given bid
+    pub fn commits_to(&self, bid: &Bid) -> bool {
+        self.bid_id == bid.id
+            && self.auction_id == bid.auction_id
+            && self.bid_hash == bid.get_hash()
     }
 
     pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
@@ -74,61 +157,61 @@ impl BidSignature {
         serde_json::to_vec(self)
     }
 
+    // Commitment to a bid carried by a transaction, the signature itself or the one implied
+    // by a full `BidRecord`
+    pub fn from_transaction(bytes: &[u8]) -> Option<BidSignature> {
+        match BidSignature::deserialized_from_bytes(bytes) {
+            Ok(signature) => Some(signature),
+            Err(_) => BidRecord::deserialized_from_bytes(bytes)
+                .ok()
+                .map(|record| record.get_signature()),
+        }
+    }
+
     pub fn get_signatures(chain: &Chain) -> Vec<BidSignature> {
         chain
             .get_blocks()
             .iter()
-            .filter_map(|block| {
-                BidSignature::deserialized_from_bytes(block.get_transactions()).ok()
-            })
+            .filter_map(|block| BidSignature::from_transaction(block.get_transactions()))
             .collect()
     }
 
-    pub fn verify_bids(
-        signatures: Vec<BidSignature>,
-        bids: Vec<Bid>,
-        auction: Auction,
-    ) -> Vec<Bid> {
-        let mut verified_bids: Vec<Bid> = Vec::new();
-
-        // Sort bids by timestamp
-        let mut sorted_bids = bids;
-        sorted_bids.sort_by_key(|b| b.timestamp);
-
-        let mut highest_bid = 0.0; // Track the highest valid bid so far
-
-        for bid in sorted_bids {
-            if bid.timestamp > auction.ending_time {
-                continue; // Skip bids that are after the auction end time
-            }
-
-            // If bid is smaller or equal to the highest valid bid seen so far, invalidate
-            if bid.amount <= highest_bid {
-                continue; // Skip this bid, it's not valid
-            }
-
-            // Check if the bid's signature is valid
-            let mut is_valid = false;
-            for signature in &signatures {
-                if bid.id.to_string() == signature.bid_id && bid.get_hash() == signature.bid_hash {
-                    is_valid = true;
-                    break;
-                }
-            }
-
-            if is_valid {
-                // Add the bid to the valid list and update the highest bid
-                verified_bids.push(bid.clone());
-                highest_bid = bid.amount;
+    // IDs of every bid recorded on the chain for the given auction
+    pub fn get_bid_ids(chain: &Chain, auction_id: &str) -> Vec<String> {
+        let mut bid_ids: Vec<String> = Vec::new();
+        for signature in BidSignature::get_signatures(chain) {
+            if signature.auction_id == auction_id && !bid_ids.contains(&signature.bid_id) {
+                bid_ids.push(signature.bid_id);
             }
         }
+        bid_ids
+    }
 
-        verified_bids
+    // Block that first recorded this exact bid
+    pub fn get_confirmation(chain: &Chain, bid: &Bid) -> Option<Confirmation> {
+        chain.find_confirmation(|block| {
+            BidSignature::from_transaction(block.get_transactions())
+                .is_some_and(|signature| signature.commits_to(bid))
+        })
     }
 
+    // Bids are ordered by the height of the block that confirmed them and checked against the
+    // auction's lifecycle (opening, closing and cancellation blocks), never against the bidder's own clock (`Bid::timestamp`)
+    // Each accepted bid locks the bidder's money in escrow, bids beyond their available balance
+    // are rejected (see `Accounts::replay`)
+    pub fn verify_bids(chain: &Chain, bids: Vec<Bid>, auction: Auction) -> Vec<Bid> {
+        let auction_id = auction.id.clone();
+        Accounts::replay(chain, &[auction], &bids).get_accepted_bids(&auction_id)
+    }
+
+    // Highest verified bid, equal bids go to the first confirmed (see `Accounts`)
     pub fn winning_bid(verified_bids: Vec<Bid>) -> Option<Bid> {
         verified_bids
             .into_iter()
-            .max_by(|a, b| a.amount.partial_cmp(&b.amount).unwrap())
+            // Verified bids share the auction's currency, so the comparison is always exact
+            .reduce(|winning, bid| match bid.amount > winning.amount {
+                true => bid,
+                false => winning,
+            })
     }
 }
@@ -0,0 +1,62 @@
+use crate::blockchain::chain::Chain;
+
+use super::{
+    Auction,
+    bid::Bid,
+    lifecycle::AuctionLifecycle,
+    record::{AuctionRecord, BidRecord},
+    signature::BidSignature,
+};
+
+// An auction rebuilt from the chain alone
+#[derive(Debug, Clone)]
+pub(crate) struct ProjectedAuction {
+    pub auction: Auction,
+    pub lifecycle: AuctionLifecycle,
+    // Every bid recorded in full for the auction, and the ones valid under the rule set
+    pub bids: Vec<Bid>,
+    pub valid_bids: Vec<Bid>,
+    // Highest valid bid, the winner once the auction is closed
+    pub winning_bid: Option<Bid>,
+}
+
+// Projection of the chain into the current auctions, bids and winners
+// Only auctions and bids recorded in full (`LedgerMode::FullRecords`) can be rebuilt this way,
+// hash only commitments still need their record from the DHT
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AuctionState {
+    auctions: Vec<ProjectedAuction>,
+}
+
+impl AuctionState {
+    // Replays the chain from the genesis block
+    pub fn replay(chain: &Chain) -> AuctionState {
+        let mut auctions: Vec<ProjectedAuction> = Vec::new();
+        for auction in AuctionRecord::get_auctions(chain) {
+            if !auction.has_valid_id() || auctions.iter().any(|p| p.auction.id == auction.id) {
+                continue; // Skip forged IDs and records of an auction already projected
+            }
+            let bids = BidRecord::get_bids(chain, &auction.id);
+            let Some(lifecycle) = AuctionLifecycle::replay(chain, &auction) else {
+                continue;
+            };
+            let lifecycle = lifecycle.with_settlement(chain, &auction);
+            let valid_bids = BidSignature::verify_bids(chain, bids.clone(), auction.clone());
+            let winning_bid = BidSignature::winning_bid(valid_bids.clone());
+            auctions.push(ProjectedAuction {
+                auction,
+                lifecycle,
+                bids,
+                valid_bids,
+                winning_bid,
+            });
+        }
+        AuctionState { auctions }
+    }
+
+    pub fn get_auction(&self, auction_id: &str) -> Option<&ProjectedAuction> {
+        self.auctions
+            .iter()
+            .find(|projected| projected.auction.id == auction_id)
+    }
+}
@@ -65,17 +65,14 @@ impl Block {
         Block { header, body }
     }
 
-    pub fn genesis() -> Block {
+    // First block of a chain, its transaction is the genesis allocation
+    pub fn genesis(transaction: Vec<u8>) -> Block {
         Block {
             header: block_header::BlockHeader::genesis(),
-            body: block_body::BlockBody::new(vec![]),
+            body: block_body::BlockBody::new(transaction),
         }
     }
 
-    pub fn deserialized(serialized: &str) -> Self {
-        serde_json::from_str(serialized).unwrap()
-    }
-
     pub fn serialized(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
@@ -37,15 +37,13 @@ impl BlockHeader {
         self.nonce = nonce;
     }
 
-    pub fn new(prev_hash: Vec<u8>) -> BlockHeader {
+    // Timestamps are explicit, miners pick one that respects the chain's median time past
+    pub fn new_with_timestamp(prev_hash: Vec<u8>, timestamp: u64) -> BlockHeader {
         BlockHeader {
             prev_hash,
             nonce: 0,
             difficulty: 1,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            timestamp,
         }
     }
 
@@ -1,10 +1,32 @@
 use super::block::Block;
+use super::params::{MAX_FUTURE_BLOCK_TIME, MEDIAN_TIME_SPAN};
+
+// The blocks are kept in the order they are fetched from the DHT: the latest block first
+// and the genesis block last, heights are counted from the genesis block (height 0)
 
 #[derive(Default, Clone)]
 pub(crate) struct Chain {
     blocks: Vec<Block>,
 }
 
+// Where and when a transaction was confirmed in the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Confirmation {
+    pub height: u64,
+    pub timestamp: u64,
+    pub block_hash: Vec<u8>,
+}
+
+impl Confirmation {
+    pub fn new(height: u64, block: &Block) -> Confirmation {
+        Confirmation {
+            height,
+            timestamp: block.header.get_timestamp(),
+            block_hash: block.get_hash(),
+        }
+    }
+}
+
 impl Chain {
     // Creates a new chain with the genesis block
     pub fn new() -> Chain {
@@ -30,4 +52,83 @@ impl Chain {
     pub fn get_blocks(&self) -> &Vec<Block> {
         &self.blocks
     }
+
+    // Returns the block at the given height (0 is the genesis block)
+    pub fn get_block_at_height(&self, height: u64) -> Option<&Block> {
+        let index = (self.blocks.len() as u64).checked_sub(height + 1)?;
+        self.blocks.get(index as usize)
+    }
+
+    // Every block links to the previous one, so two chains of the same length and tip hold the
+    // same blocks
+    pub fn has_same_tip(&self, other: &Chain) -> bool {
+        let tip = |chain: &Chain| chain.blocks.first().map(|block| block.get_hash());
+        self.blocks.len() == other.blocks.len() && tip(self) == tip(other)
+    }
+
+    // Blocks from one height to another (both included), the lower one first
+    pub fn get_blocks_between(&self, from_height: u64, to_height: u64) -> Vec<Block> {
+        (from_height..=to_height)
+            .filter_map(|height| self.get_block_at_height(height).cloned())
+            .collect()
+    }
+
+    // Iterates over the blocks from the genesis block to the latest one, with their heights
+    pub fn blocks_from_genesis(&self) -> impl Iterator<Item = (u64, &Block)> {
+        self.blocks
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(height, block)| (height as u64, block))
+    }
+
+    // Median timestamp of the MEDIAN_TIME_SPAN blocks ending at the given height
+    pub fn median_time_past(&self, height: u64) -> u64 {
+        let first = (height + 1).saturating_sub(MEDIAN_TIME_SPAN as u64);
+        let mut timestamps: Vec<u64> = (first..=height)
+            .filter_map(|h| self.get_block_at_height(h))
+            .map(|block| block.header.get_timestamp())
+            .collect();
+        if timestamps.is_empty() {
+            return 0;
+        }
+        timestamps.sort();
+        timestamps[timestamps.len() / 2]
+    }
+
+    // Consensus rule on block timestamps: every block after the genesis must be stamped after the
+    // median time past of its predecessors, and no new block may be too far ahead of the local
+    // clock
+    // Blocks of the chain already verified keep their verdict, so a node whose clock drifts
+    // doesn't turn on blocks it accepted
+    pub fn verify_timestamps(&self, now: u64, verified: &Chain) -> bool {
+        self.blocks_from_genesis().all(|(height, block)| {
+            let timestamp = block.header.get_timestamp();
+            let is_verified = verified
+                .get_block_at_height(height)
+                .is_some_and(|verified_block| verified_block.get_hash() == block.get_hash());
+            if !is_verified && timestamp > now + MAX_FUTURE_BLOCK_TIME {
+                return false;
+            }
+            height == 0 || timestamp > self.median_time_past(height - 1)
+        })
+    }
+
+    // Smallest valid timestamp for the next block, the local clock unless it is behind the chain
+    pub fn get_next_timestamp(&self, now: u64) -> u64 {
+        match self.blocks.len() {
+            0 => now,
+            len => now.max(self.median_time_past(len as u64 - 1) + 1),
+        }
+    }
+
+    // Returns the lowest block that satisfies the predicate
+    pub fn find_confirmation<F>(&self, predicate: F) -> Option<Confirmation>
+    where
+        F: Fn(&Block) -> bool,
+    {
+        self.blocks_from_genesis()
+            .find(|(_, block)| predicate(block))
+            .map(|(height, block)| Confirmation::new(height, block))
+    }
 }
@@ -0,0 +1,8 @@
+// MEDIAN_TIME_SPAN is the number of blocks whose timestamps are used to compute the median time past.
+// A new block's timestamp must be strictly greater than the median of the previous MEDIAN_TIME_SPAN blocks,
+// which bounds how far a miner can backdate a block (and the bids inside it).
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+// MAX_FUTURE_BLOCK_TIME is how many seconds ahead of the local clock a block timestamp may be.
+// Blocks further in the future are rejected until the local clock catches up.
+pub const MAX_FUTURE_BLOCK_TIME: u64 = 2 * 60 * 60;
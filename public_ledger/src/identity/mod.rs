@@ -0,0 +1,110 @@
+//! Cryptographic identity of the local user
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use curve25519_dalek::montgomery::MontgomeryPoint;
+use ring::digest::{SHA512, digest};
+use ring::rand::SystemRandom;
+use ring::signature::{self, Ed25519KeyPair, KeyPair};
+
+// Ed25519 key pair used to sign the transactions the user authors (sellers, bidders, ...)
+// The public key is what other nodes see as the seller or bidder ID
+#[derive(Clone)]
+pub(crate) struct Identity {
+    key_pair: Arc<Ed25519KeyPair>,
+    // X25519 secret matching the Ed25519 key, so others can encrypt to our public key
+    agreement_secret: [u8; 32],
+}
+
+impl Identity {
+    // Generates a new random identity
+    pub fn generate() -> Identity {
+        Identity::from_pkcs8(&generate_pkcs8()).expect("Generated key pair is invalid")
+    }
+
+    // Loads the identity saved at the path, or generates one and saves it there, so the user
+    // keeps their auctions, invitations and balance across restarts
+    pub fn load_or_generate(path: &Path) -> io::Result<Identity> {
+        match fs::read(path) {
+            Ok(pkcs8) => Identity::from_pkcs8(&pkcs8)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let pkcs8 = generate_pkcs8();
+                write_private(path, &pkcs8)?;
+                Ok(Identity::from_pkcs8(&pkcs8).expect("Generated key pair is invalid"))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Loads an identity from a PKCS#8 encoded key pair
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Identity, ring::error::KeyRejected> {
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8)?;
+        // The Ed25519 secret scalar is the first half of the seed's SHA-512, the same scalar
+        // works on the Montgomery form of the curve
+        let seed = pkcs8_seed(pkcs8).expect("Accepted PKCS#8 documents carry the seed");
+        let mut agreement_secret = [0; 32];
+        agreement_secret.copy_from_slice(&digest(&SHA512, seed).as_ref()[0..32]);
+        Ok(Identity {
+            key_pair: Arc::new(key_pair),
+            agreement_secret,
+        })
+    }
+
+    pub fn get_public_key(&self) -> Vec<u8> {
+        self.key_pair.public_key().as_ref().to_vec()
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.key_pair.sign(message).as_ref().to_vec()
+    }
+
+    // X25519 shared secret with the owner of the agreement key, None for low order keys
+    pub fn agree(&self, agreement_key: &[u8; 32]) -> Option<[u8; 32]> {
+        let shared = MontgomeryPoint(*agreement_key).mul_clamped(self.agreement_secret);
+        (shared.to_bytes() != [0; 32]).then(|| shared.to_bytes())
+    }
+}
+
+fn generate_pkcs8() -> Vec<u8> {
+    let rng = SystemRandom::new();
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).expect("Failed to generate key pair");
+    pkcs8.as_ref().to_vec()
+}
+
+// Writes a new file only the user can read, never over an existing one
+fn write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(bytes)
+}
+
+// Ed25519 seed of a PKCS#8 document, ring only accepts the RFC 8410 layouts (v1 and v2) where
+// it follows the private key OCTET STRING headers
+fn pkcs8_seed(pkcs8: &[u8]) -> Option<&[u8]> {
+    if pkcs8.get(12..16)? != [0x04, 0x22, 0x04, 0x20] {
+        return None;
+    }
+    pkcs8.get(16..48)
+}
+
+// X25519 key matching an Ed25519 public key (its Montgomery form), what auction keys are
+// encrypted to, None if the bytes aren't a point of the curve
+pub fn agreement_key(public_key: &[u8]) -> Option<[u8; 32]> {
+    let point = CompressedEdwardsY::from_slice(public_key)
+        .ok()?
+        .decompress()?;
+    Some(point.to_montgomery().to_bytes())
+}
+
+// Checks an Ed25519 signature made by the owner of the public key
+pub fn verify_signature(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    signature::UnparsedPublicKey::new(&signature::ED25519, public_key)
+        .verify(message, signature)
+        .is_ok()
+}
@@ -0,0 +1,31 @@
+use crate::blockchain::block::Block;
+
+use super::key::Key;
+use super::namespace::Namespace;
+
+// Content-addressed namespace: values stored under a key derived from their own hash, attachment
+// chunks under the truncated SHA-256 of their bytes and blocks under the key of their block hash
+// Such a value is immutable, nothing else is ever stored under its key, and lookups of its key
+// only accept a value that hashes to it
+
+// Key of a value in the content-addressed namespace
+pub fn content_key(value: &[u8]) -> Key {
+    Key::hash(value)
+}
+
+// Key of a block, derived from its hash which covers the whole block
+pub fn block_key(block_hash: &[u8]) -> Key {
+    Namespace::Block.key(&hex::encode(&block_hash[..20]))
+}
+
+// Whether the value is the content the key addresses
+pub fn is_content_of(key: &Key, value: &[u8]) -> bool {
+    content_key(value) == *key
+        || serde_json::from_slice::<Block>(value)
+            .is_ok_and(|block| block_key(&block.get_hash()) == *key)
+}
+
+// Blocks only go under their own key, so a block under another key is a forgery
+pub fn is_misplaced_block(key: &Key, value: &[u8]) -> bool {
+    serde_json::from_slice::<Block>(value).is_ok() && !is_content_of(key, value)
+}
@@ -0,0 +1,79 @@
+use ring::digest::{Context, SHA256};
+
+use super::routing_table::node_id;
+
+// 160-bit key of the DHT, node IDs and the keys of stored values live in the same XOR metric space
+// Keys of stored values come from a `Namespace`, or from the value itself for content-addressed ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct Key([u8; 20]);
+
+impl Key {
+    pub fn random() -> Key {
+        Key(node_id::generate_node_id())
+    }
+
+    // Key sent by another node, None unless it is exactly 160 bits
+    pub fn from_slice(bytes: &[u8]) -> Option<Key> {
+        Some(Key(bytes.try_into().ok()?))
+    }
+
+    // Truncated SHA-256 of a name
+    pub fn from_name(name: &str) -> Key {
+        Key::hash(name.as_bytes())
+    }
+
+    // Truncated SHA-256 of some bytes
+    pub fn hash(bytes: &[u8]) -> Key {
+        let mut context = Context::new(&SHA256);
+        context.update(bytes);
+        let mut key = [0u8; 20];
+        key.copy_from_slice(&context.finish().as_ref()[..20]);
+        Key(key)
+    }
+
+    pub fn to_vec(self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    // XOR distance, keys compare as 160-bit numbers so the smaller distance is the closer key
+    pub fn distance(&self, other: &Key) -> Key {
+        Key(node_id::distance(&self.0, &other.0))
+    }
+
+    // Index of the k-bucket the other key falls in seen from this one, the first bit where they
+    // differ, None for the same key
+    pub fn bucket_index(&self, other: &Key) -> Option<u8> {
+        match node_id::find_k_bucket_index(&self.distance(other).0) {
+            160 => None,
+            index => Some(index as u8),
+        }
+    }
+
+    // Random key in the k-bucket of the index seen from this one: the same bits before the index,
+    // the bit at the index flipped and random bits after it
+    pub fn random_in_bucket(&self, index: u8) -> Key {
+        let random = Key::random();
+        let mut key = self.0;
+        for bit in index as usize..160 {
+            let (byte, mask) = (bit / 8, 0x80 >> (bit % 8));
+            let value = match bit == index as usize {
+                true => !self.0[byte] & mask,
+                false => random.0[byte] & mask,
+            };
+            key[byte] = (key[byte] & !mask) | value;
+        }
+        Key(key)
+    }
+}
+
+impl From<[u8; 20]> for Key {
+    fn from(bytes: [u8; 20]) -> Key {
+        Key(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Key {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
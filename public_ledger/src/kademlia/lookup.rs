@@ -0,0 +1,344 @@
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use tokio::sync::RwLock;
+
+use super::communication::{FindNodeRequest, FindValueRequest};
+use super::content::is_content_of;
+use super::key::Key;
+use super::pool::ClientPool;
+use super::record::MutableRecord;
+use super::routing_table::RoutingTable;
+use super::routing_table::node::Node;
+use super::routing_table::node_id::generate_node_id;
+use super::routing_table::params::{ALPHA, MAX_BUCKET_SIZE, RPC_TIMEOUT};
+use super::{is_unreachable, record_rpc};
+
+// Iterative lookup engine behind `find_node_dht` and `find_value_dht`
+// Candidates are kept in a shortlist sorted by XOR distance to the key, ALPHA requests are in
+// flight at a time, always to the closest candidates not asked yet, and the lookup ends once the
+// k closest candidates left have all responded, or a value is found
+// A lookup that finds mutable records asks all the k closest nodes and keeps the freshest record
+
+// What a lookup asks the nodes for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LookupKind {
+    Node,
+    Value,
+    // A value of the content-addressed namespace, nodes answering with another value failed
+    Content,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CandidateState {
+    NotQueried,
+    InFlight,
+    Responded,
+    // Unreachable, timed out or gave an error, never asked again
+    Failed,
+}
+
+struct Candidate {
+    node: Node,
+    state: CandidateState,
+}
+
+// Candidates of a lookup, closest to the key first
+pub(crate) struct Shortlist {
+    key: Key,
+    // This node, never a candidate
+    curr_node_id: Key,
+    candidates: Vec<Candidate>,
+}
+
+impl Shortlist {
+    pub fn new(key: Key, curr_node_id: Key, nodes: Vec<Node>) -> Shortlist {
+        let mut shortlist = Shortlist {
+            key,
+            curr_node_id,
+            candidates: Vec::new(),
+        };
+        shortlist.add(nodes);
+        shortlist
+    }
+
+    // Adds the nodes not in the shortlist yet
+    pub fn add(&mut self, nodes: Vec<Node>) {
+        for node in nodes {
+            let known = self
+                .candidates
+                .iter()
+                .any(|candidate| candidate.node.get_id() == node.get_id());
+            if !known && *node.get_id() != self.curr_node_id {
+                self.candidates.push(Candidate {
+                    node,
+                    state: CandidateState::NotQueried,
+                });
+            }
+        }
+        let key = self.key;
+        self.candidates
+            .sort_by_key(|candidate| key.distance(candidate.node.get_id()));
+    }
+
+    // The k closest candidates that haven't failed, the only ones worth asking
+    fn closest_alive(&self) -> impl Iterator<Item = &Candidate> {
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.state != CandidateState::Failed)
+            .take(MAX_BUCKET_SIZE)
+    }
+
+    fn in_flight(&self) -> usize {
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.state == CandidateState::InFlight)
+            .count()
+    }
+
+    // Next nodes to ask, so that at most ALPHA requests are in flight
+    pub fn next_to_query(&mut self) -> Vec<Node> {
+        let free = ALPHA.saturating_sub(self.in_flight());
+        let to_query: Vec<Key> = self
+            .closest_alive()
+            .filter(|candidate| candidate.state == CandidateState::NotQueried)
+            .take(free)
+            .map(|candidate| *candidate.node.get_id())
+            .collect();
+        self.candidates
+            .iter_mut()
+            .filter(|candidate| to_query.contains(candidate.node.get_id()))
+            .map(|candidate| {
+                candidate.state = CandidateState::InFlight;
+                candidate.node.clone()
+            })
+            .collect()
+    }
+
+    fn set_state(&mut self, id: &Key, state: CandidateState) {
+        if let Some(candidate) = self
+            .candidates
+            .iter_mut()
+            .find(|candidate| candidate.node.get_id() == id)
+        {
+            candidate.state = state;
+        }
+    }
+
+    // A node answered with the closest nodes it knows
+    pub fn responded(&mut self, id: &Key, nodes: Vec<Node>) {
+        self.set_state(id, CandidateState::Responded);
+        self.add(nodes);
+    }
+
+    pub fn failed(&mut self, id: &Key) {
+        self.set_state(id, CandidateState::Failed);
+    }
+
+    // Done once the k closest candidates left have all responded, or none is left to ask
+    pub fn is_finished(&self) -> bool {
+        self.closest_alive()
+            .all(|candidate| candidate.state == CandidateState::Responded)
+    }
+
+    // The k closest nodes that responded
+    pub fn closest(&self) -> Vec<Node> {
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.state == CandidateState::Responded)
+            .take(MAX_BUCKET_SIZE)
+            .map(|candidate| candidate.node.clone())
+            .collect()
+    }
+
+    pub fn get_failed(&self) -> Vec<Node> {
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.state == CandidateState::Failed)
+            .map(|candidate| candidate.node.clone())
+            .collect()
+    }
+
+    fn finish(&self, value: Option<Vec<u8>>, published_at: u64) -> LookupResult {
+        let failed = self.get_failed().len();
+        if failed > 0 {
+            println!(
+                "Lookup of {:?}: {} node(s) failed to respond",
+                hex::encode(self.key),
+                failed
+            );
+        }
+        LookupResult {
+            value,
+            published_at,
+            closest: self.closest(),
+        }
+    }
+}
+
+// Outcome of a lookup
+pub(crate) struct LookupResult {
+    // Only for value lookups that found it
+    pub value: Option<Vec<u8>>,
+    // When the value found was originally published
+    pub published_at: u64,
+    // The k closest nodes to the key that responded, for value lookups the ones that didn't have it
+    pub closest: Vec<Node>,
+}
+
+// Answer of one node: the value, or the closest nodes it knows
+struct QueryResponse {
+    value: Option<Vec<u8>>,
+    published_at: u64,
+    nodes: Vec<Node>,
+    // How long the node took to answer
+    rtt: Duration,
+}
+
+// How asking one node went
+enum QueryOutcome {
+    Answered(QueryResponse),
+    // The node answered with an error: it is alive, but of no use to the lookup
+    Refused { rtt: Duration },
+    // The node couldn't be reached in time, which counts towards evicting it
+    Unreachable,
+}
+
+// Runs an iterative lookup of the key from the closest nodes of our routing table
+// Every node learned along the way is added to the routing table, and every node asked records
+// whether it answered
+// The bucket of the key counts as refreshed
+pub async fn lookup(
+    routing_table: &RwLock<RoutingTable>,
+    key: Key,
+    kind: LookupKind,
+) -> LookupResult {
+    let (curr_node, client_pool, closest_known) = {
+        let mut routing_table = routing_table.write().await;
+        routing_table.touch_bucket(&key, Instant::now());
+        (
+            routing_table.get_curr_node().clone(),
+            routing_table.get_client_pool().clone(),
+            routing_table.get_closest_k_nodes(&key, MAX_BUCKET_SIZE),
+        )
+    };
+    let mut shortlist = Shortlist::new(key, *curr_node.get_id(), closest_known);
+    let mut in_flight = FuturesUnordered::new();
+    // Versions of a mutable record found so far, with when each was published
+    let mut records: Vec<(Vec<u8>, u64)> = Vec::new();
+
+    while !shortlist.is_finished() {
+        for node in shortlist.next_to_query() {
+            in_flight.push(query(&client_pool, curr_node.clone(), node, key, kind));
+        }
+        let Some((node, outcome)) = in_flight.next().await else {
+            break;
+        };
+        let rtt = match &outcome {
+            QueryOutcome::Answered(response) => Some(response.rtt),
+            QueryOutcome::Refused { rtt } => Some(*rtt),
+            QueryOutcome::Unreachable => None,
+        };
+        record_rpc(routing_table, &node, rtt).await;
+        match outcome {
+            QueryOutcome::Answered(response) => {
+                // The node holding the value isn't marked as responded, so it stays out of
+                // the closest nodes that didn't have it
+                // Mutable records are collected from every responder instead, any of them may
+                // hold a stale version
+                match response.value {
+                    Some(value) if kind == LookupKind::Content && !is_content_of(&key, &value) => {
+                        shortlist.failed(node.get_id());
+                        continue;
+                    }
+                    Some(value) if MutableRecord::deserialized_from_bytes(&value).is_ok() => {
+                        records.push((value, response.published_at));
+                    }
+                    Some(value) => return shortlist.finish(Some(value), response.published_at),
+                    None => {}
+                }
+                {
+                    let mut routing_table = routing_table.write().await;
+                    for node in &response.nodes {
+                        routing_table.add_node(node.clone());
+                    }
+                }
+                shortlist.responded(node.get_id(), response.nodes);
+            }
+            QueryOutcome::Refused { .. } | QueryOutcome::Unreachable => {
+                shortlist.failed(node.get_id())
+            }
+        }
+    }
+
+    let freshest = MutableRecord::freshest(&key, records.iter().map(|(value, _)| value.as_slice()));
+    let freshest = records
+        .into_iter()
+        .find(|(value, _)| MutableRecord::deserialized_from_bytes(value).ok() == freshest);
+    match freshest {
+        Some((value, published_at)) => shortlist.finish(Some(value), published_at),
+        None => shortlist.finish(None, 0),
+    }
+}
+
+// Asks one node over the pooled connection, connect and request together within RPC_TIMEOUT
+// Errors are classified like `answer_rtt` does, and malformed nodes in the answer are dropped
+async fn query(
+    client_pool: &ClientPool,
+    curr_node: Node,
+    node: Node,
+    key: Key,
+    kind: LookupKind,
+) -> (Node, QueryOutcome) {
+    let client = client_pool.client(&node.get_ip(), node.get_port());
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(RPC_TIMEOUT, async move {
+        let Some(mut client) = client else {
+            return QueryOutcome::Unreachable;
+        };
+        let result = match kind {
+            LookupKind::Node => {
+                let request = tonic::Request::new(FindNodeRequest {
+                    key: key.to_vec(),
+                    node: Some(curr_node.to_proto()),
+                    nonce: generate_node_id().to_vec(),
+                });
+                client
+                    .find_node(request)
+                    .await
+                    .map(|response| (None, 0, response.into_inner().nodes))
+            }
+            LookupKind::Value | LookupKind::Content => {
+                let request = tonic::Request::new(FindValueRequest {
+                    key: key.to_vec(),
+                    node: Some(curr_node.to_proto()),
+                    nonce: generate_node_id().to_vec(),
+                });
+                client.find_value(request).await.map(|response| {
+                    let response = response.into_inner();
+                    (
+                        Some(response.value).filter(|value| !value.is_empty()),
+                        response.published_at,
+                        response.nodes,
+                    )
+                })
+            }
+        };
+        match result {
+            Ok((value, published_at, nodes)) => QueryOutcome::Answered(QueryResponse {
+                value,
+                published_at,
+                nodes: nodes.iter().filter_map(Node::from_proto).collect(),
+                rtt: started.elapsed(),
+            }),
+            Err(status) if is_unreachable(&status) => QueryOutcome::Unreachable,
+            Err(_) => QueryOutcome::Refused {
+                rtt: started.elapsed(),
+            },
+        }
+    })
+    .await
+    .unwrap_or(QueryOutcome::Unreachable);
+    (node, outcome)
+}
@@ -1,18 +1,32 @@
+pub(crate) mod content;
+pub(crate) mod key;
+pub(crate) mod lookup;
+pub(crate) mod namespace;
+pub(crate) mod pool;
+pub(crate) mod record;
 pub(crate) mod routing_table;
+pub(crate) mod shutdown;
 
 use futures::future::join_all;
+use key::Key;
+use lookup::{LookupKind, LookupResult, lookup};
+use namespace::Namespace;
+use pool::ClientPool;
+use record::MutableRecord;
 use routing_table::node::{self, Node};
-use routing_table::node_id::distance;
 // Parameters
-use routing_table::params::{ALPHA, MAX_BUCKET_SIZE};
+use routing_table::params::{MAX_BUCKET_SIZE, MAX_CONSECUTIVE_FAILURES, RPC_TIMEOUT};
+use routing_table::storage::{Push, cached_ttl, received_published_at, unix_now};
+use shutdown::Shutdown;
 
 // ARC and RwLock are used to allow multiple threads to access the routing table concurrently
-use std::collections::HashSet;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, broadcast};
 
 // Tonic GRPC server
-use tonic::{Request, Response, Status, transport::Server};
+use tonic::{Code, Request, Response, Status, transport::Server};
 
 // Protobuf generated code
 pub mod communication {
@@ -20,12 +34,13 @@ pub mod communication {
 }
 use communication::kademlia_server::{Kademlia, KademliaServer};
 use communication::{
-    FindNodeRequest, FindNodeResponse, FindValueRequest, FindValueResponse, PingRequest,
-    PingResponse, StoreRequest, StoreResponse, kademlia_client::KademliaClient,
+    Event, FindNodeRequest, FindNodeResponse, FindValueRequest, FindValueResponse, PingRequest,
+    PingResponse, StoreRequest, StoreResponse, SubscribeRequest,
 };
 
+use crate::blockchain::block::Block;
 use crate::kademlia;
-use ring::digest::{Context, SHA256};
+use crate::pubsub::BLOCKS_TOPIC;
 
 // This is the main Kademlia service that will handle all the requests
 pub struct MyKademliaService {
@@ -35,8 +50,17 @@ pub struct MyKademliaService {
 // This is the remote procedure call (RPC) implementation of the Kademlia service
 #[tonic::async_trait]
 impl Kademlia for MyKademliaService {
+    type SubscribeStream =
+        Pin<Box<dyn futures::Stream<Item = Result<Event, Status>> + Send + 'static>>;
+
     async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PingResponse>, Status> {
-        let node = routing_table::node::Node::from_proto(request.get_ref().node.as_ref().unwrap());
+        // A missing or malformed sender node is refused
+        let node = request
+            .get_ref()
+            .node
+            .as_ref()
+            .and_then(Node::from_proto)
+            .ok_or_else(|| Status::invalid_argument("Invalid sender node"))?;
 
         // Log the received ping
         println!(
@@ -70,12 +94,15 @@ impl Kademlia for MyKademliaService {
         request: Request<FindNodeRequest>,
     ) -> Result<Response<FindNodeResponse>, Status> {
         // Extract the ID from the request
-        let key: [u8; 20] = request
+        let key = Key::from_slice(&request.get_ref().key)
+            .ok_or_else(|| Status::invalid_argument("Invalid ID length"))?;
+        // Extract the sender node
+        let node = request
             .get_ref()
-            .key
-            .clone()
-            .try_into()
-            .map_err(|_| Status::invalid_argument("Invalid ID length"))?;
+            .node
+            .as_ref()
+            .and_then(Node::from_proto)
+            .ok_or_else(|| Status::invalid_argument("Invalid sender node"))?;
 
         // Scope 1: Read lock to get the closest nodes
         let closest_nodes = {
@@ -94,18 +121,13 @@ impl Kademlia for MyKademliaService {
             .collect();
 
         // Scope 2: Write lock to update the routing table
-        {
-            let node =
-                routing_table::node::Node::from_proto(request.get_ref().node.as_ref().unwrap());
-
-            update_routing_table_with_node(
-                &self.routing_table,
-                node.get_id().clone(),
-                node.get_ip(),
-                node.get_port(),
-            )
-            .await;
-        }
+        update_routing_table_with_node(
+            &self.routing_table,
+            *node.get_id(),
+            node.get_ip(),
+            node.get_port(),
+        )
+        .await;
 
         // Response with the closest nodes
         let reply = FindNodeResponse {
@@ -121,27 +143,49 @@ impl Kademlia for MyKademliaService {
     ) -> Result<Response<StoreResponse>, Status> {
         println!("Received store request");
         // Extract key
-        let key: [u8; 20] = request
+        let key = Key::from_slice(&request.get_ref().key)
+            .ok_or_else(|| Status::invalid_argument("Invalid ID length"))?;
+        // Extract the sender node
+        let node = request
             .get_ref()
-            .key
-            .clone()
-            .try_into()
-            .map_err(|_| Status::invalid_argument("Invalid ID length"))?;
-        // Extract value
+            .node
+            .as_ref()
+            .and_then(Node::from_proto)
+            .ok_or_else(|| Status::invalid_argument("Invalid sender node"))?;
+        // Extract value, it must belong under the key in some namespace and fit its size limit,
+        // larger data has to be split in chunks by the sender
+        // The namespace is worked out from the key and value, not taken from the sender, so a
+        // value can't be stored under a laxer namespace than its own
         let value: Vec<u8> = request.get_ref().value.clone();
+        let namespace = Namespace::of(&key, &value).ok_or_else(|| {
+            Status::invalid_argument(
+                Namespace::from_proto(request.get_ref().namespace())
+                    .validate(&key, &value)
+                    .err()
+                    .unwrap_or("Value doesn't belong in its namespace"),
+            )
+        })?;
+        // Blocks can only be stored under the key of their hash, whatever the namespace
+        if content::is_misplaced_block(&key, &value) {
+            return Err(Status::invalid_argument("Value doesn't match its key"));
+        }
 
-        {
+        // Replicas and refreshes carry the original publication time and the TTL left
+        let published_at = received_published_at(request.get_ref().published_at, unix_now());
+        let stored = {
             // Scope the lock so it's dropped early
             let mut routing_table = self.routing_table.write().await;
-            routing_table.store(key, value);
-        }
+            let ttl = match request.get_ref().ttl_secs {
+                0 => namespace.ttl(routing_table.get_storage_timings()),
+                ttl_secs => Duration::from_secs(ttl_secs),
+            };
+            routing_table.store_record(key, namespace, value, published_at, ttl, Instant::now())
+        };
 
         // Update the routing table with the new node
-        let node = routing_table::node::Node::from_proto(request.get_ref().node.as_ref().unwrap());
-
         update_routing_table_with_node(
             &self.routing_table,
-            node.get_id().clone(),
+            *node.get_id(),
             node.get_ip(),
             node.get_port(),
         )
@@ -153,6 +197,13 @@ impl Kademlia for MyKademliaService {
             hex::encode(node.get_id())
         );
 
+        // A refused value isn't acknowledged
+        if !stored {
+            return Err(Status::already_exists(
+                "A newer, content-addressed or other namespace's value is already stored",
+            ));
+        }
+
         // Create a response with the stored message
         let reply = StoreResponse {
             message: "Stored successfully".to_string(),
@@ -166,23 +217,27 @@ impl Kademlia for MyKademliaService {
         request: Request<FindValueRequest>,
     ) -> Result<Response<FindValueResponse>, Status> {
         // Extract the key from the request
-        let key: [u8; 20] = request
+        let key = Key::from_slice(&request.get_ref().key)
+            .ok_or_else(|| Status::invalid_argument("Invalid ID length"))?;
+        // Extract the sender node
+        let node = request
             .get_ref()
-            .key
-            .clone()
-            .try_into()
-            .map_err(|_| Status::invalid_argument("Invalid ID length"))?;
+            .node
+            .as_ref()
+            .and_then(Node::from_proto)
+            .ok_or_else(|| Status::invalid_argument("Invalid sender node"))?;
 
         // Scope 1: Read lock to try to get the value
-        if let Some(value) = {
+        if let Some(stored) = {
             let routing_table = self.routing_table.read().await;
-            routing_table.get(key).cloned()
+            routing_table.get_stored(key, Instant::now()).cloned()
         } {
             // If value is found
             let reply = FindValueResponse {
-                value,
+                value: stored.value,
                 nodes: vec![],
                 nonce: request.get_ref().nonce.clone(),
+                published_at: stored.published_at,
             };
             return Ok(Response::new(reply));
         }
@@ -204,35 +259,80 @@ impl Kademlia for MyKademliaService {
             .collect();
 
         // Scope 3: Write lock to add new node to the routing table
-        {
-            let node =
-                routing_table::node::Node::from_proto(request.get_ref().node.as_ref().unwrap());
-
-            update_routing_table_with_node(
-                &self.routing_table,
-                node.get_id().clone(),
-                node.get_ip(),
-                node.get_port(),
-            )
-            .await;
-        }
+        update_routing_table_with_node(
+            &self.routing_table,
+            *node.get_id(),
+            node.get_ip(),
+            node.get_port(),
+        )
+        .await;
 
         // Return response with closest nodes to continue the search
         let reply = FindValueResponse {
             value: vec![],
             nodes,
             nonce: request.get_ref().nonce.clone(),
+            published_at: 0,
         };
 
         Ok(Response::new(reply))
     }
+
+    async fn subscribe(
+        &self,
+        request: Request<SubscribeRequest>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let topic = request.get_ref().topic.clone();
+        let receiver = self.routing_table.read().await.get_event_bus().subscribe();
+
+        // A node following our blocks gets followed back, so blocks flow both ways
+        // Only nodes already in our routing table are followed, at the address we know them by,
+        // so a subscriber can't make us dial an address of its choosing
+        if let Some(node) = request.get_ref().node.as_ref().and_then(Node::from_proto) {
+            let known = self
+                .routing_table
+                .read()
+                .await
+                .get_node(node.get_id())
+                .cloned();
+            update_routing_table_with_node(
+                &self.routing_table,
+                *node.get_id(),
+                node.get_ip(),
+                node.get_port(),
+            )
+            .await;
+            if topic == BLOCKS_TOPIC
+                && let Some(known) = known
+            {
+                relay_blocks_from(self.routing_table.clone(), known);
+            }
+        }
+
+        // Forward the events of the topic until the subscriber goes away
+        let stream = futures::stream::unfold(receiver, move |mut receiver| {
+            let topic = topic.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) if event.topic == topic => return Some((Ok(event), receiver)),
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
 }
 
 // This function starts the Kademlia server, this will process all calls made to it and update routing table
+// It runs, with the node's background maintenance, until the shutdown is triggered
 pub async fn start_kademlia_server(
     routing_table: Arc<RwLock<routing_table::RoutingTable>>,
     addr: String,
     port: u16,
+    shutdown: Shutdown,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let kademlia_service = MyKademliaService {
         routing_table: routing_table.clone(),
@@ -241,9 +341,13 @@ pub async fn start_kademlia_server(
 
     let socket_addr = format!("[{}]:{}", addr, port).parse()?;
 
+    // Expire, replicate and refresh the stored values, and refresh the buckets, while the node runs
+    tokio::spawn(maintain_storage(routing_table.clone(), shutdown.clone()));
+    tokio::spawn(maintain_routing_table(routing_table, shutdown.clone()));
+
     Server::builder()
         .add_service(kademlia_server)
-        .serve(socket_addr)
+        .serve_with_shutdown(socket_addr, async move { shutdown.wait().await })
         .await?;
 
     Ok(())
@@ -254,16 +358,17 @@ pub async fn join_kademlia_network(
     boot_addr: String,
     boot_port: u16,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let uri = format!("http://[{}]:{}", boot_addr, boot_port);
-
-    // Create a new Kademlia client
-    let mut client = KademliaClient::connect(uri).await?;
-
-    // Get the current node(and release the read lock after)
-    let curr_node = {
+    // Get the current node and a client of the bootstrap node (and release the read lock after)
+    let (curr_node, client) = {
         let routing_table_read = routing_table.read().await;
-        routing_table_read.get_curr_node().to_proto()
+        (
+            routing_table_read.get_curr_node().to_proto(),
+            routing_table_read
+                .get_client_pool()
+                .client(&boot_addr, boot_port),
+        )
     };
+    let mut client = client.ok_or("Invalid bootstrap node address")?;
 
     // Send a ping to the bootstrap node
     let request = tonic::Request::new(PingRequest {
@@ -271,10 +376,17 @@ pub async fn join_kademlia_network(
         nonce: kademlia::routing_table::node_id::generate_node_id().to_vec(),
     });
 
+    let started = Instant::now();
     let response = client.ping(request).await?;
+    let rtt = started.elapsed();
 
     // Update the routing table with the bootstrap node's ID
-    let node = routing_table::node::Node::from_proto(response.get_ref().node.as_ref().unwrap());
+    let node = response
+        .get_ref()
+        .node
+        .as_ref()
+        .and_then(Node::from_proto)
+        .ok_or("Invalid bootstrap node")?;
 
     println!(
         "Ping response from bootstrap node with ID: {:?}, IP: {}, Port: {}",
@@ -290,246 +402,401 @@ pub async fn join_kademlia_network(
         node.get_port(),
     )
     .await;
+    record_rpc(&routing_table, &node, Some(rtt)).await;
 
-    // Refresh the routing table
+    // Follow the bootstrap node's blocks, it follows ours back
+    relay_blocks_from(routing_table.clone(), node);
+
+    // Look ourselves up so the buckets closest to us fill up, then refresh the farther ones
+    let curr_node_id = *routing_table.read().await.get_curr_node().get_id();
+    find_node_dht(&routing_table, curr_node_id).await;
     refresh_routing_table(routing_table.clone()).await;
 
     Ok(())
 }
 
+// A node that sends us an RPC is alive, it's added to the routing table or seen again
 async fn update_routing_table_with_node(
     routing_table: &RwLock<routing_table::RoutingTable>,
-    id: [u8; 20],
+    id: Key,
     ip: String,
     port: u16,
 ) {
     let new_node = routing_table::node::Node::with_id(id, ip, port);
     let mut routing_table = routing_table.write().await;
     routing_table.add_node(new_node);
+    routing_table.record_response(&id, None, Instant::now());
     println!("Added node with ID to Table: {:?}", hex::encode(id));
 }
 
+// Records how one of our RPCs to a node went: an answer, with its round-trip time, or none,
+// which counts towards evicting the node
+async fn record_rpc(
+    routing_table: &RwLock<routing_table::RoutingTable>,
+    node: &Node,
+    rtt: Option<Duration>,
+) {
+    let mut routing_table = routing_table.write().await;
+    match rtt {
+        Some(rtt) => routing_table.record_response(node.get_id(), Some(rtt), Instant::now()),
+        None if routing_table.record_failure(node.get_id()) => println!(
+            "Evicted node with ID: {:?} after {} failed RPCs in a row",
+            hex::encode(node.get_id()),
+            MAX_CONSECUTIVE_FAILURES
+        ),
+        None => {}
+    }
+}
+
+// Whether an RPC failed because the node couldn't be reached in time, rather than answering
+// with an error
+fn is_unreachable(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable | Code::DeadlineExceeded | Code::Cancelled | Code::Unknown
+    )
+}
+
+// Round-trip time of an RPC started at `started`, None if the node didn't answer
+// Errors like a refused value are answers too, only failing to reach the node in time isn't
+fn answer_rtt<T>(result: &Result<T, Status>, started: Instant) -> Option<Duration> {
+    match result {
+        Err(status) if is_unreachable(status) => None,
+        _ => Some(started.elapsed()),
+    }
+}
+
+// Outcome of storing a value in the DHT
+#[derive(Clone, Default)]
+pub struct StoreResult {
+    // Closest nodes to the key that stored the value
+    pub acknowledged: Vec<Node>,
+    // Closest nodes that were unreachable, timed out or refused the value
+    pub failed: Vec<Node>,
+}
+
+impl StoreResult {
+    // Whether at least `quorum` of the closest nodes stored the value
+    // A network with fewer nodes than the quorum needs all of them
+    pub fn has_quorum(&self, quorum: usize) -> bool {
+        let contacted = self.acknowledged.len() + self.failed.len();
+        self.acknowledged.len() >= quorum.min(contacted)
+    }
+}
+
+// Stores a value on this node, as its original publisher, and on the k closest nodes to the key
+// in the network, found with an iterative lookup
+// Returns which of them stored it, None if the value doesn't belong under the key in the namespace
+// or is too large for it, so it can't be stored at all
 pub async fn store_value_dht(
     routing_table: &RwLock<routing_table::RoutingTable>,
-    key: [u8; 20],
+    namespace: Namespace,
+    key: Key,
     value: Vec<u8>,
-) {
-    // Store in own routing table
-    {
-        let mut routing_table = routing_table.write().await;
-        routing_table.store(key, value.clone());
+) -> Option<StoreResult> {
+    // Other nodes would refuse it
+    if let Err(e) = namespace.validate(&key, &value) {
+        println!("Can't store value of {} bytes: {}", value.len(), e);
+        return None;
     }
 
-    // Use a single read lock to get the current node and K-closest nodes
-    let (curr_node, k_closest) = {
-        let routing_table = routing_table.read().await;
+    // Store in own routing table, as the original publisher
+    let (published_at, ttl) = {
+        let mut routing_table = routing_table.write().await;
         (
-            routing_table.get_curr_node().clone(),
-            routing_table.get_closest_k_nodes(&key, MAX_BUCKET_SIZE),
+            routing_table.publish(key, namespace, value.clone(), Instant::now()),
+            namespace.ttl(routing_table.get_storage_timings()),
         )
     };
 
-    // Iterate over the k-closest nodes and send the value to each node
-    for node in k_closest {
-        // Create a new Kademlia client
-
-        let uri = format!("http://[{}]:{}", node.get_ip(), node.get_port());
-        let mut client = KademliaClient::connect(uri).await.unwrap();
+    let k_closest = find_node_dht(routing_table, key).await;
+    let push = Push {
+        key,
+        namespace,
+        value,
+        published_at,
+        ttl,
+    };
+    Some(push_to_nodes(routing_table, k_closest, &push).await)
+}
 
-        // Send the value to the node
-        let request = tonic::Request::new(StoreRequest {
-            node: Some(curr_node.to_proto()),
-            key: key.to_vec(),
-            value: value.clone(),
-            nonce: kademlia::routing_table::node_id::generate_node_id().to_vec(),
-        });
+// Stores a new version of this node's copy of a pointer, as a mutable record signed by this node
+// Nodes holding a version with the same or a higher sequence number keep theirs
+pub async fn store_record_dht(
+    routing_table: &RwLock<routing_table::RoutingTable>,
+    salt: &str,
+    seq: u64,
+    value: Vec<u8>,
+) -> Option<StoreResult> {
+    let record = {
+        let routing_table = routing_table.read().await;
+        MutableRecord::new(routing_table.get_node_identity(), salt, seq, value)
+    };
+    store_value_dht(
+        routing_table,
+        Namespace::Pointer,
+        record.get_key(),
+        record.serialized_to_bytes().ok()?,
+    )
+    .await
+}
 
-        match client.store(request).await {
-            Ok(response) => {
-                println!(
-                    "Stored value on node with ID: {:?}, Response: {:?}",
-                    hex::encode(node.get_id()),
-                    response.into_inner().message
-                );
-            }
-            Err(e) => {
-                println!(
-                    "Failed to store value on node with ID: {:?}, Error: {:?}",
-                    hex::encode(node.get_id()),
-                    e
-                );
-            }
-        }
-    }
+// Fetches the freshest version of a publisher's copy of a pointer
+pub async fn find_record_dht(
+    routing_table: &RwLock<routing_table::RoutingTable>,
+    publisher_id: Key,
+    salt: &str,
+) -> Option<MutableRecord> {
+    let key = MutableRecord::key(&publisher_id, salt);
+    let bytes = find_value_dht(routing_table, Namespace::Pointer, key).await?;
+    MutableRecord::deserialized_from_bytes(&bytes)
+        .ok()
+        .filter(|record| record.is_valid(&key))
 }
 
-pub fn string_to_hash_key(key: &str) -> [u8; 20] {
-    let mut context = Context::new(&SHA256);
-    context.update(key.as_bytes());
-    let result = context.finish();
-    let mut hash_key = [0u8; 20];
-    hash_key.copy_from_slice(&result.as_ref()[..20]);
-    hash_key
+// Iterative node lookup, returns the k closest nodes to the key that answered, without this node
+pub async fn find_node_dht(
+    routing_table: &RwLock<routing_table::RoutingTable>,
+    key: Key,
+) -> Vec<Node> {
+    lookup(routing_table, key, LookupKind::Node).await.closest
 }
 
-pub async fn find_value_dht(
+// Sends a value to every node in parallel, each with RPC_TIMEOUT to store it
+// Nodes refusing it still answered, only the others count as failed RPCs
+async fn push_to_nodes(
     routing_table: &RwLock<routing_table::RoutingTable>,
-    key: [u8; 20],
-) -> Option<Vec<u8>> {
-    // Check own storage first
-    {
+    nodes: Vec<Node>,
+    push: &Push,
+) -> StoreResult {
+    let (curr_node, client_pool) = {
         let routing_table = routing_table.read().await;
-        if let Some(value) = routing_table.get(key) {
-            return Some(value.clone());
+        (
+            routing_table.get_curr_node().clone(),
+            routing_table.get_client_pool().clone(),
+        )
+    };
+    let (curr_node, client_pool) = (&curr_node, &client_pool);
+    let futures = nodes.into_iter().map(|node| async move {
+        let started = Instant::now();
+        let push_value = push_value(client_pool, curr_node, &node, push);
+        let stored = tokio::time::timeout(RPC_TIMEOUT, push_value).await;
+        let rtt = stored
+            .as_ref()
+            .ok()
+            .and_then(|stored| answer_rtt(stored, started));
+        (node, matches!(stored, Ok(Ok(()))), rtt)
+    });
+
+    let mut result = StoreResult::default();
+    for (node, stored, rtt) in join_all(futures).await {
+        record_rpc(routing_table, &node, rtt).await;
+        match stored {
+            true => result.acknowledged.push(node),
+            false => result.failed.push(node),
         }
     }
+    result
+}
 
-    // Search in kademlia
-    let key = Arc::new(key);
-    let curr_node = Arc::new({
-        let rt = routing_table.read().await;
-        rt.get_curr_node().clone()
-    });
-
-    let mut queried_nodes = HashSet::new();
-    let mut closest_nodes = {
-        let rt = routing_table.read().await;
-        rt.get_closest_k_nodes(&key, MAX_BUCKET_SIZE)
+// Sends a value to a node, returns the node's error if it didn't store it
+async fn push_value(
+    client_pool: &ClientPool,
+    curr_node: &Node,
+    node: &Node,
+    push: &Push,
+) -> Result<(), Status> {
+    // Get a client over the pooled connection to the node
+    let Some(mut client) = client_pool.client(&node.get_ip(), node.get_port()) else {
+        println!(
+            "Invalid address of node with ID: {:?}",
+            hex::encode(node.get_id())
+        );
+        return Err(Status::unavailable("Invalid node address"));
     };
 
-    while !closest_nodes.is_empty() {
-        let mut futures = vec![];
-
-        for node in closest_nodes.iter().take(ALPHA) {
-            if !queried_nodes.contains(node.get_id()) {
-                let node = node.clone();
-                let key = Arc::clone(&key);
-                queried_nodes.insert(*node.get_id());
-
-                let curr_node = Arc::clone(&curr_node);
-                futures.push(tokio::spawn(async move {
-                    let uri = format!("http://[{}]:{}", node.get_ip(), node.get_port());
-                    let mut client = KademliaClient::connect(uri).await.ok()?;
-
-                    let request = tonic::Request::new(FindValueRequest {
-                        key: key.to_vec(),
-                        node: Some(curr_node.to_proto()),
-                        nonce: kademlia::routing_table::node_id::generate_node_id().to_vec(),
-                    });
+    // Send the value to the node, with at least a second to live
+    let request = tonic::Request::new(StoreRequest {
+        node: Some(curr_node.to_proto()),
+        key: push.key.to_vec(),
+        value: push.value.clone(),
+        nonce: kademlia::routing_table::node_id::generate_node_id().to_vec(),
+        published_at: push.published_at,
+        ttl_secs: push.ttl.as_secs().max(1),
+        namespace: push.namespace.to_proto().into(),
+    });
 
-                    client.find_value(request).await.ok()
-                }));
-            }
+    match client.store(request).await {
+        Ok(response) => {
+            println!(
+                "Stored value on node with ID: {:?}, Response: {:?}",
+                hex::encode(node.get_id()),
+                response.into_inner().message
+            );
+            Ok(())
         }
+        Err(e) => {
+            println!(
+                "Failed to store value on node with ID: {:?}, Error: {:?}",
+                hex::encode(node.get_id()),
+                e
+            );
+            Err(e)
+        }
+    }
+}
 
-        if futures.is_empty() {
-            break;
+// Storage lifecycle of a node: every maintenance interval it drops expired values, refreshes the
+// values it published and replicates the others to the current k closest nodes of their keys,
+// so values outlive the nodes that held them and nobody keeps values nobody refreshes
+// Idle pooled connections are closed along the way
+pub async fn maintain_storage(
+    routing_table: Arc<RwLock<routing_table::RoutingTable>>,
+    shutdown: Shutdown,
+) {
+    loop {
+        let maintenance_interval = {
+            let routing_table = routing_table.read().await;
+            routing_table.get_storage_timings().maintenance_interval
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(maintenance_interval) => {}
+            _ = shutdown.wait() => return,
         }
 
-        let results = join_all(futures).await;
-
-        let mut new_nodes = vec![];
-
-        for result in results {
-            if let Ok(Some(response)) = result {
-                let response = response.into_inner();
-                if !response.value.is_empty() {
-                    return Some(response.value);
-                } else {
-                    let nodes = response.nodes;
-                    for node_proto in nodes {
-                        let node: Node = Node::from_proto(&node_proto);
-                        let clone = node.clone();
-                        new_nodes.push(node);
-                        {
-                            let mut rt = routing_table.write().await;
-                            rt.add_node(clone);
-                        }
-                    }
-                }
+        let pushes = {
+            let mut routing_table = routing_table.write().await;
+            let now = Instant::now();
+            let expired = routing_table.expire(now);
+            if expired > 0 {
+                println!("Expired {} stored values", expired);
             }
-        }
+            // Idle connections close even when no new request comes to evict them
+            routing_table.get_client_pool().evict_idle(now);
+            routing_table.take_due_pushes(now)
+        };
 
-        if new_nodes.is_empty() {
-            break;
+        for push in pushes {
+            let k_closest = {
+                let routing_table = routing_table.read().await;
+                routing_table.get_closest_k_nodes(&push.key, MAX_BUCKET_SIZE)
+            };
+            push_to_nodes(&routing_table, k_closest, &push).await;
         }
-
-        new_nodes.sort_by_key(|node| distance(&key, node.get_id()));
-        closest_nodes = new_nodes;
     }
-
-    None
 }
 
-pub async fn refresh_bucket(routing_table: &RwLock<routing_table::RoutingTable>, bucket_index: u8) {
-    // Get curr_node
-    let curr_node = Arc::new({
-        let rt = routing_table.read().await;
-        rt.get_curr_node().clone()
-    });
-
-    let bucket = {
+// Finds a value, the namespace is the one copies cached on the lookup path are stored in
+pub async fn find_value_dht(
+    routing_table: &RwLock<routing_table::RoutingTable>,
+    namespace: Namespace,
+    key: Key,
+) -> Option<Vec<u8>> {
+    // Check own storage first, a mutable record held here may be stale though
+    let local = {
         let routing_table = routing_table.read().await;
-        routing_table.get_k_bucket_map().get(&bucket_index).cloned()
+        routing_table.get(key).cloned()
     };
+    if let Some(value) = &local
+        && MutableRecord::deserialized_from_bytes(value).is_err()
+    {
+        return local;
+    }
 
-    if let Some(bucket) = bucket {
-        if let Some(random_node) = bucket.get_random_node() {
-            let random_id = routing_table::node_id::generate_random_id_near_distance(
-                curr_node.get_id(),
-                bucket_index,
-            );
+    // Search in kademlia
+    let result = lookup(routing_table, key, LookupKind::Value).await;
+    let Some(value) = result.value.clone() else {
+        return local;
+    };
+    // Ours unless the record found would replace it
+    if let Some(local) = local
+        && MutableRecord::accepts(&key, Some(&local), &value) != Some(true)
+    {
+        return Some(local);
+    }
+    cache_on_path(routing_table, namespace, key, result).await;
+    Some(value)
+}
 
-            let uri = format!(
-                "http://[{}]:{}",
-                random_node.get_ip(),
-                random_node.get_port()
-            );
-            if let Ok(mut client) = KademliaClient::connect(uri.clone()).await {
-                let request = tonic::Request::new(FindNodeRequest {
-                    key: random_id,
-                    node: Some(curr_node.to_proto()), // Send the current node info
-                    nonce: kademlia::routing_table::node_id::generate_node_id().to_vec(),
-                });
-
-                // Send the FindNodeRequest and handle the response
-                match client.find_node(request).await {
-                    Ok(response) => {
-                        let nodes = response.into_inner().nodes;
-
-                        // Lock the routing table to update nodes
-                        let mut routing_table = routing_table.write().await;
-                        for node_proto in nodes {
-                            let node: Node = Node::from_proto(&node_proto);
-                            routing_table.add_node(node);
-                        }
-
-                        println!("Refreshed bucket nº {}", bucket_index);
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "Failed to refresh bucket with node ID: {:?}, Error: {:?}",
-                            hex::encode(random_node.get_id()),
-                            e
-                        );
-                    }
-                }
-            } else {
-                eprintln!("Failed to connect to node at {}", uri.clone());
-            }
-        } else {
-            eprintln!("No random node found in bucket {}", bucket_index);
+// Finds a block or chunk, only accepting the value the key addresses, so a node holding a
+// tampered copy can't hand it out
+pub async fn find_content_dht(
+    routing_table: &RwLock<routing_table::RoutingTable>,
+    namespace: Namespace,
+    key: Key,
+) -> Option<Vec<u8>> {
+    {
+        let routing_table = routing_table.read().await;
+        if let Some(value) = routing_table.get(key)
+            && content::is_content_of(&key, value)
+        {
+            return Some(value.clone());
         }
-    } else {
-        eprintln!("Bucket {} not found", bucket_index);
     }
+
+    let result = lookup(routing_table, key, LookupKind::Content).await;
+    let value = result.value.clone()?;
+    cache_on_path(routing_table, namespace, key, result).await;
+    Some(value)
+}
+
+// Caches a value found by a lookup on the closest node on the way that didn't have it, so hot
+// keys aren't always fetched from the same k nodes
+// The node just answered the lookup, so waiting for it to store the value is short
+async fn cache_on_path(
+    routing_table: &RwLock<routing_table::RoutingTable>,
+    namespace: Namespace,
+    key: Key,
+    result: LookupResult,
+) {
+    let (Some(value), Some(cache_node)) = (result.value, result.closest.first().cloned()) else {
+        return;
+    };
+    let ttl = {
+        let routing_table = routing_table.read().await;
+        let nodes_between = routing_table.count_closer_nodes(&key, cache_node.get_id());
+        cached_ttl(
+            namespace.ttl(routing_table.get_storage_timings()),
+            nodes_between,
+        )
+    };
+    let push = Push {
+        key,
+        namespace,
+        value,
+        published_at: result.published_at,
+        ttl,
+    };
+    push_to_nodes(routing_table, vec![cache_node], &push).await;
+}
+
+// Refreshes a bucket, existing or not yet, with a lookup of a random key in it, which adds the
+// nodes it finds on the way
+pub async fn refresh_bucket(routing_table: &RwLock<routing_table::RoutingTable>, bucket_index: u8) {
+    let random_key = {
+        let routing_table = routing_table.read().await;
+        routing_table
+            .get_curr_node()
+            .get_id()
+            .random_in_bucket(bucket_index)
+    };
+    let closest = find_node_dht(routing_table, random_key).await;
+    println!(
+        "Refreshed bucket nº {}, {} nodes answered",
+        bucket_index,
+        closest.len()
+    );
 }
 
+// Refreshes the buckets due for a refresh in parallel
 pub async fn refresh_routing_table(routing_table: Arc<RwLock<routing_table::RoutingTable>>) {
-    let buckets = routing_table.read().await.get_k_bucket_map().clone();
+    let buckets = routing_table
+        .read()
+        .await
+        .get_buckets_to_refresh(Instant::now());
     let mut futures = vec![];
 
-    for (bucket_index, _bucket) in buckets {
+    for bucket_index in buckets {
         let routing_table_clone = Arc::clone(&routing_table);
         futures.push(tokio::spawn(async move {
             refresh_bucket(&routing_table_clone, bucket_index).await;
@@ -539,3 +806,74 @@ pub async fn refresh_routing_table(routing_table: Arc<RwLock<routing_table::Rout
     // Wait for all tasks to complete
     join_all(futures).await;
 }
+
+// Routing table maintenance of a node: every maintenance interval it refreshes the buckets no
+// lookup went through for the refresh interval, so peers that went away are replaced and
+// quiet parts of the network stay known
+pub async fn maintain_routing_table(
+    routing_table: Arc<RwLock<routing_table::RoutingTable>>,
+    shutdown: Shutdown,
+) {
+    loop {
+        let maintenance_interval = {
+            let routing_table = routing_table.read().await;
+            routing_table.get_storage_timings().maintenance_interval
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(maintenance_interval) => {}
+            _ = shutdown.wait() => return,
+        }
+        refresh_routing_table(routing_table.clone()).await;
+    }
+}
+
+// Subscribes to a peer's blocks and republishes them to our own subscribers
+// Blocks already published are dropped by the event bus, so relays between peers can't loop,
+// and so are blocks that can't be fetched from the DHT by their key, which no chain can point to
+pub fn relay_blocks_from(routing_table: Arc<RwLock<routing_table::RoutingTable>>, node: Node) {
+    tokio::spawn(async move {
+        let (event_bus, curr_node, client_pool) = {
+            let routing_table = routing_table.read().await;
+            (
+                routing_table.get_event_bus().clone(),
+                routing_table.get_curr_node().clone(),
+                routing_table.get_client_pool().clone(),
+            )
+        };
+        if !event_bus.start_relay(*node.get_id()) {
+            return; // Already relaying this peer
+        }
+
+        if let Some(mut client) = client_pool.client(&node.get_ip(), node.get_port()) {
+            let request = tonic::Request::new(SubscribeRequest {
+                node: Some(curr_node.to_proto()),
+                topic: BLOCKS_TOPIC.to_string(),
+                nonce: kademlia::routing_table::node_id::generate_node_id().to_vec(),
+            });
+            let started = Instant::now();
+            let response = client.subscribe(request).await;
+            record_rpc(&routing_table, &node, answer_rtt(&response, started)).await;
+            if let Ok(response) = response {
+                let mut stream = response.into_inner();
+                while let Ok(Some(event)) = stream.message().await {
+                    let Ok(block) = serde_json::from_slice::<Block>(&event.block) else {
+                        continue;
+                    };
+                    let key = content::block_key(&block.get_hash());
+                    if find_content_dht(&routing_table, Namespace::Block, key)
+                        .await
+                        .is_some()
+                    {
+                        event_bus.publish_block(&block);
+                    }
+                }
+            }
+        }
+
+        println!(
+            "Stopped relaying blocks from {:?}",
+            hex::encode(node.get_id())
+        );
+        event_bus.end_relay(node.get_id());
+    });
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use crate::auction::attachment::CHUNK_SIZE;
+use crate::auction::bid::Bid;
+use crate::auction::index::PostingsList;
+use crate::auction::private::{PrivateAuction, PrivateBid};
+use crate::auction::{self, Auction};
+use crate::blockchain::block::Block;
+
+use super::communication;
+use super::content;
+use super::key::Key;
+use super::record::{MutableRecord, UserRecord};
+use super::routing_table::params::{MAX_VALUE_SIZE, StorageTimings};
+
+// What kind of value a key holds, sent along with every store, though storing nodes work it out
+// from the key and value themselves (see `Namespace::of`)
+// A namespace derives the keys of its values and decides what a storing node accepts under
+// them: only values that belong under the key, up to a size, for at most a TTL
+// A key stays in the namespace it was first stored in until its value expires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Namespace {
+    // Blocks, under the key of their hash
+    Block,
+    // Attachment chunks, under the key of their hash
+    Chunk,
+    // Auctions, public or sealed, under the key of their ID
+    Auction,
+    // Bids, public or sealed, under the key of their auction's ID and their own
+    Bid,
+    // Signed mutable records like `latest_block`, under the key of their publisher and name
+    Pointer,
+    // Postings lists of the auction search index
+    Index,
+    // Anything users store from the menu, small and short-lived
+    UserData,
+}
+
+impl Namespace {
+    const ALL: [Namespace; 7] = [
+        Namespace::Block,
+        Namespace::Chunk,
+        Namespace::Auction,
+        Namespace::Bid,
+        Namespace::Pointer,
+        Namespace::Index,
+        Namespace::UserData,
+    ];
+
+    fn prefix(self) -> &'static str {
+        match self {
+            Namespace::Block => "block",
+            Namespace::Chunk => "chunk",
+            Namespace::Auction => "auction",
+            Namespace::Bid => "bid",
+            Namespace::Pointer => "pointer",
+            Namespace::Index => "index",
+            Namespace::UserData => "user",
+        }
+    }
+
+    // Key of a named value, the name is hashed along with the namespace so the same name in two
+    // namespaces gives two keys
+    pub fn key(self, name: &str) -> Key {
+        Key::from_name(&format!("{}:{}", self.prefix(), name))
+    }
+
+    // Largest value a node accepts to store in the namespace
+    pub fn max_size(self) -> usize {
+        match self {
+            Namespace::Chunk => CHUNK_SIZE,
+            Namespace::Auction => 64 * 1024,
+            Namespace::Bid | Namespace::UserData => 16 * 1024,
+            Namespace::Block | Namespace::Pointer | Namespace::Index => MAX_VALUE_SIZE,
+        }
+    }
+
+    // Longest a value of the namespace is kept without being stored again
+    pub fn ttl(self, timings: &StorageTimings) -> Duration {
+        match self {
+            Namespace::UserData => timings.value_ttl / 24,
+            _ => timings.value_ttl,
+        }
+    }
+
+    // Whether the value may be stored under the key in this namespace, with the reason if not
+    pub fn validate(self, key: &Key, value: &[u8]) -> Result<(), &'static str> {
+        if value.len() > self.max_size() {
+            return Err("Value too large for its namespace");
+        }
+        let belongs = match self {
+            Namespace::Block => serde_json::from_slice::<Block>(value)
+                .is_ok_and(|block| content::block_key(&block.get_hash()) == *key),
+            Namespace::Chunk => content::content_key(value) == *key,
+            Namespace::Auction => {
+                serde_json::from_slice::<Auction>(value).is_ok_and(|auction| {
+                    auction.has_valid_id() && Namespace::Auction.key(&auction.id) == *key
+                }) || PrivateAuction::deserialized_from_bytes(value).is_ok_and(|private_auction| {
+                    Namespace::Auction.key(&private_auction.private_auction_id) == *key
+                })
+            }
+            Namespace::Bid => {
+                serde_json::from_slice::<Bid>(value).is_ok_and(|bid| {
+                    bid.has_valid_id() && auction::bid_key(&bid.auction_id, &bid.id) == *key
+                }) || PrivateBid::deserialized_from_bytes(value)
+                    .is_ok_and(|private_bid| private_bid.get_key() == *key)
+            }
+            Namespace::Pointer => MutableRecord::deserialized_from_bytes(value)
+                .is_ok_and(|record| record.is_valid(key)),
+            Namespace::Index => PostingsList::deserialized_from_bytes(value)
+                .is_ok_and(|postings| postings.is_valid(key)),
+            Namespace::UserData => {
+                UserRecord::deserialized_from_bytes(value).is_ok_and(|record| record.is_valid(key))
+            }
+        };
+        match belongs {
+            true => Ok(()),
+            false => Err("Value doesn't belong under its key"),
+        }
+    }
+
+    // Whether a stored value is never replaced by another until it expires, blocks and chunks
+    // are addressed by their content, auctions and bids by an ID fixed when they were created
+    pub fn is_immutable(self) -> bool {
+        matches!(
+            self,
+            Namespace::Block | Namespace::Chunk | Namespace::Auction | Namespace::Bid
+        )
+    }
+
+    // Namespace the value belongs in under the key, None if it belongs in none
+    // Every namespace binds its values to their keys, so a value belongs in one at most
+    pub fn of(key: &Key, value: &[u8]) -> Option<Namespace> {
+        Namespace::ALL
+            .into_iter()
+            .find(|namespace| namespace.validate(key, value).is_ok())
+    }
+
+    pub fn to_proto(self) -> communication::Namespace {
+        match self {
+            Namespace::Block => communication::Namespace::Block,
+            Namespace::Chunk => communication::Namespace::Chunk,
+            Namespace::Auction => communication::Namespace::Auction,
+            Namespace::Bid => communication::Namespace::Bid,
+            Namespace::Pointer => communication::Namespace::Pointer,
+            Namespace::Index => communication::Namespace::Index,
+            Namespace::UserData => communication::Namespace::UserData,
+        }
+    }
+
+    pub fn from_proto(proto: communication::Namespace) -> Namespace {
+        match proto {
+            communication::Namespace::Block => Namespace::Block,
+            communication::Namespace::Chunk => Namespace::Chunk,
+            communication::Namespace::Auction => Namespace::Auction,
+            communication::Namespace::Bid => Namespace::Bid,
+            communication::Namespace::Pointer => Namespace::Pointer,
+            communication::Namespace::Index => Namespace::Index,
+            communication::Namespace::UserData => Namespace::UserData,
+        }
+    }
+}
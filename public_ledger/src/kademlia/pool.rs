@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use tonic::transport::{Channel, Endpoint};
+
+use super::communication::kademlia_client::KademliaClient;
+use super::routing_table::params::{CONNECTION_IDLE_TIMEOUT, MAX_STREAMS_PER_PEER, RPC_TIMEOUT};
+
+// Pool of the connections to other nodes, every outgoing RPC goes through it
+// A connection is one HTTP/2 channel per peer address that requests to the peer are multiplexed
+// on, opened by its first request, with RPC_TIMEOUT to connect and to answer each request and at
+// most MAX_STREAMS_PER_PEER requests in flight, and closed once unused for CONNECTION_IDLE_TIMEOUT
+// A peer that went down is connected to again by the next request
+#[derive(Clone, Default)]
+pub(crate) struct ClientPool {
+    channels: Arc<Mutex<HashMap<String, PooledChannel>>>,
+}
+
+struct PooledChannel {
+    channel: Channel,
+    last_used: Instant,
+}
+
+impl ClientPool {
+    pub fn new() -> ClientPool {
+        ClientPool::default()
+    }
+
+    // Client of the node at the address over the pooled connection, None if the address is invalid
+    pub fn client(&self, ip: &str, port: u16) -> Option<KademliaClient<Channel>> {
+        let uri = format!("http://[{}]:{}", ip, port);
+        let now = Instant::now();
+        self.evict_idle(now);
+
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(pooled) = channels.get_mut(&uri) {
+            pooled.last_used = now;
+            return Some(KademliaClient::new(pooled.channel.clone()));
+        }
+        let channel = Endpoint::from_shared(uri.clone())
+            .ok()?
+            .connect_timeout(RPC_TIMEOUT)
+            .timeout(RPC_TIMEOUT)
+            .concurrency_limit(MAX_STREAMS_PER_PEER)
+            .connect_lazy();
+        channels.insert(
+            uri,
+            PooledChannel {
+                channel: channel.clone(),
+                last_used: now,
+            },
+        );
+        Some(KademliaClient::new(channel))
+    }
+
+    // Closes the connections unused for CONNECTION_IDLE_TIMEOUT, returns how many
+    // Requests still running on one keep it open until they are done
+    pub fn evict_idle(&self, now: Instant) -> usize {
+        let mut channels = self.channels.lock().unwrap();
+        let before = channels.len();
+        channels.retain(|_, pooled| {
+            now.saturating_duration_since(pooled.last_used) < CONNECTION_IDLE_TIMEOUT
+        });
+        before - channels.len()
+    }
+}
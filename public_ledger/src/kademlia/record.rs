@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+use crate::identity::{Identity, verify_signature};
+
+use super::key::Key;
+use super::namespace::Namespace;
+
+// Signed mutable record, like BitTorrent's BEP 44, for pointer keys updated in place such as
+// `latest_block`
+// As in BEP 44 the key is derived from the publisher's public key and a salt naming the pointer,
+// so every publisher has its own copy of a pointer and nobody else can move it
+// Storing nodes keep the record with the highest sequence number whose signature is valid, and
+// never let a plain value or an unsigned one replace it, so a pointer can only move forward
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MutableRecord {
+    // Publisher of this version
+    pub public_key: Vec<u8>,
+    // Name of the pointer, like `latest_block`
+    pub salt: String,
+    // Grows with every version, for `latest_block` the height of the block
+    pub seq: u64,
+    pub value: Vec<u8>,
+    // Publisher's signature of the key, the sequence number and the value
+    pub signature: Vec<u8>,
+}
+
+impl MutableRecord {
+    pub fn new(identity: &Identity, salt: &str, seq: u64, value: Vec<u8>) -> Self {
+        let mut record = MutableRecord {
+            public_key: identity.get_public_key(),
+            salt: salt.to_string(),
+            seq,
+            value,
+            signature: vec![],
+        };
+        record.signature = identity.sign(&record.signed_message(&record.get_key()));
+        record
+    }
+
+    // Publisher ID of a public key, nodes take the one of the key they sign records with as their
+    // node ID, so their pointers can be found from the routing table
+    pub fn publisher_id(public_key: &[u8]) -> Key {
+        Key::hash(public_key)
+    }
+
+    // Key of the publisher's copy of the pointer named by the salt
+    pub fn key(publisher_id: &Key, salt: &str) -> Key {
+        Namespace::Pointer.key(&format!("{}:{}", hex::encode(publisher_id), salt))
+    }
+
+    pub fn get_key(&self) -> Key {
+        MutableRecord::key(&MutableRecord::publisher_id(&self.public_key), &self.salt)
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+
+    // The key is signed too, so a record can't be replayed under another key
+    fn signed_message(&self, key: &Key) -> Vec<u8> {
+        serde_json::to_vec(&("mutable", hex::encode(key), self.seq, &self.value)).unwrap()
+    }
+
+    // Whether the record was signed by the publisher the key belongs to
+    pub fn is_valid(&self, key: &Key) -> bool {
+        self.get_key() == *key
+            && verify_signature(&self.public_key, &self.signed_message(key), &self.signature)
+    }
+
+    // Whether a value may be stored under the key over the one already there, by the rules of
+    // mutable records: a record needs a valid signature and a higher sequence number than the
+    // stored record, and nothing else replaces a record
+    // The key only accepts records of its own publisher
+    // None when neither value is a record
+    pub fn accepts(key: &Key, existing: Option<&[u8]>, incoming: &[u8]) -> Option<bool> {
+        let existing =
+            existing.and_then(|bytes| MutableRecord::deserialized_from_bytes(bytes).ok());
+        let Ok(incoming) = MutableRecord::deserialized_from_bytes(incoming) else {
+            return existing.map(|_| false);
+        };
+        let accepted = incoming.is_valid(key)
+            && existing.is_none_or(|existing| existing == incoming || existing.seq < incoming.seq);
+        Some(accepted)
+    }
+
+    // The freshest valid record of the key among serialized candidates, on equal sequence
+    // numbers the first one
+    pub fn freshest<'a>(
+        key: &Key,
+        candidates: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Option<MutableRecord> {
+        candidates
+            .into_iter()
+            .filter_map(|bytes| MutableRecord::deserialized_from_bytes(bytes).ok())
+            .filter(|record| record.is_valid(key))
+            .reduce(|freshest, record| match record.seq > freshest.seq {
+                true => record,
+                false => freshest,
+            })
+    }
+}
+
+// Value users store from the menu, along with the name it's stored under so storing nodes can
+// check the key is the name's, and nobody can take a key of another namespace for user data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct UserRecord {
+    pub name: String,
+    pub value: String,
+}
+
+impl UserRecord {
+    pub fn new(name: &str, value: &str) -> Self {
+        UserRecord {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn deserialized_from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn serialized_to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+
+    pub fn is_valid(&self, key: &Key) -> bool {
+        Namespace::UserData.key(&self.name) == *key
+    }
+}
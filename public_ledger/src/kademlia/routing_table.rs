@@ -1,9 +1,22 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::auction::index::PostingsList;
+use crate::identity::Identity;
+use crate::kademlia::content;
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+use crate::kademlia::pool::ClientPool;
+use crate::kademlia::record::MutableRecord;
+use crate::pubsub::EventBus;
+use params::StorageTimings;
+use storage::{Push, StoredValue, unix_now};
 
 pub(crate) mod k_bucket;
 pub(crate) mod node;
 pub(crate) mod node_id;
 pub(crate) mod params;
+pub(crate) mod storage;
 
 // The routing table is a list of node that joined the network by contacting the bootstrap node in this case curr_node the local node
 
@@ -14,20 +27,58 @@ pub(crate) struct RoutingTable {
     // This must be a vector of K-Buckets
     k_bucket_map: HashMap<u8, k_bucket::K_Bucket>,
     // Local Storage
-    local_storage: HashMap<[u8; 20], Vec<u8>>,
+    local_storage: HashMap<Key, StoredValue>,
+    // TTL, republish and replication timings of the stored values
+    storage_timings: StorageTimings,
+    // Key the node signs the mutable records it publishes with
+    node_identity: Identity,
+    // Subscribers to the events of the blocks stored here
+    event_bus: EventBus,
+    // Connections to the other nodes
+    client_pool: ClientPool,
+    // Last lookup through each bucket, buckets without one for the refresh interval are refreshed
+    last_lookups: HashMap<u8, Instant>,
 }
 
 impl RoutingTable {
     // Constructor - Creates a new Routing Table wich means a new node and an empty vector table
     pub fn new(ip: String, port: u16) -> RoutingTable {
-        let curr_node = node::Node::new(ip, port);
+        RoutingTable::with_storage_timings(ip, port, StorageTimings::default())
+    }
+
+    // Same with other storage timings, tests run the storage lifecycle in seconds
+    pub fn with_storage_timings(
+        ip: String,
+        port: u16,
+        storage_timings: StorageTimings,
+    ) -> RoutingTable {
+        // Our node ID is the publisher ID of our records, so peers know where to find them
+        let node_identity = Identity::generate();
+        let curr_node = node::Node::with_id(
+            MutableRecord::publisher_id(&node_identity.get_public_key()),
+            ip,
+            port,
+        );
         RoutingTable {
             curr_node,
             k_bucket_map: HashMap::new(),
             local_storage: HashMap::new(),
+            storage_timings,
+            node_identity,
+            event_bus: EventBus::new(),
+            client_pool: ClientPool::new(),
+            last_lookups: HashMap::new(),
         }
     }
 
+    pub fn get_node_identity(&self) -> &Identity {
+        &self.node_identity
+    }
+
+    pub fn get_storage_timings(&self) -> &StorageTimings {
+        &self.storage_timings
+    }
+
     // Get the current node
     pub fn get_curr_node(&self) -> &node::Node {
         &self.curr_node
@@ -35,29 +86,29 @@ impl RoutingTable {
 
     // Add a node to the routing table
     pub fn add_node(&mut self, node: node::Node) {
-        // Get the distance between the current node and the node to add
-        let distance = node_id::distance(self.get_curr_node().get_id(), node.get_id());
-        // Get the index of the bucket in the table
-        let index = node_id::find_k_bucket_index(&distance);
-
-        // Check if it's adding itself
-        if index == 160 {
+        // Get the index of the bucket in the table, none if it's adding itself
+        let Some(index) = self.get_curr_node().get_id().bucket_index(node.get_id()) else {
             return;
-        }
+        };
 
         // Check if the bucket exists
-        if !self.k_bucket_map.contains_key(&(index as u8)) {
+        if !self.k_bucket_map.contains_key(&index) {
             // Create a new bucket
-            self.k_bucket_map.insert(
-                index as u8,
-                k_bucket::K_Bucket::new(params::MAX_BUCKET_SIZE),
-            );
+            self.k_bucket_map
+                .insert(index, k_bucket::K_Bucket::new(params::MAX_BUCKET_SIZE));
         }
         // Add the node to the bucket
+        self.k_bucket_map.get_mut(&index).unwrap().add_node(node);
+    }
+
+    // The node of the ID, if it is in the routing table
+    pub fn get_node(&self, id: &Key) -> Option<&node::Node> {
+        let index = self.get_curr_node().get_id().bucket_index(id)?;
         self.k_bucket_map
-            .get_mut(&(index as u8))
-            .unwrap()
-            .add_node(node);
+            .get(&index)?
+            .get_nodes()
+            .iter()
+            .find(|node| node.get_id() == id)
     }
 
     // Get the number of nodes in the routing table
@@ -81,15 +132,15 @@ impl RoutingTable {
         all_nodes
     }
 
-    pub fn get_closest_k_nodes(&self, id: &[u8; 20], k: usize) -> Vec<node::Node> {
+    pub fn get_closest_k_nodes(&self, id: &Key, k: usize) -> Vec<node::Node> {
         // Get all nodes from the routing table
         let all_nodes_from_buckets = self.get_all_nodes();
 
         //Sort the nodes by distance to the given id
         let mut all_nodes = all_nodes_from_buckets;
         all_nodes.sort_by(|a, b| {
-            let dist_a = node_id::distance(id, a.get_id());
-            let dist_b = node_id::distance(id, b.get_id());
+            let dist_a = id.distance(a.get_id());
+            let dist_b = id.distance(b.get_id());
             dist_a.cmp(&dist_b)
         });
 
@@ -102,14 +153,235 @@ impl RoutingTable {
         all_nodes
     }
 
-    // Store a value in the local storage
-    pub fn store(&mut self, key: [u8; 20], value: Vec<u8>) {
-        self.local_storage.insert(key, value);
+    // Number of known nodes closer to the key than the node
+    pub fn count_closer_nodes(&self, key: &Key, id: &Key) -> usize {
+        let node_distance = key.distance(id);
+        self.get_all_nodes()
+            .iter()
+            .filter(|node| key.distance(node.get_id()) < node_distance)
+            .count()
+    }
+
+    fn get_node_mut(&mut self, id: &Key) -> Option<&mut node::Node> {
+        let index = self.curr_node.get_id().bucket_index(id)?;
+        self.k_bucket_map.get_mut(&index)?.get_node_mut(id)
+    }
+
+    // A lookup of the key goes through its bucket
+    pub fn touch_bucket(&mut self, key: &Key, now: Instant) {
+        if let Some(index) = self.curr_node.get_id().bucket_index(key) {
+            self.last_lookups.insert(index, now);
+        }
+    }
+
+    // Buckets no lookup went through for the refresh interval, from the farthest one to the
+    // closest one holding nodes, along with the buckets in between that don't exist yet
+    // The closer ones are filled by looking up our own ID instead
+    pub fn get_buckets_to_refresh(&self, now: Instant) -> Vec<u8> {
+        let Some(closest) = self.k_bucket_map.keys().max().copied() else {
+            return Vec::new();
+        };
+        let refresh_interval = self.storage_timings.refresh_interval;
+        (0..=closest)
+            .filter(|index| {
+                self.last_lookups.get(index).is_none_or(|last_lookup| {
+                    now.saturating_duration_since(*last_lookup) >= refresh_interval
+                })
+            })
+            .collect()
+    }
+
+    // A known node answered one of our RPCs, with its round-trip time, or sent us one
+    pub fn record_response(&mut self, id: &Key, rtt: Option<Duration>, now: Instant) {
+        if let Some(node) = self.get_node_mut(id) {
+            node.responded(rtt, now);
+        }
+    }
+
+    // A known node failed to answer one of our RPCs, after MAX_CONSECUTIVE_FAILURES in a row it's
+    // evicted from its bucket so lookups stop asking it, returns whether it was
+    pub fn record_failure(&mut self, id: &Key) -> bool {
+        let Some(node) = self.get_node_mut(id) else {
+            return false;
+        };
+        if node.failed() < params::MAX_CONSECUTIVE_FAILURES {
+            return false;
+        }
+        let Some(index) = self.curr_node.get_id().bucket_index(id) else {
+            return false;
+        };
+        let bucket = self.k_bucket_map.get_mut(&index).unwrap();
+        bucket.remove_node(id);
+        if bucket.get_size() == 0 {
+            self.k_bucket_map.remove(&index);
+        }
+        true
     }
 
-    // Get a value from the local storage
-    pub fn get(&self, key: [u8; 20]) -> Option<&Vec<u8>> {
-        self.local_storage.get(&key)
+    // Store a value in the local storage, blocks are also published to the subscribers
+    // and postings lists of the auction index are merged with the copy already stored
+    // A value published before the one stored is refused, unless it can be merged, so stale
+    // replicas and refreshes can't bring back an old value
+    // Mutable records follow their own rules instead, see `MutableRecord::accepts`, and values
+    // of the content-addressed namespace are never replaced, nor refused over a value that isn't,
+    // neither are other immutable values like auctions and bids (see `Namespace::is_immutable`)
+    // A key stays in its namespace until its value expires, unless the content it addresses comes,
+    // and the TTL is capped by the namespace's, returns whether the value was stored
+    pub fn store_record(
+        &mut self,
+        key: Key,
+        namespace: Namespace,
+        value: Vec<u8>,
+        published_at: u64,
+        ttl: Duration,
+        now: Instant,
+    ) -> bool {
+        let expires_at = now + ttl.min(namespace.ttl(&self.storage_timings));
+        let existing = self
+            .local_storage
+            .get(&key)
+            .filter(|stored| !stored.is_expired(now));
+        if existing.is_some_and(|existing| existing.namespace != namespace)
+            && !content::is_content_of(&key, &value)
+        {
+            return false;
+        }
+        let stored = match existing {
+            Some(existing) if existing.value == value => StoredValue {
+                namespace,
+                value,
+                published_at: existing.published_at.max(published_at),
+                expires_at: existing.expires_at.max(expires_at),
+                last_stored: now,
+                republish_at: existing.republish_at,
+            },
+            Some(existing) => {
+                let mergeable = PostingsList::deserialized_from_bytes(&value).is_ok();
+                let accepted = match (
+                    content::is_content_of(&key, &existing.value),
+                    content::is_content_of(&key, &value),
+                ) {
+                    (true, _) => false,
+                    (false, true) => true,
+                    (false, false) => {
+                        !namespace.is_immutable()
+                            && !content::is_misplaced_block(&key, &value)
+                            && MutableRecord::accepts(&key, Some(&existing.value), &value)
+                                .unwrap_or(existing.published_at <= published_at || mergeable)
+                    }
+                };
+                if !accepted {
+                    return false;
+                }
+                self.event_bus.publish_stored_value(&value);
+                StoredValue {
+                    namespace,
+                    value: PostingsList::merge_stored(Some(&existing.value), value),
+                    published_at: existing.published_at.max(published_at),
+                    expires_at,
+                    last_stored: now,
+                    // Another publication, whoever published it refreshes it
+                    republish_at: existing
+                        .republish_at
+                        .filter(|_| existing.published_at == published_at),
+                }
+            }
+            None => {
+                if MutableRecord::accepts(&key, None, &value) == Some(false)
+                    || content::is_misplaced_block(&key, &value)
+                {
+                    return false;
+                }
+                self.event_bus.publish_stored_value(&value);
+                StoredValue {
+                    namespace,
+                    value,
+                    published_at,
+                    expires_at,
+                    last_stored: now,
+                    republish_at: None,
+                }
+            }
+        };
+        self.local_storage.insert(key, stored);
+        true
+    }
+
+    // Store a value this node publishes, it refreshes it until it stops running
+    // Returns the publication time to send along with the value
+    pub fn publish(&mut self, key: Key, namespace: Namespace, value: Vec<u8>, now: Instant) -> u64 {
+        let published_at = unix_now();
+        let ttl = namespace.ttl(&self.storage_timings);
+        let republish_at = now + self.storage_timings.republish_interval;
+        if self.store_record(key, namespace, value, published_at, ttl, now)
+            && let Some(stored) = self.local_storage.get_mut(&key)
+        {
+            stored.republish_at = Some(republish_at);
+        }
+        published_at
+    }
+
+    pub fn get_event_bus(&self) -> &EventBus {
+        &self.event_bus
+    }
+
+    pub fn get_client_pool(&self) -> &ClientPool {
+        &self.client_pool
+    }
+
+    // Get a value from the local storage, expired values are gone even before `expire` runs
+    pub fn get(&self, key: Key) -> Option<&Vec<u8>> {
+        self.get_stored(key, Instant::now())
+            .map(|stored| &stored.value)
+    }
+
+    pub fn get_stored(&self, key: Key, now: Instant) -> Option<&StoredValue> {
+        self.local_storage
+            .get(&key)
+            .filter(|stored| !stored.is_expired(now))
+    }
+
+    // Drop the values nobody stored again in time, returns how many
+    pub fn expire(&mut self, now: Instant) -> usize {
+        let before = self.local_storage.len();
+        self.local_storage
+            .retain(|_, stored| !stored.is_expired(now));
+        before - self.local_storage.len()
+    }
+
+    // Values to push to the k closest nodes of their keys now:
+    // the ones this node published, with a fresh TTL, when their republish is due,
+    // and every other one not stored in the last replication interval, with the TTL it has left
+    // Their timers restart, so each is pushed once per interval
+    pub fn take_due_pushes(&mut self, now: Instant) -> Vec<Push> {
+        let timings = self.storage_timings;
+        let mut pushes = Vec::new();
+        for (key, stored) in self.local_storage.iter_mut() {
+            if stored.is_expired(now) {
+                continue;
+            }
+            let ttl = match stored.republish_at {
+                Some(republish_at) if republish_at <= now => {
+                    stored.republish_at = Some(now + timings.republish_interval);
+                    let ttl = stored.namespace.ttl(&timings);
+                    stored.expires_at = now + ttl;
+                    ttl
+                }
+                _ if now.duration_since(stored.last_stored) >= timings.replicate_interval => {
+                    stored.remaining_ttl(now)
+                }
+                _ => continue,
+            };
+            stored.last_stored = now;
+            pushes.push(Push {
+                key: *key,
+                namespace: stored.namespace,
+                value: stored.value.clone(),
+                published_at: stored.published_at,
+                ttl,
+            });
+        }
+        pushes
     }
 
     pub fn get_k_bucket(&self, index: u8) -> Option<&k_bucket::K_Bucket> {
@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 
 use super::{node::Node, params::MAX_BUCKET_SIZE};
+use crate::kademlia::key::Key;
 
 // K_Bucket is a struct that represents a bucket in the Kademlia DHT Tree
 #[derive(Clone)]
@@ -31,8 +32,8 @@ impl K_Bucket {
         if self.nodes.contains(&node) {
             // Move the node to the front of the bucket
             let index = self.nodes.iter().position(|x| *x == node).unwrap();
-            self.nodes.remove(index);
-            self.nodes.push_front(node);
+            let existing = self.nodes.remove(index).unwrap();
+            self.nodes.push_front(node.with_liveness_of(&existing));
         } else {
             // Check if the bucket is full
             if self.is_full() {
@@ -54,13 +55,14 @@ impl K_Bucket {
         &self.nodes
     }
 
-    pub fn get_random_node(&self) -> Option<Node> {
-        if self.nodes.is_empty() {
-            return None;
-        }
-        use rand::Rng;
-        let random_index = rand::rng().random_range(0..self.nodes.len());
-        Some(self.nodes[random_index].clone())
+    pub fn get_node_mut(&mut self, id: &Key) -> Option<&mut Node> {
+        self.nodes.iter_mut().find(|node| node.get_id() == id)
+    }
+
+    // Remove a node from the bucket
+    pub fn remove_node(&mut self, id: &Key) -> Option<Node> {
+        let index = self.nodes.iter().position(|node| node.get_id() == id)?;
+        self.nodes.remove(index)
     }
     
 }
\ No newline at end of file
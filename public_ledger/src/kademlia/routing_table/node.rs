@@ -1,39 +1,40 @@
-use crate::kademlia::communication;
+use std::time::{Duration, Instant};
 
-// Node of Kademlia DHT Tree
-use super::node_id::generate_node_id;
+use crate::kademlia::communication;
+use crate::kademlia::key::Key;
 
 #[derive(Clone)]
 pub(crate) struct Node {
   
-    // Unique Identifier of the Node / TODO: Change to Key Struct
-    id: [u8; 20], // 160-bit identifier
+    // Unique Identifier of the Node
+    id: Key, // 160-bit identifier
     // IP Address of the Node
     ip: String,
     // PORT of the Node
     port: u16,   
+    // Last time the Node answered or sent us an RPC, None if it never did
+    last_seen: Option<Instant>,
+    // Round-trip time of the last RPC the Node answered
+    rtt: Option<Duration>,
+    // RPCs in a row the Node failed to answer
+    failures: u32,
   }
   
   impl Node {
     // Constructor
-    pub fn new(ip: String, port: u16) -> Node {
+    pub fn with_id(id: impl Into<Key>, ip: String, port: u16) -> Node {
       Node {
-      id: generate_node_id(),
-      ip,
-      port,
-      }
-    }
-
-    pub fn with_id(id: [u8; 20], ip: String, port: u16) -> Node {
-      Node {
-      id,
+      id: id.into(),
       ip,
       port,
+      last_seen: None,
+      rtt: None,
+      failures: 0,
       }
     }
 
     // Get the Clone of the ID of the Node
-    pub fn get_id(&self) -> &[u8; 20] {
+    pub fn get_id(&self) -> &Key {
       &self.id
     }
 
@@ -55,11 +56,50 @@ pub(crate) struct Node {
       }
     }
 
-    pub fn from_proto(proto: &communication::Node) -> Node {
-      Node {
-          id: proto.id.clone().try_into().expect("Invalid ID length"),
+    // Node sent by another node, None if its ID or port is malformed
+    pub fn from_proto(proto: &communication::Node) -> Option<Node> {
+      Some(Node {
+          id: Key::from_slice(&proto.id)?,
           ip: proto.ip.clone(),
-          port: proto.port as u16,
+          port: u16::try_from(proto.port).ok()?,
+          last_seen: None,
+          rtt: None,
+          failures: 0,
+      })
+    }
+
+    pub fn get_last_seen(&self) -> Option<Instant> {
+      self.last_seen
+    }
+
+    pub fn get_rtt(&self) -> Option<Duration> {
+      self.rtt
+    }
+
+    pub fn get_failures(&self) -> u32 {
+      self.failures
+    }
+
+    // The Node answered an RPC, with its round-trip time, or sent us one
+    pub fn responded(&mut self, rtt: Option<Duration>, now: Instant) {
+      self.last_seen = Some(now);
+      self.rtt = rtt.or(self.rtt);
+      self.failures = 0;
+    }
+
+    // The Node failed to answer an RPC, returns how many in a row
+    pub fn failed(&mut self) -> u32 {
+      self.failures += 1;
+      self.failures
+    }
+
+    // Same Node at a maybe new address, keeping what we know of its liveness
+    pub fn with_liveness_of(self, other: &Node) -> Node {
+      Node {
+          last_seen: other.last_seen,
+          rtt: other.rtt,
+          failures: other.failures,
+          ..self
       }
     }
 
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 // MAX_BUCKET_SIZE is the maximum number of nodes that can be stored in a single bucket.
 // This is a constant that can be adjusted based on the expected size of the network and the desired performance characteristics.
 pub const MAX_BUCKET_SIZE: usize = 4;
@@ -6,3 +8,58 @@ pub const MAX_BUCKET_SIZE: usize = 4;
 // If higher, it will increase the load on the network, but may also speed up the search.
 // If lower, it will reduce the load on the network, but may slow down the search.
 pub const ALPHA: usize = 3;
+
+// MAX_VALUE_SIZE is the largest value (in bytes) a node accepts to store.
+// Larger data, like item attachments, must be split in chunks that fit in it.
+pub const MAX_VALUE_SIZE: usize = 256 * 1024;
+
+// RPC_TIMEOUT bounds each connect and request to another node, a node that doesn't answer in time counts as failed.
+pub const RPC_TIMEOUT: Duration = Duration::from_secs(5);
+
+// MAX_CONSECUTIVE_FAILURES is how many RPCs in a row a node may fail before it's evicted from its bucket.
+// Any answer or request from the node starts the count again.
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+// Connection pool: a connection to a peer is closed after CONNECTION_IDLE_TIMEOUT without requests,
+// and carries at most MAX_STREAMS_PER_PEER requests at a time, further ones wait for a free slot.
+pub const CONNECTION_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+pub const MAX_STREAMS_PER_PEER: usize = 16;
+
+// MAX_PUBLISHED_AT_SKEW is how many seconds ahead of the local clock a stored value's publication time may be.
+// Later times are cut down to it, so a value can't be dated in the future to outrank every later publication.
+pub const MAX_PUBLISHED_AT_SKEW: u64 = 10 * 60;
+
+// Storage lifecycle and bucket refresh, Kademlia's tExpire, tRepublish, tReplicate and tRefresh:
+// VALUE_TTL is how long a stored value lives unless it is stored again.
+// REPUBLISH_INTERVAL is how often the original publisher stores its values again, it must be shorter than VALUE_TTL.
+// REPLICATE_INTERVAL is how often a node pushes the values it holds to the current k closest nodes.
+// MAINTENANCE_INTERVAL is how often a node looks for expired values and values due for a push, and for buckets due for a refresh.
+// REFRESH_INTERVAL is how long a bucket may go without a lookup through it before a lookup of a random key in it refreshes it.
+pub const VALUE_TTL: Duration = Duration::from_secs(24 * 3600 + 10);
+pub const REPUBLISH_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+pub const REPLICATE_INTERVAL: Duration = Duration::from_secs(3600);
+pub const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60);
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+
+// Storage lifecycle and bucket refresh timings of a node, the constants above by default
+// Tests shorten them to seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageTimings {
+    pub value_ttl: Duration,
+    pub republish_interval: Duration,
+    pub replicate_interval: Duration,
+    pub maintenance_interval: Duration,
+    pub refresh_interval: Duration,
+}
+
+impl Default for StorageTimings {
+    fn default() -> Self {
+        StorageTimings {
+            value_ttl: VALUE_TTL,
+            republish_interval: REPUBLISH_INTERVAL,
+            replicate_interval: REPLICATE_INTERVAL,
+            maintenance_interval: MAINTENANCE_INTERVAL,
+            refresh_interval: REFRESH_INTERVAL,
+        }
+    }
+}
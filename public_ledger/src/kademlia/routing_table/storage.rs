@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+
+use super::params::MAX_PUBLISHED_AT_SKEW;
+
+// A value held in the local storage, with its place in the storage lifecycle
+#[derive(Debug, Clone)]
+pub(crate) struct StoredValue {
+    pub namespace: Namespace,
+    pub value: Vec<u8>,
+    // Unix time the original publisher published this version of the value
+    // A value is never replaced by another one published earlier
+    pub published_at: u64,
+    // Dropped after this, unless stored again
+    pub expires_at: Instant,
+    // Last time the value was stored here or pushed on, replication skips values stored since
+    pub last_stored: Instant,
+    // Only on the node that published the value: when it publishes it again
+    pub republish_at: Option<Instant>,
+}
+
+impl StoredValue {
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at <= now
+    }
+
+    // Time to live left, what a replica pushed now gets
+    pub fn remaining_ttl(&self, now: Instant) -> Duration {
+        self.expires_at.saturating_duration_since(now)
+    }
+}
+
+// A value to push to the k closest nodes of its key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Push {
+    pub key: Key,
+    pub namespace: Namespace,
+    pub value: Vec<u8>,
+    pub published_at: u64,
+    pub ttl: Duration,
+}
+
+// TTL of a copy cached along a lookup path, halved for every node closer to the key than the
+// caching node, so copies far from the key don't outlive the ones where it belongs
+pub fn cached_ttl(value_ttl: Duration, nodes_between: usize) -> Duration {
+    let halvings = u32::try_from(nodes_between).unwrap_or(u32::MAX).min(31);
+    value_ttl / (1u32 << halvings)
+}
+
+// Publication time to keep for a value received at the given unix time, 0 means published now
+// and a time too far ahead of the clock is cut down to it, so a value dated in the future can't
+// outrank every later publication
+pub fn received_published_at(published_at: u64, now: u64) -> u64 {
+    match published_at {
+        0 => now,
+        published_at => published_at.min(now + MAX_PUBLISHED_AT_SKEW),
+    }
+}
+
+// Current unix time, the publication time of values published now
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
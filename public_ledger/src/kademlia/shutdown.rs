@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+// Signal that stops a node's server and background maintenance, shared by its clones
+// Tasks check it between rounds of work, so none stops halfway through one
+#[derive(Clone)]
+pub(crate) struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Shutdown {
+        let (sender, _) = watch::channel(false);
+        Shutdown {
+            sender: Arc::new(sender),
+        }
+    }
+
+    // Stops everything waiting on the signal, for good
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    // Resolves once the signal is triggered, right away if it already was
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
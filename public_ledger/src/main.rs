@@ -1,4 +1,6 @@
 use app::AuctionApp;
+use app::proxy_agent::{ProxyAgents, wait_for_proxy_agents};
+use identity::Identity;
 use kademlia::communication::{FindValueRequest, PingRequest}; // Needed for proper request building
 use kademlia::{
     MyKademliaService, communication::kademlia_client::KademliaClient,
@@ -14,14 +16,28 @@ mod tests;
 mod app;
 mod auction;
 mod blockchain;
+mod identity;
 mod kademlia;
+mod pubsub;
+
+// Where the user's key pair is kept between runs, IDENTITY_FILE picks another file so several
+// users can run on the same machine
+const DEFAULT_IDENTITY_FILE: &str = "identity.pk8";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let identity_file =
+        std::env::var("IDENTITY_FILE").unwrap_or_else(|_| DEFAULT_IDENTITY_FILE.to_string());
+    let identity = Identity::load_or_generate(std::path::Path::new(&identity_file))?;
     let options = eframe::NativeOptions::default();
-    Ok(eframe::run_native(
+    let proxy_agents = ProxyAgents::default();
+    let app_proxy_agents = proxy_agents.clone();
+    let result = eframe::run_native(
         "Auction App",
         options,
-        Box::new(|_cc| Ok(Box::new(AuctionApp::new()))),
-    )?)
+        Box::new(|_cc| Ok(Box::new(AuctionApp::new(app_proxy_agents, identity)))),
+    );
+    // Proxy bids keep going after the window is closed, until their auctions end
+    wait_for_proxy_agents(&proxy_agents).await;
+    Ok(result?)
 }
@@ -0,0 +1,125 @@
+//! Publish/subscribe of chain events
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast;
+
+use crate::auction;
+use crate::auction::reputation::FeedbackTransaction;
+use crate::auction::seller::SellerTransaction;
+use crate::auction::settlement::SettlementTransaction;
+use crate::auction::signature::{AuctionSignature, BidSignature};
+use crate::blockchain::block::Block;
+use crate::kademlia::communication::Event;
+use crate::kademlia::key::Key;
+
+// Every new block
+pub const BLOCKS_TOPIC: &str = "blocks";
+// Every new auction, a single auction's topic is `auction::auction_key`
+pub const AUCTIONS_TOPIC: &str = "auctions";
+
+// How many events a slow subscriber can fall behind before it skips some
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+// How many block hashes are remembered to drop blocks heard from several peers
+const SEEN_BLOCKS_CAPACITY: usize = 1024;
+
+// Fans out the events the node learns about to its subscribers
+#[derive(Clone)]
+pub(crate) struct EventBus {
+    sender: broadcast::Sender<Event>,
+    seen_blocks: Arc<Mutex<VecDeque<Vec<u8>>>>,
+    // Peers whose block stream we are relaying
+    relays: Arc<Mutex<HashSet<Key>>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        EventBus {
+            sender,
+            seen_blocks: Arc::new(Mutex::new(VecDeque::new())),
+            relays: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    // Publishes the events of a block, false if it was already published
+    pub fn publish_block(&self, block: &Block) -> bool {
+        let block_hash = block.get_hash();
+        {
+            let mut seen_blocks = self.seen_blocks.lock().unwrap();
+            if seen_blocks.contains(&block_hash) {
+                return false;
+            }
+            if seen_blocks.len() == SEEN_BLOCKS_CAPACITY {
+                seen_blocks.pop_front();
+            }
+            seen_blocks.push_back(block_hash.clone());
+        }
+
+        let serialized = block.serialized().into_bytes();
+        let mut topics = vec![(BLOCKS_TOPIC.to_string(), "block")];
+        topics.extend(transaction_topics(block.get_transactions()));
+        for (topic, kind) in topics {
+            // Sending only fails when nobody is subscribed
+            let _ = self.sender.send(Event {
+                topic,
+                kind: kind.to_string(),
+                block_hash: block_hash.clone(),
+                block: serialized.clone(),
+            });
+        }
+        true
+    }
+
+    // Publishes a value stored in the DHT if it is a block
+    pub fn publish_stored_value(&self, value: &[u8]) {
+        if let Ok(block) = serde_json::from_slice::<Block>(value) {
+            self.publish_block(&block);
+        }
+    }
+
+    // Marks a peer as relayed, false if it already is
+    pub fn start_relay(&self, node_id: Key) -> bool {
+        self.relays.lock().unwrap().insert(node_id)
+    }
+
+    pub fn end_relay(&self, node_id: &Key) {
+        self.relays.lock().unwrap().remove(node_id);
+    }
+}
+
+// Topics a block's transaction is published on, with the kind of event
+fn transaction_topics(transaction: &[u8]) -> Vec<(String, &'static str)> {
+    if let Some(signature) = AuctionSignature::from_transaction(transaction) {
+        vec![
+            (AUCTIONS_TOPIC.to_string(), "auction"),
+            (auction::auction_key(&signature.auction_id), "auction"),
+        ]
+    } else if let Some(signature) = BidSignature::from_transaction(transaction) {
+        vec![(auction::auction_key(&signature.auction_id), "bid")]
+    } else if let Ok(seller) = SellerTransaction::deserialized_from_bytes(transaction) {
+        vec![(auction::auction_key(&seller.target_auction_id), "seller")]
+    } else if let Ok(settlement) = SettlementTransaction::deserialized_from_bytes(transaction) {
+        vec![(
+            auction::auction_key(&settlement.settled_auction_id),
+            "settlement",
+        )]
+    } else if let Ok(feedback) = FeedbackTransaction::deserialized_from_bytes(transaction) {
+        vec![(
+            auction::auction_key(&feedback.feedback_auction_id),
+            "feedback",
+        )]
+    } else {
+        vec![]
+    }
+}
@@ -1,5 +1,7 @@
 #[cfg(test)]
 mod tests {
+    mod helpers;
+
     // Test Block Functionality
     mod block;
     mod node_id;
@@ -7,4 +9,52 @@ mod tests {
     mod k_bucket;
 
     mod routing_table;
-}
\ No newline at end of file
+
+    mod money;
+
+    mod auction;
+
+    mod chain;
+
+    mod settlement;
+
+    mod lifecycle;
+
+    mod state;
+
+    mod proxy;
+
+    mod pubsub;
+
+    mod index;
+
+    mod attachment;
+
+    mod account;
+
+    mod reputation;
+
+    mod private;
+
+    mod lot;
+
+    mod conflict;
+
+    mod storage;
+
+    mod lookup;
+
+    mod record;
+
+    mod content;
+
+    mod key;
+
+    mod pool;
+
+    mod liveness;
+
+    mod maintenance;
+
+    mod identity;
+}
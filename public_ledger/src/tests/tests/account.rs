@@ -0,0 +1,279 @@
+use super::helpers::{build_chain, empty_blocks, eur, faucet};
+use crate::auction::Auction;
+use crate::auction::account::{Accounts, FaucetTransaction, MAX_FAUCET_GRANT, TransferTransaction};
+use crate::auction::bid::Bid;
+use crate::auction::lifecycle::LifecycleState;
+use crate::auction::money::{Currency, Money};
+use crate::auction::record::LedgerMode;
+use crate::auction::settlement::{SETTLEMENT_PERIOD, SettlementTransaction};
+use crate::auction::signature::BidSignature;
+use crate::identity::Identity;
+
+fn transfer(from: &Identity, to: &Identity, amount: Money) -> Vec<u8> {
+    TransferTransaction::new(from, to.get_public_key(), amount)
+        .serialized_to_bytes()
+        .unwrap()
+}
+
+// Test faucet grants and transfers, only signed and funded transfers apply, and the faucet
+// only counts in the genesis allocation unless it starts a test network
+#[test]
+fn test_faucet_and_transfers() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+
+    let mut forged = TransferTransaction::new(&bob, bob.get_public_key(), eur(100));
+    forged.transfer_from = alice.get_public_key();
+    let chain = build_chain(vec![
+        (faucet(&[&alice], eur(1_000)), 900),
+        // Over the cap, and after the genesis block, ignored
+        (faucet(&[&bob], eur(MAX_FAUCET_GRANT + 1)), 1_000),
+        // After the genesis block of a network that isn't a test network, ignored
+        (faucet(&[&bob], eur(500)), 1_050),
+        (transfer(&alice, &bob, eur(300)), 1_100),
+        // More than bob has
+        (transfer(&bob, &alice, eur(301)), 1_200),
+        // Not signed by alice
+        (forged.serialized_to_bytes().unwrap(), 1_300),
+    ]);
+
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert_eq!(
+        accounts.available(&alice.get_public_key(), Currency::Eur),
+        eur(700)
+    );
+    assert_eq!(
+        accounts.available(&bob.get_public_key(), Currency::Eur),
+        eur(300)
+    );
+    assert_eq!(
+        accounts.available(&alice.get_public_key(), Currency::Usd),
+        Money::zero(Currency::Usd)
+    );
+}
+
+// Test that a signed transfer mined again is only applied once
+#[test]
+fn test_transfer_replay() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+
+    let once = transfer(&alice, &bob, eur(300));
+    let chain = build_chain(vec![
+        (faucet(&[&alice], eur(1_000)), 900),
+        (once.clone(), 1_000),
+        (once, 1_100),
+        // A new transfer of the same amount has its own nonce and still applies
+        (transfer(&alice, &bob, eur(300)), 1_200),
+    ]);
+
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert_eq!(
+        accounts.available(&alice.get_public_key(), Currency::Eur),
+        eur(400)
+    );
+    assert_eq!(
+        accounts.available(&bob.get_public_key(), Currency::Eur),
+        eur(600)
+    );
+}
+
+// Test that on a test network, where anyone can use the faucet, grants over the cap are ignored,
+// whether in the genesis allocation or later, and that a transfer altered after signing is too
+#[test]
+fn test_faucet_cap_and_altered_transfer() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let test_network = |recipients: &[&Identity], amount: Money| {
+        let recipients = recipients.iter().map(|id| id.get_public_key()).collect();
+        FaucetTransaction::test_network(recipients, amount)
+            .serialized_to_bytes()
+            .unwrap()
+    };
+
+    // Over the cap from the start, the network is still a test network
+    let chain = build_chain(vec![
+        (test_network(&[&alice], eur(MAX_FAUCET_GRANT + 1)), 900),
+        (faucet(&[&bob], eur(MAX_FAUCET_GRANT)), 1_000),
+    ]);
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert_eq!(
+        accounts.available(&alice.get_public_key(), Currency::Eur),
+        eur(0)
+    );
+    assert_eq!(
+        accounts.available(&bob.get_public_key(), Currency::Eur),
+        eur(MAX_FAUCET_GRANT)
+    );
+
+    // The signature covers the amount and the recipient, so the nonce can't be reused for others
+    let mut raised = TransferTransaction::new(&alice, bob.get_public_key(), eur(100));
+    raised.transfer_amount = eur(900);
+    let mut redirected = TransferTransaction::new(&alice, bob.get_public_key(), eur(100));
+    redirected.transfer_to = vec![9u8; 32];
+    let chain = build_chain(vec![
+        (test_network(&[&alice], eur(1_000)), 900),
+        (faucet(&[&bob], eur(MAX_FAUCET_GRANT + 1)), 1_000),
+        (raised.serialized_to_bytes().unwrap(), 1_100),
+        (redirected.serialized_to_bytes().unwrap(), 1_200),
+    ]);
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert_eq!(
+        accounts.available(&alice.get_public_key(), Currency::Eur),
+        eur(1_000)
+    );
+    assert_eq!(
+        accounts.available(&bob.get_public_key(), Currency::Eur),
+        eur(0)
+    );
+    assert_eq!(accounts.available(&[9u8; 32], Currency::Eur), eur(0));
+}
+
+// Test that bids lock escrow, outbid bidders get it back and the seller is paid on settlement
+#[test]
+fn test_bid_escrow() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    );
+    let bid = |bidder: &Identity, amount: u64| {
+        Bid::new(auction.id.clone(), bidder.get_public_key(), eur(amount)).signed(bidder)
+    };
+    let alice_first = bid(&alice, 500);
+    // Bob only has 600
+    let bob_too_high = bid(&bob, 700);
+    let bob_bid = bid(&bob, 550);
+    // Alice can raise using her own escrow
+    let alice_raise = bid(&alice, 900);
+    // Not signed by the bidder it names
+    let unsigned = Bid::new(auction.id.clone(), alice.get_public_key(), eur(950));
+
+    let open_blocks = vec![
+        (
+            FaucetTransaction::test_network(vec![alice.get_public_key()], eur(1_000))
+                .serialized_to_bytes()
+                .unwrap(),
+            900,
+        ),
+        (faucet(&[&bob], eur(600)), 950),
+        (full.auction_transaction(&auction), 1_000),
+        (full.bid_transaction(&alice_first), 1_100),
+        (full.bid_transaction(&bob_too_high), 1_200),
+        (full.bid_transaction(&bob_bid), 1_300),
+        (full.bid_transaction(&alice_raise), 1_400),
+        (full.bid_transaction(&unsigned), 1_500),
+    ];
+    let chain = build_chain(open_blocks.clone());
+
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    let accepted: Vec<String> = accounts
+        .get_accepted_bids(&auction.id)
+        .into_iter()
+        .map(|bid| bid.id)
+        .collect();
+    assert_eq!(
+        accepted,
+        vec![
+            alice_first.id.clone(),
+            bob_bid.id.clone(),
+            alice_raise.id.clone()
+        ]
+    );
+    let alice_id = alice.get_public_key();
+    let bob_id = bob.get_public_key();
+    assert_eq!(accounts.available(&alice_id, Currency::Eur), eur(100));
+    assert_eq!(accounts.escrowed(&alice_id, Currency::Eur), eur(900));
+    assert_eq!(
+        accounts.spendable_for_bid(&alice_id, &auction.id, Currency::Eur),
+        eur(1_000)
+    );
+    // Outbid, released
+    assert_eq!(accounts.available(&bob_id, Currency::Eur), eur(600));
+    assert_eq!(accounts.escrowed(&bob_id, Currency::Eur), eur(0));
+
+    // The rule set sees the same bids
+    let verified = BidSignature::verify_bids(&chain, vec![], auction.clone());
+    assert_eq!(verified.len(), 3);
+
+    // Settling pays the winner's escrow to the seller, once the median time past closed the auction
+    let settlement = SettlementTransaction::new(&chain, &auction, vec![]);
+    assert_eq!(settlement.price_paid, Some(eur(900)));
+    let mut blocks = open_blocks;
+    blocks.extend(empty_blocks(5, 2_100));
+    blocks.push((settlement.serialized_to_bytes().unwrap(), 2_200));
+    let chain = build_chain(blocks);
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert!(accounts.get_escrows(&auction.id).is_empty());
+    assert_eq!(accounts.escrowed(&alice_id, Currency::Eur), eur(0));
+    assert_eq!(accounts.available(&alice_id, Currency::Eur), eur(100));
+    assert_eq!(
+        accounts.available(&seller.get_public_key(), Currency::Eur),
+        eur(900)
+    );
+}
+
+// Test that an auction nobody settles within the settlement period lapses, giving the winner's
+// escrow back, and that a settlement confirmed after that is ignored
+#[test]
+fn test_unsettled_auction_lapses() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    );
+    let alice_bid = Bid::new(auction.id.clone(), alice.get_public_key(), eur(500)).signed(&alice);
+
+    let mut blocks = vec![
+        (faucet(&[&alice], eur(1_000)), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (full.bid_transaction(&alice_bid), 1_100),
+    ];
+    blocks.extend(empty_blocks(5, 2_100));
+    let closed = build_chain(blocks.clone());
+    let closing_height = auction.closing_height(&closed).unwrap();
+    let settlement = SettlementTransaction::new(&closed, &auction, vec![]);
+    let alice_id = alice.get_public_key();
+
+    // Still locked on the last block the seller could settle in
+    let open_blocks = closing_height as usize + SETTLEMENT_PERIOD as usize - blocks.len();
+    blocks.extend(empty_blocks(open_blocks as u64, 2_200));
+    let chain = build_chain(blocks.clone());
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert_eq!(accounts.escrowed(&alice_id, Currency::Eur), eur(500));
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert_eq!(lifecycle.state, LifecycleState::Closed);
+    assert_eq!(
+        lifecycle.with_settlement(&chain, &auction).state,
+        LifecycleState::Closed
+    );
+
+    // The settlement comes too late, the auction lapsed and the seller gets nothing
+    blocks.push((settlement.serialized_to_bytes().unwrap(), 2_300));
+    let chain = build_chain(blocks);
+    assert!(SettlementTransaction::get_valid_settlement(&chain, &auction).is_none());
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert_eq!(
+        lifecycle.with_settlement(&chain, &auction).state,
+        LifecycleState::Lapsed
+    );
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert!(accounts.get_escrows(&auction.id).is_empty());
+    assert_eq!(accounts.escrowed(&alice_id, Currency::Eur), eur(0));
+    assert_eq!(accounts.available(&alice_id, Currency::Eur), eur(1_000));
+    assert_eq!(
+        accounts.available(&seller.get_public_key(), Currency::Eur),
+        eur(0)
+    );
+}
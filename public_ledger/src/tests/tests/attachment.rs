@@ -0,0 +1,116 @@
+use crate::auction::attachment::{
+    Attachment, AttachmentError, AttachmentManifest, CHUNK_SIZE, MAX_ATTACHMENT_SIZE, chunk_key,
+};
+
+// Test an attachment is split in content addressed chunks and put back together
+#[test]
+fn test_attachment_chunks() {
+    let bytes: Vec<u8> = (0..CHUNK_SIZE * 2 + 10).map(|i| (i % 251) as u8).collect();
+    let attachment = Attachment::new("photo.png".to_string(), &bytes).unwrap();
+    let manifest = &attachment.manifest;
+    assert_eq!(attachment.chunks.len(), 3);
+    assert_eq!(manifest.chunk_hashes.len(), 3);
+    assert_eq!(manifest.size, bytes.len() as u64);
+    assert!(manifest.is_image());
+    assert_ne!(
+        chunk_key(&manifest.chunk_hashes[0]),
+        chunk_key(&manifest.chunk_hashes[1])
+    );
+
+    let chunks: Vec<Option<Vec<u8>>> = attachment.chunks.iter().cloned().map(Some).collect();
+    assert_eq!(manifest.reassemble(chunks.clone()), Ok(bytes));
+
+    // A chunk that is missing or was tampered with is reported
+    let mut missing = chunks.clone();
+    missing[1] = None;
+    assert_eq!(
+        manifest.reassemble(missing),
+        Err(AttachmentError::MissingChunk(1))
+    );
+    let mut corrupt = chunks;
+    corrupt[2].as_mut().unwrap()[0] ^= 1;
+    assert_eq!(
+        manifest.reassemble(corrupt),
+        Err(AttachmentError::CorruptChunk(2))
+    );
+
+    // Manifests from other nodes with a hash that isn't one are refused as they are decoded
+    let serialized = serde_json::to_string(manifest).unwrap();
+    let decoded: AttachmentManifest = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(&decoded, manifest);
+    let mut short = manifest.clone();
+    short.chunk_hashes[1].truncate(4);
+    let error = serde_json::from_str::<AttachmentManifest>(&serde_json::to_string(&short).unwrap())
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains(&AttachmentError::InvalidChunkHash(1).to_string())
+    );
+    assert_eq!(chunk_key(&short.chunk_hashes[1]), None);
+
+    // So are manifests claiming a size over the limit, or a chunk count their size doesn't give
+    let mut huge = manifest.clone();
+    huge.size = 1 << 62;
+    let error = serde_json::from_str::<AttachmentManifest>(&serde_json::to_string(&huge).unwrap())
+        .unwrap_err();
+    assert!(error.to_string().contains("attachment too large"));
+    let mut extra = manifest.clone();
+    extra.chunk_hashes.push(extra.chunk_hashes[0].clone());
+    let error = serde_json::from_str::<AttachmentManifest>(&serde_json::to_string(&extra).unwrap())
+        .unwrap_err();
+    assert!(
+        error.to_string().contains(
+            &AttachmentError::ChunkCountMismatch {
+                expected: 3,
+                found: 4
+            }
+            .to_string()
+        )
+    );
+    assert_eq!(extra.chunks_to_fetch().count(), 3);
+
+    // Files over the limit are refused
+    let too_large = vec![0u8; MAX_ATTACHMENT_SIZE + 1];
+    assert_eq!(
+        Attachment::new("specs.pdf".to_string(), &too_large).unwrap_err(),
+        AttachmentError::TooLarge(MAX_ATTACHMENT_SIZE + 1)
+    );
+}
+
+// Test that chunks adding up to another size than the manifest claims, a short chunk list and
+// files that are missing or over the limit are reported instead of read
+#[test]
+fn test_attachment_errors() {
+    let attachment = Attachment::new("notes.txt".to_string(), b"ten bytes!").unwrap();
+    let chunks: Vec<Option<Vec<u8>>> = attachment.chunks.iter().cloned().map(Some).collect();
+
+    // Still one chunk, but the chunk holds fewer bytes than claimed
+    let mut padded = attachment.manifest.clone();
+    padded.size = 12;
+    assert_eq!(
+        padded.reassemble(chunks),
+        Err(AttachmentError::SizeMismatch {
+            expected: 12,
+            found: 10
+        })
+    );
+    assert_eq!(
+        attachment.manifest.reassemble(vec![]),
+        Err(AttachmentError::MissingChunk(0))
+    );
+
+    let path = std::env::temp_dir().join(format!("attachment-{}.bin", padded.content_id()));
+    assert!(matches!(
+        Attachment::from_file(&path),
+        Err(AttachmentError::Io(_))
+    ));
+    // Sized without writing the bytes, the limit is checked before reading
+    let file = std::fs::File::create(&path).unwrap();
+    file.set_len(MAX_ATTACHMENT_SIZE as u64 + 1).unwrap();
+    assert_eq!(
+        Attachment::from_file(&path).unwrap_err(),
+        AttachmentError::TooLarge(MAX_ATTACHMENT_SIZE + 1)
+    );
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,110 @@
+// Test that auction and bid IDs never collide and can't be forged
+#[test]
+fn test_auction_and_bid_ids_are_unique() {
+    use crate::auction::Auction;
+    use crate::auction::bid::Bid;
+    use crate::auction::money::{Currency, Money};
+
+    let seller = vec![1u8; 20];
+    let price = Money::new(100, Currency::Eur);
+    let a1 = Auction::new_with_duration(seller.clone(), "Laptop".to_string(), price, 0, 1);
+    let a2 = Auction::new_with_duration(seller.clone(), "Laptop".to_string(), price, 0, 1);
+    assert_ne!(a1.id, a2.id);
+    assert!(a1.has_valid_id());
+
+    // Same seller and nonce always give the same ID, another seller does not
+    assert_eq!(
+        Auction::derive_id(&seller, 7),
+        Auction::derive_id(&seller, 7)
+    );
+    assert_ne!(
+        Auction::derive_id(&seller, 7),
+        Auction::derive_id(&[2u8; 20], 7)
+    );
+
+    let mut forged = a1.clone();
+    forged.seller_id = vec![2u8; 20];
+    assert!(!forged.has_valid_id());
+
+    let b1 = Bid::new(a1.id.clone(), vec![3u8; 20], price);
+    let b2 = Bid::new(a1.id.clone(), vec![3u8; 20], price);
+    assert_ne!(b1.id, b2.id);
+    assert!(b1.has_valid_id());
+    let mut moved = b1.clone();
+    moved.auction_id = a2.id.clone();
+    assert!(!moved.has_valid_id());
+
+    // Whatever another node sends is decoded without panicking
+    let bytes = b1.serialized().into_bytes();
+    assert_eq!(Bid::deserialized_from_bytes(&bytes).unwrap().id, b1.id);
+    assert!(Bid::deserialized_from_bytes(&[0xff, 0xfe]).is_err());
+    assert!(Auction::deserialized_from_bytes(b"{\"id\": 1}").is_err());
+}
+
+// Test that records written with the sequential IDs of older versions are still readable, under
+// legacy IDs that no derived ID matches
+#[test]
+fn test_legacy_numeric_ids_migration() {
+    use crate::auction::money::{Currency, Money};
+    use crate::auction::{Auction, legacy_id};
+
+    let legacy = r#"{"id":3,"item_name":"Laptop","starting_price":12.34,"starting_time":0,"ending_time":3600,"bids":[{"id":1,"auction_id":3,"bidder_id":[1,2],"amount":15.0,"timestamp":10}]}"#;
+    let auction = Auction::deserialized_from_bytes(legacy.as_bytes()).unwrap();
+    assert_eq!(auction.id, legacy_id(3));
+    assert_eq!(auction.starting_price, Money::new(1234, Currency::Eur));
+    assert!(!auction.has_valid_id());
+
+    // Bids keep pointing at their auction
+    let bid = &auction.bids[0];
+    assert_eq!(bid.id, legacy_id(1));
+    assert_eq!(bid.auction_id, auction.id);
+    assert_eq!(bid.amount, Money::new(1500, Currency::Eur));
+    assert!(!bid.has_valid_id());
+
+    // Migrated records are written back with string IDs
+    let migrated = Auction::deserialized_from_bytes(auction.serialized().as_bytes()).unwrap();
+    assert_eq!(migrated.id, auction.id);
+    assert_eq!(migrated.bids[0].auction_id, auction.id);
+}
+
+// Test that auctions and bids are enumerated from the chain rather than from a counter
+#[test]
+fn test_enumerate_ids_from_chain() {
+    use crate::auction::Auction;
+    use crate::auction::bid::Bid;
+    use crate::auction::money::{Currency, Money};
+    use crate::auction::signature::{AuctionSignature, BidSignature};
+    use crate::blockchain::block::Block;
+    use crate::blockchain::block::block_body::BlockBody;
+    use crate::blockchain::block::block_header::BlockHeader;
+    use crate::blockchain::chain::Chain;
+
+    let price = Money::new(100, Currency::Eur);
+    let a1 = Auction::new_with_duration(vec![1u8; 20], "Laptop".to_string(), price, 0, 1);
+    let a2 = Auction::new_with_duration(vec![2u8; 20], "Phone".to_string(), price, 0, 1);
+    let bid = Bid::new(a1.id.clone(), vec![3u8; 20], price);
+
+    let transactions = vec![
+        AuctionSignature::new(a1.id.clone(), a1.get_hash())
+            .serialized_to_bytes()
+            .unwrap(),
+        AuctionSignature::new(a2.id.clone(), a2.get_hash())
+            .serialized_to_bytes()
+            .unwrap(),
+        BidSignature::new(bid.id.clone(), bid.auction_id.clone(), bid.get_hash())
+            .serialized_to_bytes()
+            .unwrap(),
+    ];
+    let mut chain = Chain::new();
+    for transaction in transactions {
+        let header = BlockHeader::new_with_timestamp(vec![0; 64], 0);
+        chain.add_block(Block::new(header, BlockBody::new(transaction)));
+    }
+
+    assert_eq!(
+        AuctionSignature::get_auction_ids(&chain),
+        vec![a1.id.clone(), a2.id.clone()]
+    );
+    assert_eq!(BidSignature::get_bid_ids(&chain, &a1.id), vec![bid.id]);
+    assert!(BidSignature::get_bid_ids(&chain, &a2.id).is_empty());
+}
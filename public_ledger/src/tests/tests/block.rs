@@ -6,7 +6,7 @@ fn test_block_mining() {
     use crate::blockchain::block::block_body::BlockBody;
     use crate::blockchain::block::block_header::BlockHeader;
 
-    let header = BlockHeader::new("parent_hash".as_bytes().to_vec());
+    let header = BlockHeader::new_with_timestamp("parent_hash".as_bytes().to_vec(), 0);
     let body = BlockBody::new("transactions".as_bytes().to_vec());
     let mut block = Block::new(header, body);
     block.mine();
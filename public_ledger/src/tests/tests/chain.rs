@@ -0,0 +1,142 @@
+use super::helpers::{build_chain, empty_blocks, genesis_allocation};
+use crate::blockchain::chain::Chain;
+
+// Test the median time past rule on block timestamps
+#[test]
+fn test_median_time_past() {
+    // Timestamps don't have to increase, only to beat the median of the previous blocks
+    let chain = build_chain(vec![
+        (vec![], 100),
+        (vec![], 300),
+        (vec![], 400),
+        (vec![], 350),
+    ]);
+    assert_eq!(
+        chain.get_block_at_height(0).unwrap().header.get_timestamp(),
+        100
+    );
+    assert_eq!(chain.median_time_past(0), 100);
+    assert_eq!(chain.median_time_past(2), 300);
+    assert_eq!(chain.median_time_past(3), 350);
+    assert!(chain.verify_timestamps(1_000, &Chain::new()));
+    assert_eq!(chain.get_next_timestamp(0), 351);
+    assert_eq!(chain.get_next_timestamp(1_000), 1_000);
+
+    // A block stamped before the median of its predecessors is rejected
+    let backdated = build_chain(vec![
+        (vec![], 100),
+        (vec![], 300),
+        (vec![], 400),
+        (vec![], 250),
+    ]);
+    assert!(!backdated.verify_timestamps(1_000, &Chain::new()));
+    assert!(chain.has_same_tip(&chain.clone()));
+    assert!(!chain.has_same_tip(&backdated));
+
+    // A new block too far ahead of the local clock is rejected
+    let future = build_chain(vec![(vec![], 100), (vec![], 100_000)]);
+    assert!(!future.verify_timestamps(1_000, &Chain::new()));
+
+    // Blocks already verified aren't held to a clock that fell behind, blocks past them are
+    assert!(future.verify_timestamps(1_000, &future));
+    let verified = build_chain(vec![(vec![], 100)]);
+    assert!(!future.verify_timestamps(1_000, &verified));
+}
+
+// Test that bids are ordered and cut off by the block that confirmed them, not the bidder's clock
+#[test]
+fn test_bids_ordered_by_block_inclusion() {
+    use crate::auction::Auction;
+    use crate::auction::bid::Bid;
+    use crate::auction::money::{Currency, Money};
+    use crate::auction::signature::{AuctionSignature, BidSignature};
+    use crate::identity::Identity;
+
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let carol = Identity::generate();
+
+    let auction = Auction::new(
+        vec![1u8; 20],
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let bid_signature = |bid: &Bid| {
+        BidSignature::new(bid.id.clone(), bid.auction_id.clone(), bid.get_hash())
+            .serialized_to_bytes()
+            .unwrap()
+    };
+
+    let mut early = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(500, Currency::Eur),
+    )
+    .signed(&alice);
+    early.timestamp = 1_900;
+    // Claims to be older, but was confirmed later, so it must outbid the early one
+    let mut backdated = Bid::new(
+        auction.id.clone(),
+        bob.get_public_key(),
+        Money::new(400, Currency::Eur),
+    )
+    .signed(&bob);
+    backdated.timestamp = 1_100;
+    // Confirmed after the closing block
+    let mut late = Bid::new(
+        auction.id.clone(),
+        carol.get_public_key(),
+        Money::new(900, Currency::Eur),
+    )
+    .signed(&carol);
+    late.timestamp = 1_950;
+
+    // The median time past passes the ending time at height 7
+    let chain = build_chain(
+        [
+            vec![
+                (genesis_allocation(&[&alice, &bob, &carol]), 900),
+                (
+                    AuctionSignature::new(auction.id.clone(), auction.get_hash())
+                        .serialized_to_bytes()
+                        .unwrap(),
+                    1_000,
+                ),
+                (bid_signature(&early), 1_500),
+                (bid_signature(&backdated), 1_600),
+            ],
+            empty_blocks(4, 2_100),
+            vec![(bid_signature(&late), 2_200)],
+        ]
+        .concat(),
+    );
+
+    assert_eq!(auction.closing_height(&chain), Some(7));
+    assert!(!auction.lifecycle(&chain).unwrap().is_active());
+
+    let verified = BidSignature::verify_bids(
+        &chain,
+        vec![late.clone(), backdated.clone(), early.clone()],
+        auction.clone(),
+    );
+    let verified_ids: Vec<&String> = verified.iter().map(|bid| &bid.id).collect();
+    assert_eq!(verified_ids, vec![&early.id]);
+    assert_eq!(
+        BidSignature::winning_bid(verified).map(|bid| bid.id),
+        Some(early.id)
+    );
+
+    // Before the closing block is mined the auction is still open
+    let open_chain = build_chain(vec![
+        (vec![], 900),
+        (
+            AuctionSignature::new(auction.id.clone(), auction.get_hash())
+                .serialized_to_bytes()
+                .unwrap(),
+            1_000,
+        ),
+    ]);
+    assert!(auction.lifecycle(&open_chain).unwrap().is_active());
+}
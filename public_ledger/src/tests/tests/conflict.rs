@@ -0,0 +1,150 @@
+use super::helpers::{build_chain, eur, faucet};
+use crate::auction::Auction;
+use crate::auction::account::Accounts;
+use crate::auction::bid::Bid;
+use crate::auction::money::{Currency, Money};
+use crate::auction::record::LedgerMode;
+use crate::auction::settlement::SettlementTransaction;
+use crate::auction::signature::BidSignature;
+use crate::identity::Identity;
+
+fn laptop(seller: &Identity) -> Auction {
+    Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    )
+}
+
+fn accepted_ids(accounts: &Accounts, auction: &Auction) -> Vec<String> {
+    accounts
+        .get_accepted_bids(&auction.id)
+        .into_iter()
+        .map(|bid| bid.id)
+        .collect()
+}
+
+// Test that of two equal bids the first confirmed wins, whatever the bidders' clocks say
+#[test]
+fn test_equal_bids_first_confirmed_wins() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let auction = laptop(&seller);
+    let alice_bid = Bid::new(auction.id.clone(), alice.get_public_key(), eur(500)).signed(&alice);
+    // Bob claims to have bid first
+    let mut bob_bid = Bid::new(auction.id.clone(), bob.get_public_key(), eur(500));
+    bob_bid.timestamp = alice_bid.timestamp - 60;
+    let bob_bid = bob_bid.signed(&bob);
+
+    let chain = build_chain(vec![
+        (faucet(&[&alice, &bob], eur(1_000)), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (full.bid_transaction(&alice_bid), 1_100),
+        (full.bid_transaction(&bob_bid), 1_200),
+    ]);
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert_eq!(
+        accepted_ids(&accounts, &auction),
+        vec![alice_bid.id.clone()]
+    );
+    let settlement = SettlementTransaction::new(&chain, &auction, vec![]);
+    assert_eq!(settlement.winning_bid_id, Some(alice_bid.id.clone()));
+
+    // Among equal bids the winning bid is the first one, never an arbitrary one
+    let winning_bid = BidSignature::winning_bid(vec![alice_bid.clone(), bob_bid.clone()]);
+    assert_eq!(winning_bid.map(|bid| bid.id), Some(alice_bid.id.clone()));
+    let winning_bid = BidSignature::winning_bid(vec![bob_bid.clone(), alice_bid]);
+    assert_eq!(winning_bid.map(|bid| bid.id), Some(bob_bid.id));
+}
+
+// Test that a bid ID counts once: later versions, repeated and unsigned commitments are ignored
+#[test]
+fn test_duplicate_bid_ids() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let auction = laptop(&seller);
+    let first_version = Bid::new(auction.id.clone(), alice.get_public_key(), eur(300));
+    // Same nonce, so the same ID, but another amount
+    let mut second_version = first_version.clone();
+    second_version.amount = eur(900);
+    let first_version = first_version.signed(&alice);
+    let second_version = second_version.signed(&alice);
+    assert_eq!(first_version.id, second_version.id);
+
+    // Bob commits a record alice never signed under her next bid's ID, before she does
+    let raise = Bid::new(auction.id.clone(), alice.get_public_key(), eur(600)).signed(&alice);
+    let mut hijacked = raise.clone();
+    hijacked.amount = eur(1);
+
+    let chain = build_chain(vec![
+        (faucet(&[&alice, &bob], eur(1_000)), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (full.bid_transaction(&first_version), 1_100),
+        (full.bid_transaction(&second_version), 1_200),
+        // Confirmed again, still counts once
+        (full.bid_transaction(&first_version), 1_300),
+        (full.bid_transaction(&hijacked), 1_400),
+        (full.bid_transaction(&raise), 1_500),
+    ]);
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    let accepted = accounts.get_accepted_bids(&auction.id);
+    let accepted: Vec<(String, Money)> = accepted
+        .into_iter()
+        .map(|bid| (bid.id, bid.amount))
+        .collect();
+    assert_eq!(
+        accepted,
+        vec![
+            (first_version.id.clone(), eur(300)),
+            (raise.id.clone(), eur(600))
+        ]
+    );
+    assert_eq!(
+        accounts.escrowed(&alice.get_public_key(), Currency::Eur),
+        eur(600)
+    );
+
+    // The settlement names the signed record by its hash, not just its ID
+    let settlement = SettlementTransaction::new(&chain, &auction, vec![]);
+    assert_eq!(settlement.winning_bid_hash, Some(raise.get_hash()));
+}
+
+// Test a bidder's own bids racing each other: a raise reuses the escrow it outranks, a later,
+// lower bid of the same bidder is rejected
+#[test]
+fn test_own_bids_racing() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let auction = laptop(&seller);
+    let bid = |amount: u64| {
+        Bid::new(auction.id.clone(), alice.get_public_key(), eur(amount)).signed(&alice)
+    };
+    let first = bid(600);
+    // More than alice has left once the first is locked, but it replaces it
+    let raise = bid(800);
+    // Sent before the raise but confirmed after it
+    let stale = bid(700);
+
+    let chain = build_chain(vec![
+        (faucet(&[&alice], eur(1_000)), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (full.bid_transaction(&first), 1_100),
+        (full.bid_transaction(&raise), 1_200),
+        (full.bid_transaction(&stale), 1_300),
+    ]);
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert_eq!(
+        accepted_ids(&accounts, &auction),
+        vec![first.id.clone(), raise.id.clone()]
+    );
+    let alice_id = alice.get_public_key();
+    assert_eq!(accounts.escrowed(&alice_id, Currency::Eur), eur(800));
+    assert_eq!(accounts.available(&alice_id, Currency::Eur), eur(200));
+}
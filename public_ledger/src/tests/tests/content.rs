@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+use crate::auction::attachment::CHUNK_SIZE;
+use crate::blockchain::block::Block;
+use crate::kademlia::content::{block_key, content_key, is_content_of, is_misplaced_block};
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+use crate::kademlia::routing_table::RoutingTable;
+
+// Test that blocks and chunks are only stored under the key of their hash and never replaced,
+// while a value squatting their key gives way to them, even from another namespace
+#[test]
+fn test_content_addressed_store() {
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    let (now, ttl) = (Instant::now(), Duration::from_secs(60));
+    let mut store = |key: Key, namespace: Namespace, value: &[u8], published_at: u64| {
+        routing_table.store_record(key, namespace, value.to_vec(), published_at, ttl, now)
+    };
+
+    let genesis = Block::genesis(vec![]);
+    let block = genesis.serialized().into_bytes();
+    let key = block_key(&genesis.get_hash());
+    assert!(is_content_of(&key, &block));
+    // A block under another key, and anything over a stored block
+    assert!(!store(Key::from([1; 20]), Namespace::Block, &block, 100));
+    assert!(store(key, Namespace::Block, &block, 100));
+    assert!(!store(key, Namespace::Block, b"tampered block", 200));
+    let mut tampered = genesis.clone();
+    tampered.header.set_nonce(genesis.get_nonce() + 1);
+    assert!(!store(
+        key,
+        Namespace::Block,
+        tampered.serialized().as_bytes(),
+        200
+    ));
+    // The same block again is fine
+    assert!(store(key, Namespace::Block, &block, 200));
+
+    let chunk = b"attachment chunk";
+    let chunk_key = content_key(chunk);
+    assert!(store(chunk_key, Namespace::UserData, b"squatter", 300));
+    assert!(store(chunk_key, Namespace::Chunk, chunk, 100));
+    assert!(!store(chunk_key, Namespace::UserData, b"squatter", 400));
+    assert_eq!(routing_table.get(chunk_key), Some(&chunk.to_vec()));
+}
+
+// Test that values that don't hash to their key are refused: chunks over the chunk size or under
+// another key, blocks from any namespace under a key that isn't theirs, and tampered chunks over
+// a stored one
+#[test]
+fn test_content_mismatch_refused() {
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    let (now, ttl) = (Instant::now(), Duration::from_secs(60));
+
+    let chunk = b"attachment chunk".to_vec();
+    let chunk_key = content_key(&chunk);
+    let other_key = content_key(b"another chunk");
+    assert!(Namespace::Chunk.validate(&chunk_key, &chunk).is_ok());
+    assert!(Namespace::Chunk.validate(&other_key, &chunk).is_err());
+    let oversized = vec![0u8; CHUNK_SIZE + 1];
+    assert_eq!(
+        Namespace::Chunk.validate(&content_key(&oversized), &oversized),
+        Err("Value too large for its namespace")
+    );
+
+    // A block is misplaced anywhere but under its own key, whatever namespace it claims
+    let genesis = Block::genesis(vec![]);
+    let block = genesis.serialized().into_bytes();
+    assert!(!is_misplaced_block(&block_key(&genesis.get_hash()), &block));
+    assert!(is_misplaced_block(&other_key, &block));
+    assert!(!is_misplaced_block(&other_key, &chunk));
+    assert!(!routing_table.store_record(
+        other_key,
+        Namespace::UserData,
+        block.clone(),
+        100,
+        ttl,
+        now
+    ));
+    assert_eq!(routing_table.get(other_key), None);
+
+    // The stored chunk stays, a tampered copy doesn't hash to its key
+    assert!(routing_table.store_record(chunk_key, Namespace::Chunk, chunk.clone(), 100, ttl, now));
+    let mut tampered = chunk.clone();
+    tampered[0] ^= 1;
+    assert!(!is_content_of(&chunk_key, &tampered));
+    assert!(!routing_table.store_record(chunk_key, Namespace::Chunk, tampered, 200, ttl, now));
+    assert_eq!(routing_table.get(chunk_key), Some(&chunk));
+}
@@ -0,0 +1,68 @@
+use crate::auction::Auction;
+use crate::auction::account::{FaucetTransaction, MAX_FAUCET_GRANT};
+use crate::auction::bid::Bid;
+use crate::auction::money::{Currency, Money};
+use crate::auction::signature::{AuctionSignature, BidSignature};
+use crate::blockchain::block::Block;
+use crate::blockchain::block::block_body::BlockBody;
+use crate::blockchain::block::block_header::BlockHeader;
+use crate::blockchain::chain::Chain;
+use crate::identity::Identity;
+
+// Builds a mined chain (latest block first, like `fetch_full_chain`) from transactions and timestamps
+pub fn build_chain(blocks: Vec<(Vec<u8>, u64)>) -> Chain {
+    let mut ordered = Vec::new();
+    let mut prev_hash = vec![0; 64];
+    for (transaction, timestamp) in blocks {
+        let header = BlockHeader::new_with_timestamp(prev_hash, timestamp);
+        let mut block = Block::new(header, BlockBody::new(transaction));
+        block.mine();
+        prev_hash = block.get_hash();
+        ordered.push(block);
+    }
+    let mut chain = Chain::new();
+    for block in ordered.into_iter().rev() {
+        chain.add_block(block);
+    }
+    chain
+}
+
+// Empty blocks stamped a second apart from the given time, enough of them (more than half of
+// MEDIAN_TIME_SPAN) bring the median time past there
+pub fn empty_blocks(count: u64, timestamp: u64) -> Vec<(Vec<u8>, u64)> {
+    (0..count).map(|i| (vec![], timestamp + i)).collect()
+}
+
+// Hash commitment of an auction, as mined on the chain
+pub fn auction_signature(auction: &Auction) -> Vec<u8> {
+    AuctionSignature::new(auction.id.clone(), auction.get_hash())
+        .serialized_to_bytes()
+        .unwrap()
+}
+
+// Hash commitment of a bid, as mined on the chain
+pub fn bid_signature(bid: &Bid) -> Vec<u8> {
+    BidSignature::new(bid.id.clone(), bid.auction_id.clone(), bid.get_hash())
+        .serialized_to_bytes()
+        .unwrap()
+}
+
+pub fn eur(minor_units: u64) -> Money {
+    Money::new(minor_units, Currency::Eur)
+}
+
+// Faucet transaction granting the amount to every recipient
+pub fn faucet(recipients: &[&Identity], amount: Money) -> Vec<u8> {
+    let recipients = recipients
+        .iter()
+        .map(|identity| identity.get_public_key())
+        .collect();
+    FaucetTransaction::new(recipients, amount)
+        .serialized_to_bytes()
+        .unwrap()
+}
+
+// Genesis transaction funding the given bidders through the faucet
+pub fn genesis_allocation(accounts: &[&Identity]) -> Vec<u8> {
+    faucet(accounts, eur(MAX_FAUCET_GRANT))
+}
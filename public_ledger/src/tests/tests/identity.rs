@@ -0,0 +1,19 @@
+use crate::identity::Identity;
+
+// Test that the identity saved on the first run is the one loaded on the next ones, and that a
+// corrupted key file is an error rather than a new identity
+#[test]
+fn test_identity_persisted() {
+    let path = std::env::temp_dir().join(format!(
+        "identity-{}.pk8",
+        hex::encode(Identity::generate().get_public_key())
+    ));
+    let first = Identity::load_or_generate(&path).unwrap();
+    let second = Identity::load_or_generate(&path).unwrap();
+    assert_eq!(first.get_public_key(), second.get_public_key());
+    assert_eq!(first.sign(b"message"), second.sign(b"message"));
+
+    std::fs::write(&path, b"not a key").unwrap();
+    assert!(Identity::load_or_generate(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
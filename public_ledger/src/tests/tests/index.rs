@@ -0,0 +1,155 @@
+use crate::auction::Auction;
+use crate::auction::index::{self, AuctionQuery, ENDING_BUCKET_SECONDS, IndexEntry, PostingsList};
+use crate::auction::money::{Currency, Money};
+use crate::kademlia::namespace::Namespace;
+
+fn entry(auction_id: &str, ending_time: u64) -> IndexEntry {
+    IndexEntry {
+        auction_id: auction_id.to_string(),
+        ending_time,
+    }
+}
+
+// Test an auction is filed under its words, category, seller and ending day
+#[test]
+fn test_index_keys() {
+    assert_eq!(
+        index::keywords("Vintage camera, vintage LENS a"),
+        vec!["vintage", "camera", "lens"]
+    );
+
+    let auction = Auction::new(
+        vec![1u8; 32],
+        "Camera".to_string(),
+        Money::new(1_000, Currency::Eur),
+        1_000,
+        ENDING_BUCKET_SECONDS * 3 + 10,
+    )
+    .with_description("Old lens".to_string())
+    .with_category(" Photo ".to_string());
+    assert_eq!(
+        index::index_keys(&auction),
+        vec![
+            index::keyword_key("camera"),
+            index::keyword_key("old"),
+            index::keyword_key("lens"),
+            index::category_key("photo"),
+            index::seller_key(&[1u8; 32]),
+            index::ending_key(3),
+        ]
+    );
+}
+
+// Test groups are intersected, lists within a group united, and the ending range filtered and
+// capped
+#[test]
+fn test_query_combine() {
+    let day = ENDING_BUCKET_SECONDS;
+    let query = AuctionQuery {
+        keywords: vec!["camera".to_string()],
+        category: None,
+        seller_id: None,
+        ending_between: Some((day + 100, 2 * day + 100)),
+    };
+    let groups = query.key_groups();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[1], vec![index::ending_key(1), index::ending_key(2)]);
+
+    let keyword_list = PostingsList::new(
+        &index::keyword_key("laptop"),
+        vec![
+            entry("a", day + 200),
+            entry("b", day + 50),
+            entry("c", 2 * day + 50),
+            entry("d", 5 * day),
+        ],
+    );
+    let ending_lists = vec![
+        PostingsList::new(
+            &index::ending_key(1),
+            vec![entry("a", day + 200), entry("b", day + 50)],
+        ),
+        PostingsList::new(&index::ending_key(2), vec![entry("c", 2 * day + 50)]),
+    ];
+    // b is in the first day bucket but ends before the range, d is in neither bucket
+    assert_eq!(
+        query.combine(vec![vec![keyword_list], ending_lists]),
+        vec!["a".to_string(), "c".to_string()]
+    );
+    assert!(AuctionQuery::default().is_empty());
+
+    // However far the range goes, a bounded number of days is looked up
+    let unbounded = AuctionQuery {
+        ending_between: Some((day, u64::MAX)),
+        ..AuctionQuery::default()
+    };
+    assert_eq!(
+        unbounded.key_groups()[0].len() as u64,
+        index::MAX_ENDING_BUCKETS
+    );
+
+    // The longest range a search may ask for covers its last day even when it starts mid-day
+    let from = day + day / 2;
+    let to = from + index::MAX_ENDING_HOURS * 3600;
+    let longest = AuctionQuery {
+        ending_between: Some((from, to)),
+        ..AuctionQuery::default()
+    };
+    let buckets = &longest.key_groups()[0];
+    assert_eq!(buckets.first(), Some(&index::ending_key(1)));
+    assert_eq!(buckets.last(), Some(&index::ending_key(to / day)));
+    assert!(longest.ends_in_range(to));
+    assert!(!longest.ends_in_range(from - 1));
+}
+
+// Test a stored postings list is merged with the one already held
+#[test]
+fn test_postings_merge_stored() {
+    let existing = PostingsList::new("keyword:laptop", vec![entry("a", 1)])
+        .serialized()
+        .into_bytes();
+    let incoming = PostingsList::new("keyword:laptop", vec![entry("b", 2), entry("a", 1)])
+        .serialized()
+        .into_bytes();
+    let merged = PostingsList::merge_stored(Some(&existing), incoming.clone());
+    assert_eq!(
+        PostingsList::deserialized_from_bytes(&merged).unwrap(),
+        PostingsList::new("keyword:laptop", vec![entry("a", 1), entry("b", 2)])
+    );
+
+    // A list of another key isn't merged in
+    let other = PostingsList::new("keyword:camera", vec![entry("c", 3)])
+        .serialized()
+        .into_bytes();
+    assert_eq!(
+        PostingsList::merge_stored(Some(&merged), other.clone()),
+        other
+    );
+
+    // Anything else is simply replaced
+    assert_eq!(PostingsList::merge_stored(None, incoming.clone()), incoming);
+    assert_eq!(
+        PostingsList::merge_stored(Some(&b"block".to_vec()), incoming.clone()),
+        incoming
+    );
+}
+
+// Test a postings list is only accepted under the key of its name, and an ending day's list only
+// with auctions ending that day
+#[test]
+fn test_postings_list_keys() {
+    let day = ENDING_BUCKET_SECONDS;
+    let accepts = |key: &str, list: &PostingsList| {
+        Namespace::Index
+            .validate(&Namespace::Index.key(key), list.serialized().as_bytes())
+            .is_ok()
+    };
+    let laptops = PostingsList::new(&index::keyword_key("laptop"), vec![entry("a", day)]);
+    assert!(accepts(&index::keyword_key("laptop"), &laptops));
+    assert!(!accepts(&index::keyword_key("camera"), &laptops));
+
+    let ending = PostingsList::new(&index::ending_key(1), vec![entry("a", day + 10)]);
+    assert!(accepts(&index::ending_key(1), &ending));
+    let misfiled = PostingsList::new(&index::ending_key(1), vec![entry("a", 3 * day)]);
+    assert!(!accepts(&index::ending_key(1), &misfiled));
+}
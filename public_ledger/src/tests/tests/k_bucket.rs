@@ -1,15 +1,16 @@
 #[test]
 fn test_k_bucket_node_order(){
+    use crate::kademlia::key::Key;
     use crate::kademlia::routing_table::k_bucket::K_Bucket;
     use crate::kademlia::routing_table::node::Node;
 
     let mut bucket = K_Bucket::new(5);
-    let node1: Node = Node::new("127.0.0.1".to_string(), 1);
-    let node1_id: [u8; 20] = *node1.get_id();
-    let node2 = Node::new("127.0.0.1".to_string(), 2);
-    let node2_id: [u8; 20] = *node2.get_id();
-    let node3 = Node::new("127.0.0.1".to_string(), 3);
-    let node3_id: [u8; 20] = *node3.get_id();
+    let node1: Node = Node::with_id(Key::random(), "127.0.0.1".to_string(), 1);
+    let node1_id: Key = *node1.get_id();
+    let node2 = Node::with_id(Key::random(), "127.0.0.1".to_string(), 2);
+    let node2_id: Key = *node2.get_id();
+    let node3 = Node::with_id(Key::random(), "127.0.0.1".to_string(), 3);
+    let node3_id: Key = *node3.get_id();
     bucket.add_node(node1); 
     bucket.add_node(node2);
     bucket.add_node(node3);
@@ -24,13 +25,14 @@ fn test_k_bucket_node_order(){
 
 #[test]
 fn test_get_closest_k_nodes_returns_closest() {
+    use crate::kademlia::key::Key;
     use crate::kademlia::routing_table::{RoutingTable, node::Node};
     use crate::kademlia::routing_table::node_id;
 
     let mut rt = RoutingTable::new("127.0.0.1".to_string(), 8080); // Assuming your RoutingTable implements k_bucket_map internally
 
     // Manually create nodes with known IDs
-    let target_id: [u8; 20] = [0b00000000; 20];
+    let target_id = Key::from([0b00000000; 20]);
 
     // Closer in XOR space
     let node1 = Node::with_id([0b00000001; 20], "127.0.0.1".to_string(), 1); // XOR distance: 1
@@ -53,7 +55,7 @@ fn test_get_closest_k_nodes_returns_closest() {
         node3.get_id(),
         node2.get_id(),];
 
-    let result_ids: Vec<&[u8; 20]> = closest.iter().map(|n| n.get_id()).collect();
+    let result_ids: Vec<&Key> = closest.iter().map(|n| n.get_id()).collect();
 
     for i in rt.get_all_nodes().iter() {
         println!("Node ID: {:?}", i.get_id());
@@ -0,0 +1,191 @@
+use std::time::{Duration, Instant};
+
+use crate::auction::bid::Bid;
+use crate::auction::money::{Currency, Money};
+use crate::auction::{Auction, bid_key};
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+use crate::kademlia::record::UserRecord;
+use crate::kademlia::routing_table::RoutingTable;
+
+// Test that keys measure XOR distance and find the bucket of another key by the first bit where
+// they differ
+#[test]
+fn test_key_distance_and_buckets() {
+    let zero = Key::from([0; 20]);
+    let mut last_bit = [0; 20];
+    last_bit[19] = 1;
+    let last_bit = Key::from(last_bit);
+    let first_bit = Key::from([0x80; 20]);
+
+    assert_eq!(zero.distance(&first_bit), first_bit);
+    assert_eq!(first_bit.distance(&last_bit), last_bit.distance(&first_bit));
+    assert!(zero.distance(&last_bit) < zero.distance(&first_bit));
+    assert_eq!(zero.bucket_index(&first_bit), Some(0));
+    assert_eq!(zero.bucket_index(&last_bit), Some(159));
+    assert_eq!(zero.bucket_index(&zero), None);
+    assert_eq!(Key::from_slice(&[0; 20]), Some(zero));
+    assert_eq!(Key::from_slice(&[0; 32]), None);
+}
+
+// Test that each namespace derives its own keys and only accepts values that belong under them,
+// up to its size limit, and that a key stays in the namespace it was stored in
+#[test]
+fn test_namespaces() {
+    assert_ne!(Namespace::Auction.key("1"), Namespace::UserData.key("1"));
+
+    let auction = Auction::new(
+        vec![1u8; 32],
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let accepts =
+        |namespace: Namespace, key: Key, value: &[u8]| namespace.validate(&key, value).is_ok();
+    let auction_bytes = auction.serialized().into_bytes();
+    let auction_dht_key = Namespace::Auction.key(&auction.id);
+    assert!(accepts(Namespace::Auction, auction_dht_key, &auction_bytes));
+    let other_key = Namespace::Auction.key("other");
+    assert!(!accepts(Namespace::Auction, other_key, &auction_bytes));
+    assert!(!accepts(
+        Namespace::Auction,
+        auction_dht_key,
+        b"not an auction"
+    ));
+
+    let bid = Bid::new(
+        auction.id.clone(),
+        vec![2u8; 32],
+        Money::new(300, Currency::Eur),
+    );
+    let bid_bytes = bid.serialized().into_bytes();
+    assert!(accepts(
+        Namespace::Bid,
+        bid_key(&auction.id, &bid.id),
+        &bid_bytes
+    ));
+    assert!(!accepts(
+        Namespace::Bid,
+        bid_key("other", &bid.id),
+        &bid_bytes
+    ));
+    // Pointers hold signed records only
+    assert!(!accepts(
+        Namespace::Pointer,
+        auction_dht_key,
+        &auction_bytes
+    ));
+
+    // User data only goes under the key of its own name, so it can't take another's key
+    let user_key = Namespace::UserData.key("notes");
+    let note = UserRecord::new("notes", "anything")
+        .serialized_to_bytes()
+        .unwrap();
+    assert!(accepts(Namespace::UserData, user_key, &note));
+    assert!(!accepts(Namespace::UserData, auction_dht_key, &note));
+    assert!(!accepts(Namespace::UserData, user_key, b"anything"));
+    let too_large = UserRecord::new("notes", &"a".repeat(Namespace::UserData.max_size()))
+        .serialized_to_bytes()
+        .unwrap();
+    assert!(!accepts(Namespace::UserData, user_key, &too_large));
+
+    // Storing nodes work the namespace out from the key and value
+    assert_eq!(
+        Namespace::of(&auction_dht_key, &auction_bytes),
+        Some(Namespace::Auction)
+    );
+    assert_eq!(Namespace::of(&user_key, &note), Some(Namespace::UserData));
+    assert_eq!(Namespace::of(&auction_dht_key, &note), None);
+    assert_eq!(Namespace::of(&other_key, &auction_bytes), None);
+
+    // User data is short-lived, and nothing else moves into an auction's key
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    let (now, ttl) = (Instant::now(), Duration::from_secs(48 * 3600));
+    let timings = *routing_table.get_storage_timings();
+    assert!(Namespace::UserData.ttl(&timings) < Namespace::Auction.ttl(&timings));
+    assert!(routing_table.store_record(user_key, Namespace::UserData, note, 100, ttl, now));
+    let expires_at = routing_table.get_stored(user_key, now).unwrap().expires_at;
+    assert_eq!(expires_at, now + Namespace::UserData.ttl(&timings));
+
+    let store = |routing_table: &mut RoutingTable, namespace: Namespace, published_at: u64| {
+        routing_table.store_record(
+            auction_dht_key,
+            namespace,
+            auction_bytes.clone(),
+            published_at,
+            ttl,
+            now,
+        )
+    };
+    assert!(store(&mut routing_table, Namespace::Auction, 100));
+    assert!(!store(&mut routing_table, Namespace::UserData, 200));
+    assert!(store(&mut routing_table, Namespace::Auction, 200));
+}
+
+// Test that auctions and bids only go under the key of an ID derived from their own fields, and
+// that a stored one isn't replaced by another carrying the same ID
+#[test]
+fn test_auction_and_bid_records_fixed() {
+    let auction = Auction::new(
+        vec![1u8; 32],
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let auction_dht_key = Namespace::Auction.key(&auction.id);
+    let mut forged_auction = auction.clone();
+    forged_auction.nonce += 1;
+    assert!(
+        Namespace::Auction
+            .validate(&auction_dht_key, forged_auction.serialized().as_bytes())
+            .is_err()
+    );
+
+    let bid = Bid::new(
+        auction.id.clone(),
+        vec![2u8; 32],
+        Money::new(300, Currency::Eur),
+    );
+    let mut forged_bid = bid.clone();
+    forged_bid.nonce += 1;
+    assert!(
+        Namespace::Bid
+            .validate(
+                &bid_key(&auction.id, &bid.id),
+                forged_bid.serialized().as_bytes()
+            )
+            .is_err()
+    );
+
+    // The description isn't part of the ID, a later copy with another one is still refused
+    let mut amended = auction.clone();
+    amended.description = "Broken laptop".to_string();
+    let amended_bytes = amended.serialized().into_bytes();
+    assert!(
+        Namespace::Auction
+            .validate(&auction_dht_key, &amended_bytes)
+            .is_ok()
+    );
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    let (now, ttl) = (Instant::now(), Duration::from_secs(3600));
+    let auction_bytes = auction.serialized().into_bytes();
+    assert!(routing_table.store_record(
+        auction_dht_key,
+        Namespace::Auction,
+        auction_bytes.clone(),
+        100,
+        ttl,
+        now
+    ));
+    assert!(!routing_table.store_record(
+        auction_dht_key,
+        Namespace::Auction,
+        amended_bytes,
+        200,
+        ttl,
+        now
+    ));
+    assert_eq!(routing_table.get(auction_dht_key), Some(&auction_bytes));
+}
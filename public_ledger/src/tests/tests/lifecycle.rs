@@ -0,0 +1,403 @@
+use super::helpers::{
+    auction_signature, bid_signature, build_chain, empty_blocks, genesis_allocation,
+};
+use crate::auction::Auction;
+use crate::auction::account::Accounts;
+use crate::auction::bid::Bid;
+use crate::auction::lifecycle::{
+    LifecycleEvent, LifecycleState, MAX_DEADLINE_EXTENSIONS, MAX_TOTAL_EXTENSION,
+};
+use crate::auction::money::{Currency, Money};
+use crate::auction::record::BidRecord;
+use crate::auction::seller::{SellerAction, SellerTransaction};
+use crate::auction::signature::BidSignature;
+use crate::identity::Identity;
+
+fn bid_record(bid: &Bid) -> Vec<u8> {
+    BidRecord::new(bid.clone()).serialized_to_bytes().unwrap()
+}
+
+fn seller_transaction(identity: &Identity, auction: &Auction, action: SellerAction) -> Vec<u8> {
+    SellerTransaction::new(identity, auction.id.clone(), action)
+        .serialized_to_bytes()
+        .unwrap()
+}
+
+// Test the states an auction goes through, replayed from the chain
+#[test]
+fn test_lifecycle_states() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let seller = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_200,
+        2_000,
+    );
+    let early = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+    let bid = Bid::new(
+        auction.id.clone(),
+        bob.get_public_key(),
+        Money::new(400, Currency::Eur),
+    )
+    .signed(&bob);
+
+    // Transitions follow the median time past, it reaches the starting time at height 5
+    // and the ending time at height 12
+    let chain = build_chain(
+        [
+            vec![
+                (genesis_allocation(&[&alice, &bob]), 900),
+                (auction_signature(&auction), 1_000),
+                (bid_signature(&early), 1_100),
+            ],
+            empty_blocks(3, 1_300),
+            vec![(bid_signature(&bid), 1_400)],
+            empty_blocks(6, 2_100),
+        ]
+        .concat(),
+    );
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert_eq!(lifecycle.state, LifecycleState::Closed);
+    let events: Vec<(LifecycleEvent, u64)> = lifecycle
+        .transitions
+        .iter()
+        .map(|transition| (transition.event.clone(), transition.confirmation.height))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            (LifecycleEvent::Entered(LifecycleState::Scheduled), 1),
+            (LifecycleEvent::Entered(LifecycleState::Open), 5),
+            (LifecycleEvent::Entered(LifecycleState::Closed), 12),
+        ]
+    );
+
+    // Bids confirmed while scheduled are not accepted
+    let verified = BidSignature::verify_bids(&chain, vec![early, bid.clone()], auction.clone());
+    assert_eq!(verified.len(), 1);
+    assert_eq!(verified[0].id, bid.id);
+}
+
+// Test that the seller can cancel before the first bid, and only then
+#[test]
+fn test_cancellation() {
+    let alice = Identity::generate();
+    let seller = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let bid = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+
+    // Before any bid the cancellation applies, and later bids are rejected
+    let chain = build_chain(vec![
+        (genesis_allocation(&[&alice]), 900),
+        (auction_signature(&auction), 1_000),
+        (
+            seller_transaction(&seller, &auction, SellerAction::Cancel),
+            1_100,
+        ),
+        (bid_signature(&bid), 1_200),
+        (vec![], 2_100),
+    ]);
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert_eq!(lifecycle.state, LifecycleState::Cancelled);
+    assert_eq!(lifecycle.cancel_height, Some(2));
+    assert!(!auction.lifecycle(&chain).unwrap().is_active());
+    assert_eq!(auction.closing_height(&chain), None);
+    assert!(BidSignature::verify_bids(&chain, vec![bid.clone()], auction.clone()).is_empty());
+
+    // After a bid it is ignored, and so is an amendment of the description
+    let amend = SellerAction::AmendDescription {
+        description: "Broken".to_string(),
+    };
+    let chain = build_chain(vec![
+        (vec![], 900),
+        (auction_signature(&auction), 1_000),
+        (bid_signature(&bid), 1_100),
+        (
+            seller_transaction(&seller, &auction, SellerAction::Cancel),
+            1_200,
+        ),
+        (seller_transaction(&seller, &auction, amend), 1_300),
+    ]);
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert_eq!(lifecycle.state, LifecycleState::Open);
+    assert!(lifecycle.has_bids);
+    assert_eq!(lifecycle.description, auction.description);
+
+    // Malformed commitments, sealed ones the bidder didn't sign and records of bids not signed
+    // by their bidder don't stop the seller
+    let unsigned = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    );
+    let malformed = BidSignature::new("junk".to_string(), auction.id.clone(), vec![0; 4]);
+    let mut forged = BidSignature::sealed(&alice, &bid);
+    forged.bid_hash = unsigned.get_hash();
+    let chain = build_chain(vec![
+        (vec![], 900),
+        (auction_signature(&auction), 1_000),
+        (malformed.serialized_to_bytes().unwrap(), 1_100),
+        (forged.serialized_to_bytes().unwrap(), 1_150),
+        (bid_record(&unsigned), 1_200),
+        (
+            seller_transaction(&seller, &auction, SellerAction::Cancel),
+            1_300,
+        ),
+    ]);
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert!(!lifecycle.has_bids);
+    assert_eq!(lifecycle.state, LifecycleState::Cancelled);
+
+    // So is a cancellation signed by someone else
+    let impostor = Identity::generate();
+    let chain = build_chain(vec![
+        (vec![], 900),
+        (auction_signature(&auction), 1_000),
+        (
+            seller_transaction(&impostor, &auction, SellerAction::Cancel),
+            1_100,
+        ),
+    ]);
+    assert_eq!(
+        auction.lifecycle(&chain).unwrap().state,
+        LifecycleState::Open
+    );
+}
+
+// Test description amendments and deadline extensions within the rules
+#[test]
+fn test_amendment_and_extension() {
+    let alice = Identity::generate();
+    let seller = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    )
+    .with_description("Used".to_string());
+    let bid = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+    let amend = SellerAction::AmendDescription {
+        description: "Like new".to_string(),
+    };
+    let extend = SellerAction::ExtendDeadline {
+        new_ending_time: 3_000,
+    };
+    // Past the total extension allowed
+    let too_far = SellerAction::ExtendDeadline {
+        new_ending_time: 2_000 + MAX_TOTAL_EXTENSION + 1,
+    };
+
+    let chain = build_chain(
+        [
+            vec![
+                (genesis_allocation(&[&alice]), 900),
+                (auction_signature(&auction), 1_000),
+                (seller_transaction(&seller, &auction, amend), 1_100),
+                (seller_transaction(&seller, &auction, extend), 1_200),
+                (seller_transaction(&seller, &auction, too_far), 1_300),
+                (vec![], 2_100),
+                (bid_signature(&bid), 2_200),
+            ],
+            empty_blocks(6, 3_100),
+        ]
+        .concat(),
+    );
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert_eq!(lifecycle.description, "Like new");
+    assert_eq!(lifecycle.ending_time, 3_000);
+    assert_eq!(lifecycle.extensions, 1);
+    assert_eq!(lifecycle.closing_height, Some(12));
+    assert!(
+        lifecycle
+            .transitions
+            .iter()
+            .any(|transition| transition.event
+                == LifecycleEvent::DeadlineExtended {
+                    new_ending_time: 3_000
+                }
+                && transition.confirmation.height == 3)
+    );
+
+    // The bid after the original deadline is still accepted thanks to the extension
+    let verified = BidSignature::verify_bids(&chain, vec![bid.clone()], auction.clone());
+    assert_eq!(verified.len(), 1);
+}
+
+// Test that seller actions breaking the rules are ignored: a fourth extension, an earlier ending
+// time, an action another key signed and an amendment once a bid is in
+#[test]
+fn test_seller_actions_outside_rules() {
+    let alice = Identity::generate();
+    let seller = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    )
+    .with_description("Used".to_string());
+    let bid = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+    let extend = |new_ending_time: u64| SellerAction::ExtendDeadline { new_ending_time };
+    let amend = |description: &str| SellerAction::AmendDescription {
+        description: description.to_string(),
+    };
+
+    let chain = build_chain(vec![
+        (genesis_allocation(&[&alice]), 900),
+        (auction_signature(&auction), 1_000),
+        (seller_transaction(&seller, &auction, extend(2_500)), 1_100),
+        // Earlier than the current ending time
+        (seller_transaction(&seller, &auction, extend(2_400)), 1_150),
+        (seller_transaction(&seller, &auction, extend(3_000)), 1_200),
+        (seller_transaction(&seller, &auction, extend(3_500)), 1_300),
+        // One extension too many
+        (seller_transaction(&seller, &auction, extend(4_000)), 1_400),
+        // Signed by a bidder, not the seller
+        (seller_transaction(&alice, &auction, amend("Broken")), 1_500),
+        (
+            seller_transaction(&alice, &auction, SellerAction::Cancel),
+            1_550,
+        ),
+        (bid_signature(&bid), 1_600),
+        // Bidders committed to the item as described
+        (
+            seller_transaction(&seller, &auction, amend("Like new")),
+            1_700,
+        ),
+    ]);
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert_eq!(lifecycle.state, LifecycleState::Open);
+    assert_eq!(lifecycle.extensions, MAX_DEADLINE_EXTENSIONS);
+    assert_eq!(lifecycle.ending_time, 3_500);
+    assert_eq!(lifecycle.description, "Used");
+    assert!(
+        !lifecycle
+            .transitions
+            .iter()
+            .any(|transition| transition.event == LifecycleEvent::DescriptionAmended)
+    );
+}
+
+// Test that a block stamped in the future doesn't close the auction early, the deadline follows
+// the median time past
+#[test]
+fn test_future_block_does_not_close() {
+    let alice = Identity::generate();
+    let seller = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let bid = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+
+    // A miner stamps a block an hour past the deadline, within MAX_FUTURE_BLOCK_TIME
+    let chain = build_chain(vec![
+        (genesis_allocation(&[&alice]), 900),
+        (auction_signature(&auction), 1_000),
+        (vec![], 1_100),
+        (vec![], 2_000 + 60 * 60),
+        (bid_signature(&bid), 1_200),
+    ]);
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert_eq!(lifecycle.state, LifecycleState::Open);
+    assert_eq!(lifecycle.closing_height, None);
+    let verified = BidSignature::verify_bids(&chain, vec![bid.clone()], auction.clone());
+    assert_eq!(verified.len(), 1);
+}
+
+// Test that nodes knowing different bid records replay the same lifecycle from the same chain,
+// a bid only one of them fetched still stops the seller's cancellation for both
+#[test]
+fn test_lifecycle_same_on_every_node() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let seller = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let first = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+    let second = Bid::new(
+        auction.id.clone(),
+        bob.get_public_key(),
+        Money::new(400, Currency::Eur),
+    )
+    .signed(&bob);
+
+    let chain = build_chain(vec![
+        (genesis_allocation(&[&alice, &bob]), 900),
+        (auction_signature(&auction), 1_000),
+        (bid_signature(&first), 1_100),
+        (
+            seller_transaction(&seller, &auction, SellerAction::Cancel),
+            1_200,
+        ),
+        (bid_signature(&second), 1_300),
+    ]);
+    let lifecycle = auction.lifecycle(&chain).unwrap();
+    assert!(lifecycle.has_bids);
+    assert_eq!(lifecycle.state, LifecycleState::Open);
+    assert_eq!(lifecycle.cancel_height, None);
+
+    // One node fetched both records, the other only the second, both accept the second bid
+    let auctions = std::slice::from_ref(&auction);
+    let full = Accounts::replay(&chain, auctions, &[first.clone(), second.clone()]);
+    let partial = Accounts::replay(&chain, auctions, std::slice::from_ref(&second));
+    let accepted_ids = |accounts: &Accounts| -> Vec<String> {
+        accounts
+            .get_accepted_bids(&auction.id)
+            .into_iter()
+            .map(|bid| bid.id)
+            .collect()
+    };
+    assert_eq!(accepted_ids(&full), vec![first.id, second.id.clone()]);
+    assert_eq!(accepted_ids(&partial), vec![second.id]);
+}
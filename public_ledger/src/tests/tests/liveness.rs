@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use crate::kademlia::key::Key;
+use crate::kademlia::routing_table::RoutingTable;
+use crate::kademlia::routing_table::node::Node;
+use crate::kademlia::routing_table::params::MAX_CONSECUTIVE_FAILURES;
+
+// Test that nodes keep when they last answered, how fast and how many RPCs in a row they failed,
+// even when learned again from other nodes, and that too many failures evict them
+#[test]
+fn test_node_liveness() {
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    let id = Key::from([7; 20]);
+    routing_table.add_node(Node::with_id(id, "127.0.0.1".to_string(), 2));
+    let node = |routing_table: &RoutingTable| {
+        routing_table
+            .get_all_nodes()
+            .into_iter()
+            .find(|node| *node.get_id() == id)
+    };
+    assert_eq!(node(&routing_table).unwrap().get_last_seen(), None);
+
+    let now = Instant::now();
+    let rtt = Duration::from_millis(30);
+    routing_table.record_response(&id, Some(rtt), now);
+    // Another node telling us about it, maybe at a new address, doesn't reset it
+    routing_table.add_node(Node::with_id(id, "127.0.0.1".to_string(), 3));
+    let seen = node(&routing_table).unwrap();
+    assert_eq!(
+        (seen.get_last_seen(), seen.get_rtt()),
+        (Some(now), Some(rtt))
+    );
+    assert_eq!(seen.get_port(), 3);
+
+    // An answer starts the count of failures again
+    for _ in 1..MAX_CONSECUTIVE_FAILURES {
+        assert!(!routing_table.record_failure(&id));
+    }
+    assert_eq!(
+        node(&routing_table).unwrap().get_failures(),
+        MAX_CONSECUTIVE_FAILURES - 1
+    );
+    routing_table.record_response(&id, None, now);
+    assert_eq!(node(&routing_table).unwrap().get_failures(), 0);
+    assert_eq!(node(&routing_table).unwrap().get_rtt(), Some(rtt));
+
+    for _ in 1..MAX_CONSECUTIVE_FAILURES {
+        assert!(!routing_table.record_failure(&id));
+    }
+    assert!(routing_table.record_failure(&id));
+    assert!(node(&routing_table).is_none());
+    assert!(routing_table.get_closest_k_nodes(&id, 4).is_empty());
+    assert!(!routing_table.record_failure(&id));
+}
+
+// Test that answers and failures of nodes we don't know are ignored rather than adding them, and
+// that a node evicted for its failures starts over with a clean record when it is learned again
+#[test]
+fn test_unknown_and_evicted_nodes() {
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    let id = Key::from([7; 20]);
+    let now = Instant::now();
+
+    routing_table.record_response(&id, Some(Duration::from_millis(30)), now);
+    assert!(!routing_table.record_failure(&id));
+    assert!(routing_table.get_node(&id).is_none());
+    // Nor is the node itself, it has no bucket
+    let own_id = *routing_table.get_curr_node().get_id();
+    routing_table.record_response(&own_id, None, now);
+    assert!(!routing_table.record_failure(&own_id));
+
+    routing_table.add_node(Node::with_id(id, "127.0.0.1".to_string(), 2));
+    routing_table.record_response(&id, Some(Duration::from_millis(30)), now);
+    while !routing_table.record_failure(&id) {}
+    assert_eq!(routing_table.get_size(), 0);
+    assert!(routing_table.get_k_bucket_map().is_empty());
+
+    routing_table.add_node(Node::with_id(id, "127.0.0.1".to_string(), 2));
+    let relearned = routing_table.get_node(&id).unwrap();
+    assert_eq!(relearned.get_failures(), 0);
+    assert_eq!(relearned.get_last_seen(), None);
+    assert!(!routing_table.record_failure(&id));
+}
@@ -0,0 +1,81 @@
+use crate::kademlia::key::Key;
+use crate::kademlia::lookup::Shortlist;
+use crate::kademlia::routing_table::node::Node;
+
+// Node whose distance to the zero key grows with `n`
+fn node(n: u8) -> Node {
+    Node::with_id([n; 20], "127.0.0.1".to_string(), u16::from(n))
+}
+
+fn ids(nodes: Vec<Node>) -> Vec<u8> {
+    nodes.iter().map(|node| node.get_id().as_ref()[0]).collect()
+}
+
+fn key(n: u8) -> Key {
+    Key::from([n; 20])
+}
+
+// Test that a lookup keeps ALPHA requests in flight to the closest candidates, keeps unqueried
+// candidates when closer ones arrive, skips failed nodes and ends once the k closest responded
+#[test]
+fn test_shortlist_convergence() {
+    let mut shortlist = Shortlist::new(
+        key(0),
+        key(99),
+        vec![node(50), node(10), node(40), node(30), node(20)],
+    );
+    assert!(!shortlist.is_finished());
+    assert_eq!(ids(shortlist.next_to_query()), vec![10, 20, 30]);
+    assert!(shortlist.next_to_query().is_empty());
+
+    // Closer nodes, and this node itself, which is never a candidate
+    shortlist.responded(&key(10), vec![node(5), node(1), node(99), node(30)]);
+    assert_eq!(ids(shortlist.next_to_query()), vec![1]);
+    // The failed node makes room for the next closest
+    shortlist.failed(&key(20));
+    assert_eq!(ids(shortlist.next_to_query()), vec![5]);
+
+    shortlist.responded(&key(1), vec![]);
+    shortlist.responded(&key(5), vec![]);
+    assert!(!shortlist.is_finished());
+    shortlist.responded(&key(30), vec![]);
+    // 40 and 50 are never asked
+    assert!(shortlist.is_finished());
+    assert_eq!(ids(shortlist.closest()), vec![1, 5, 10, 30]);
+    assert_eq!(ids(shortlist.get_failed()), vec![20]);
+}
+
+// Test that a lookup without candidates, or whose candidates all failed, is over
+#[test]
+fn test_shortlist_without_candidates() {
+    assert!(Shortlist::new(key(0), key(99), vec![]).is_finished());
+
+    let mut shortlist = Shortlist::new(key(0), key(99), vec![node(1), node(2)]);
+    assert_eq!(ids(shortlist.next_to_query()), vec![1, 2]);
+    shortlist.failed(&key(1));
+    shortlist.failed(&key(2));
+    assert!(shortlist.is_finished());
+    assert!(shortlist.closest().is_empty());
+}
+
+// Test that nodes sent by another node with a malformed ID or port are dropped, not a panic
+#[test]
+fn test_malformed_nodes_dropped() {
+    use crate::kademlia::communication;
+
+    let proto = node(7).to_proto();
+    assert_eq!(
+        Node::from_proto(&proto).map(|node| *node.get_id()),
+        Some(key(7))
+    );
+    let short_id = communication::Node {
+        id: vec![7; 3],
+        ..proto.clone()
+    };
+    assert!(Node::from_proto(&short_id).is_none());
+    let bad_port = communication::Node {
+        port: u32::from(u16::MAX) + 1,
+        ..proto
+    };
+    assert!(Node::from_proto(&bad_port).is_none());
+}
@@ -0,0 +1,261 @@
+use super::helpers::{build_chain, empty_blocks, eur, faucet, genesis_allocation};
+use crate::auction::Auction;
+use crate::auction::account::{Accounts, MAX_FAUCET_GRANT};
+use crate::auction::bid::Bid;
+use crate::auction::lot::Lot;
+use crate::auction::money::Currency;
+use crate::auction::record::LedgerMode;
+use crate::auction::settlement::{SettlementTransaction, UNIFORM_PRICE_RULES};
+use crate::identity::Identity;
+
+fn lot_bid(auction: &Auction, bidder: &Identity, lot_index: u32, quantity: u32, unit: u64) -> Bid {
+    Bid::new(auction.id.clone(), bidder.get_public_key(), eur(unit))
+        .with_lot(lot_index, quantity)
+        .signed(bidder)
+}
+
+// Test that units go to the highest unit prices, ties to the earlier bid, the last winner
+// partially, and that every winner pays the lowest winning unit price
+#[test]
+fn test_uniform_price_clearing() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let carol = Identity::generate();
+    let erin = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Tickets".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    )
+    .with_lots(vec![Lot {
+        name: "Tickets".to_string(),
+        quantity: 10,
+    }]);
+    let alice_bid = lot_bid(&auction, &alice, 0, 4, 300);
+    let bob_bid = lot_bid(&auction, &bob, 0, 4, 250);
+    // Outranked for every unit once erin bids
+    let carol_bid = lot_bid(&auction, &carol, 0, 3, 200);
+    // Same price as bob but later, only 2 units are left for it
+    let erin_bid = lot_bid(&auction, &erin, 0, 3, 250);
+    // Outranked for every unit
+    let late_bid = lot_bid(&auction, &carol, 0, 1, 150);
+    // More units than the lot has, and a lot the auction doesn't have
+    let too_many = lot_bid(&auction, &carol, 0, 11, 900);
+    let wrong_lot = lot_bid(&auction, &carol, 1, 1, 900);
+
+    let mut blocks = vec![
+        (genesis_allocation(&[&alice, &bob, &carol, &erin]), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (full.bid_transaction(&alice_bid), 1_100),
+        (full.bid_transaction(&bob_bid), 1_200),
+        (full.bid_transaction(&carol_bid), 1_300),
+        (full.bid_transaction(&erin_bid), 1_400),
+        (full.bid_transaction(&late_bid), 1_500),
+        (full.bid_transaction(&too_many), 1_600),
+        (full.bid_transaction(&wrong_lot), 1_700),
+    ];
+    let chain = build_chain(blocks.clone());
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    let accepted: Vec<String> = accounts
+        .get_accepted_bids(&auction.id)
+        .into_iter()
+        .map(|bid| bid.id)
+        .collect();
+    assert_eq!(
+        accepted,
+        vec![
+            alice_bid.id.clone(),
+            bob_bid.id.clone(),
+            carol_bid.id.clone(),
+            erin_bid.id.clone()
+        ]
+    );
+    // Carol's bid was released when erin's outranked it
+    let carol_id = carol.get_public_key();
+    assert_eq!(accounts.escrowed(&carol_id, Currency::Eur), eur(0));
+    assert_eq!(
+        accounts.escrowed(&erin.get_public_key(), Currency::Eur),
+        eur(750)
+    );
+
+    let settlement = SettlementTransaction::new(&chain, &auction, vec![]);
+    assert_eq!(settlement.rule_set, UNIFORM_PRICE_RULES);
+    assert_eq!(settlement.winning_bid_id, None);
+    let result = &settlement.lot_results[0];
+    assert_eq!(result.clearing_price, Some(eur(250)));
+    let fills: Vec<(String, u32)> = result
+        .fills
+        .iter()
+        .map(|fill| (fill.bid_id.clone(), fill.quantity))
+        .collect();
+    assert_eq!(
+        fills,
+        vec![
+            (alice_bid.id.clone(), 4),
+            (bob_bid.id.clone(), 4),
+            (erin_bid.id.clone(), 2)
+        ]
+    );
+
+    // A settlement claiming another clearing price isn't applied
+    let mut forged = settlement.clone();
+    forged.lot_results[0].clearing_price = Some(eur(300));
+    blocks.extend(empty_blocks(6, 2_100));
+    blocks.push((forged.serialized_to_bytes().unwrap(), 2_200));
+    blocks.push((settlement.serialized_to_bytes().unwrap(), 2_300));
+    let chain = build_chain(blocks);
+    let (valid, _) = SettlementTransaction::get_valid_settlement(&chain, &auction).unwrap();
+    assert_eq!(valid, settlement);
+
+    // Winners pay 250 per unit and get the rest of their escrow back
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    assert!(accounts.get_escrows(&auction.id).is_empty());
+    let balance =
+        |identity: &Identity| accounts.available(&identity.get_public_key(), Currency::Eur);
+    assert_eq!(balance(&alice), eur(MAX_FAUCET_GRANT - 1_000));
+    assert_eq!(balance(&bob), eur(MAX_FAUCET_GRANT - 1_000));
+    assert_eq!(balance(&erin), eur(MAX_FAUCET_GRANT - 500));
+    assert_eq!(balance(&carol), eur(MAX_FAUCET_GRANT));
+    assert_eq!(balance(&seller), eur(2_500));
+    let winner_ids = accounts.get_settled(&auction.id).unwrap().winner_ids();
+    assert_eq!(
+        winner_ids,
+        vec![
+            alice.get_public_key(),
+            bob.get_public_key(),
+            erin.get_public_key()
+        ]
+    );
+}
+
+// Test that each lot of a lot auction clears on its own
+#[test]
+fn test_lot_auction_results() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let carol = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Furniture".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    )
+    .with_lots(vec![
+        Lot {
+            name: "Chair".to_string(),
+            quantity: 2,
+        },
+        Lot {
+            name: "Table".to_string(),
+            quantity: 1,
+        },
+    ]);
+    let alice_chairs = lot_bid(&auction, &alice, 0, 2, 100);
+    let bob_chair = lot_bid(&auction, &bob, 0, 1, 120);
+    let carol_table = lot_bid(&auction, &carol, 1, 1, 500);
+    // Doesn't beat carol on the single table
+    let alice_table = lot_bid(&auction, &alice, 1, 1, 500);
+
+    let chain = build_chain(vec![
+        (genesis_allocation(&[&alice, &bob, &carol]), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (full.bid_transaction(&alice_chairs), 1_100),
+        (full.bid_transaction(&bob_chair), 1_200),
+        (full.bid_transaction(&carol_table), 1_300),
+        (full.bid_transaction(&alice_table), 1_400),
+    ]);
+    let settlement = SettlementTransaction::new(&chain, &auction, vec![]);
+    let winners = |lot_index: usize| -> Vec<(Vec<u8>, u32)> {
+        settlement.lot_results[lot_index]
+            .fills
+            .iter()
+            .map(|fill| (fill.winner_id.clone(), fill.quantity))
+            .collect()
+    };
+    // Bob outbid alice on one chair, the other goes to her at her own price
+    let chairs = &settlement.lot_results[0];
+    assert_eq!(chairs.clearing_price, Some(eur(100)));
+    assert_eq!(
+        winners(0),
+        vec![(bob.get_public_key(), 1), (alice.get_public_key(), 1)]
+    );
+    assert_eq!(chairs.price_of(&chairs.fills[0]), Some(eur(100)));
+    assert_eq!(settlement.lot_results[1].clearing_price, Some(eur(500)));
+    assert_eq!(winners(1), vec![(carol.get_public_key(), 1)]);
+}
+
+// Test that lot bids for no units, more units than their lot has, more than the bidder can pay
+// for in total or a total that overflows are rejected, and that a single item is one unit
+#[test]
+fn test_lot_bids_rejected() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Furniture".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    )
+    .with_lots(vec![
+        Lot {
+            name: "Chair".to_string(),
+            quantity: 2,
+        },
+        Lot {
+            name: "Table".to_string(),
+            quantity: 1,
+        },
+    ]);
+    let single = Auction::new(
+        seller.get_public_key(),
+        "Lamp".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    );
+    let no_units = lot_bid(&auction, &alice, 0, 0, 100);
+    let two_tables = lot_bid(&auction, &alice, 1, 2, 100);
+    // 1_200 in total, alice has 1_000
+    let over_budget = lot_bid(&auction, &alice, 0, 2, 600);
+    let overflowing = lot_bid(&auction, &alice, 0, 2, u64::MAX);
+    let all_in = lot_bid(&auction, &alice, 0, 2, 500);
+    let two_lamps = lot_bid(&single, &bob, 0, 2, 100);
+    let one_lamp = lot_bid(&single, &bob, 0, 1, 100);
+
+    let chain = build_chain(vec![
+        (faucet(&[&alice, &bob], eur(1_000)), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (full.auction_transaction(&single), 1_050),
+        (full.bid_transaction(&no_units), 1_100),
+        (full.bid_transaction(&two_tables), 1_200),
+        (full.bid_transaction(&over_budget), 1_300),
+        (full.bid_transaction(&overflowing), 1_400),
+        (full.bid_transaction(&all_in), 1_500),
+        (full.bid_transaction(&two_lamps), 1_600),
+        (full.bid_transaction(&one_lamp), 1_700),
+    ]);
+    let accounts = Accounts::replay(&chain, &[], &[]);
+    let accepted = |auction: &Auction| -> Vec<String> {
+        accounts
+            .get_accepted_bids(&auction.id)
+            .into_iter()
+            .map(|bid| bid.id)
+            .collect()
+    };
+    assert_eq!(accepted(&auction), vec![all_in.id]);
+    assert_eq!(
+        accounts.escrowed(&alice.get_public_key(), Currency::Eur),
+        eur(1_000)
+    );
+    assert_eq!(accepted(&single), vec![one_lamp.id]);
+}
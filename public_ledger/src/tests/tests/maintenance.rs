@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::RwLock;
+
+use crate::kademlia::maintain_routing_table;
+use crate::kademlia::routing_table::RoutingTable;
+use crate::kademlia::routing_table::node::Node;
+use crate::kademlia::routing_table::params::StorageTimings;
+use crate::kademlia::shutdown::Shutdown;
+
+fn routing_table() -> RoutingTable {
+    RoutingTable::with_storage_timings(
+        "127.0.0.1".to_string(),
+        1,
+        StorageTimings {
+            maintenance_interval: Duration::from_millis(10),
+            refresh_interval: Duration::from_secs(5),
+            ..StorageTimings::default()
+        },
+    )
+}
+
+// Test that buckets without a lookup for the refresh interval are due for a refresh, the missing
+// ones up to the closest bucket holding nodes too, and that random keys land in their bucket
+#[test]
+fn test_bucket_refresh() {
+    let mut routing_table = routing_table();
+    let curr_id = *routing_table.get_curr_node().get_id();
+    for index in [0, 1, 57, 159] {
+        assert_eq!(
+            curr_id.bucket_index(&curr_id.random_in_bucket(index)),
+            Some(index)
+        );
+    }
+
+    let now = Instant::now();
+    assert!(routing_table.get_buckets_to_refresh(now).is_empty());
+    for index in [0, 3] {
+        let id = curr_id.random_in_bucket(index);
+        routing_table.add_node(Node::with_id(id, "127.0.0.1".to_string(), 2));
+    }
+    assert_eq!(routing_table.get_buckets_to_refresh(now), vec![0, 1, 2, 3]);
+
+    // A lookup through a bucket refreshes it for the interval
+    routing_table.touch_bucket(&curr_id.random_in_bucket(1), now);
+    routing_table.touch_bucket(&curr_id.random_in_bucket(3), now);
+    assert_eq!(routing_table.get_buckets_to_refresh(now), vec![0, 2]);
+    let later = now + Duration::from_secs(5);
+    assert_eq!(
+        routing_table.get_buckets_to_refresh(later),
+        vec![0, 1, 2, 3]
+    );
+}
+
+// Test that the background maintenance stops once the shutdown is triggered
+#[tokio::test]
+async fn test_maintenance_shutdown() {
+    let shutdown = Shutdown::new();
+    let maintenance = tokio::spawn(maintain_routing_table(
+        Arc::new(RwLock::new(routing_table())),
+        shutdown.clone(),
+    ));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!maintenance.is_finished());
+
+    shutdown.trigger();
+    tokio::time::timeout(Duration::from_secs(1), maintenance)
+        .await
+        .unwrap()
+        .unwrap();
+    // Whatever waits on it later doesn't wait at all
+    tokio::time::timeout(Duration::from_secs(1), shutdown.wait())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,79 @@
+// Test parsing of decimal amounts into minor units
+#[test]
+fn test_money_parse() {
+    use crate::auction::money::{Currency, Money};
+
+    assert_eq!(
+        Money::parse("12", Currency::Eur).unwrap(),
+        Money::new(1200, Currency::Eur)
+    );
+    assert_eq!(
+        Money::parse("12.5", Currency::Eur).unwrap(),
+        Money::new(1250, Currency::Eur)
+    );
+    assert_eq!(
+        Money::parse("0.07", Currency::Usd).unwrap(),
+        Money::new(7, Currency::Usd)
+    );
+    assert!(Money::parse("12.345", Currency::Eur).is_err());
+    assert!(Money::parse("-1", Currency::Eur).is_err());
+    assert!(Money::parse("NaN", Currency::Eur).is_err());
+    assert!(Money::parse("", Currency::Eur).is_err());
+    assert_eq!(
+        Money::parse("12.5", Currency::Gbp).unwrap().to_string(),
+        "12.50 GBP"
+    );
+}
+
+// Test checked arithmetic and exact comparison
+#[test]
+fn test_money_checked_arithmetic() {
+    use crate::auction::money::{Currency, Money, MoneyError};
+    use std::cmp::Ordering;
+
+    let a = Money::new(150, Currency::Eur);
+    let b = Money::new(100, Currency::Eur);
+    let dollars = Money::new(100, Currency::Usd);
+
+    assert_eq!(a.checked_add(&b).unwrap(), Money::new(250, Currency::Eur));
+    assert_eq!(a.checked_sub(&b).unwrap(), Money::new(50, Currency::Eur));
+    assert_eq!(b.checked_sub(&a), Err(MoneyError::Overflow));
+    assert_eq!(
+        Money::new(u64::MAX, Currency::Eur).checked_add(&b),
+        Err(MoneyError::Overflow)
+    );
+    assert_eq!(
+        a.checked_add(&dollars),
+        Err(MoneyError::CurrencyMismatch(Currency::Eur, Currency::Usd))
+    );
+
+    assert_eq!(a.checked_cmp(&b).unwrap(), Ordering::Greater);
+    assert!(a > b);
+    assert!(a.checked_cmp(&dollars).is_err());
+    assert_eq!(b.partial_cmp(&dollars), None);
+}
+
+// Test that amounts serialized as floats by older versions are still readable
+#[test]
+fn test_money_legacy_float_migration() {
+    use crate::auction::bid::Bid;
+    use crate::auction::money::{Currency, Money};
+
+    // A bid as older versions wrote it, with numeric IDs and no nonce
+    let legacy = r#"{"id":1,"auction_id":0,"bidder_id":[],"amount":12.34,"timestamp":0}"#;
+    let bid = Bid::deserialized_from_bytes(legacy.as_bytes()).unwrap();
+    assert_eq!(bid.amount, Money::new(1234, Currency::Eur));
+
+    // Re-serialized bids use the new fixed-point format
+    let migrated = Bid::deserialized_from_bytes(bid.serialized().as_bytes()).unwrap();
+    assert_eq!(migrated.amount, bid.amount);
+    assert!(
+        bid.serialized()
+            .contains(r#""amount":{"minor_units":1234,"currency":"EUR"}"#)
+    );
+
+    assert!(Money::from_legacy_f64(f64::NAN).is_err());
+    assert!(Money::from_legacy_f64(-1.0).is_err());
+    // 2^64 minor units is one past u64::MAX and must not saturate into it
+    assert!(Money::from_legacy_f64(2f64.powi(64) / 100.0).is_err());
+}
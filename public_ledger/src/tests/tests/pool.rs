@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+use crate::kademlia::communication::PingRequest;
+use crate::kademlia::pool::ClientPool;
+use crate::kademlia::routing_table::params::{CONNECTION_IDLE_TIMEOUT, RPC_TIMEOUT};
+
+// Test that the requests to a peer share one pooled connection, closed once idle, and that a
+// peer nobody listens on fails the request instead of hanging
+#[tokio::test]
+async fn test_client_pool() {
+    let pool = ClientPool::new();
+    let mut client = pool.client("::1", 1).unwrap();
+    pool.client("::1", 1).unwrap();
+    pool.client("::1", 2).unwrap();
+    assert!(pool.client("not an address", 1).is_none());
+    assert_eq!(pool.evict_idle(Instant::now()), 0);
+
+    let started = Instant::now();
+    let request = PingRequest {
+        node: None,
+        nonce: vec![],
+    };
+    assert!(client.ping(request).await.is_err());
+    assert!(started.elapsed() <= RPC_TIMEOUT * 2);
+
+    assert_eq!(pool.evict_idle(Instant::now() + CONNECTION_IDLE_TIMEOUT), 2);
+    assert_eq!(pool.evict_idle(Instant::now() + CONNECTION_IDLE_TIMEOUT), 0);
+}
+
+// Test that invalid addresses get no client and leave nothing in the pool, and that a connection
+// used again isn't closed before it has been idle for the whole timeout
+#[tokio::test]
+async fn test_client_pool_invalid_and_reused() {
+    let pool = ClientPool::new();
+    assert!(pool.client("::1 ::2", 1).is_none());
+    assert_eq!(pool.evict_idle(Instant::now() + CONNECTION_IDLE_TIMEOUT), 0);
+
+    pool.client("::1", 1).unwrap();
+    let reused_at = Instant::now();
+    pool.client("::1", 1).unwrap();
+    assert_eq!(
+        pool.evict_idle(reused_at + CONNECTION_IDLE_TIMEOUT - Duration::from_millis(1)),
+        0
+    );
+    assert_eq!(pool.evict_idle(Instant::now() + CONNECTION_IDLE_TIMEOUT), 1);
+}
@@ -0,0 +1,143 @@
+use super::helpers::{build_chain, empty_blocks, eur, genesis_allocation};
+use crate::auction::account::Accounts;
+use crate::auction::bid::Bid;
+use crate::auction::private::{self, AuctionKey, PrivateAuction, PrivateBid};
+use crate::auction::record::LedgerMode;
+use crate::auction::settlement::SettlementTransaction;
+use crate::auction::{Auction, bid_key};
+use crate::identity::Identity;
+use crate::kademlia::namespace::Namespace;
+
+// Test that only the seller and the invitees can open a private auction and its bids
+#[test]
+fn test_private_auction_encryption() {
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let carol = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Painting".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    )
+    .with_invitees(vec![alice.get_public_key()]);
+    assert!(auction.is_private());
+
+    let (private_auction, auction_key) = PrivateAuction::seal(&auction).unwrap();
+    let bytes = private_auction.serialized_to_bytes().unwrap();
+    assert!(!String::from_utf8_lossy(&bytes).contains("Painting"));
+
+    let private_auction = PrivateAuction::deserialized_from_bytes(&bytes).unwrap();
+    for identity in [&seller, &alice] {
+        let key = private_auction.auction_key(identity).unwrap();
+        assert_eq!(key, auction_key);
+        assert_eq!(
+            private_auction.open(&key).unwrap().get_hash(),
+            auction.get_hash()
+        );
+    }
+    assert!(private_auction.auction_key(&carol).is_none());
+    assert!(private_auction.open(&AuctionKey::generate()).is_none());
+
+    // Tampered ciphertext doesn't open
+    let mut tampered = private_auction.clone();
+    tampered.sealed_auction.ciphertext[0] ^= 1;
+    assert!(tampered.open(&auction_key).is_none());
+
+    let bid = Bid::new(auction.id.clone(), alice.get_public_key(), eur(200)).signed(&alice);
+    let private_bid = PrivateBid::seal(&auction_key, &bid);
+    assert_eq!(
+        private_bid.open(&auction_key).unwrap().get_hash(),
+        bid.get_hash()
+    );
+    assert!(private_bid.open(&AuctionKey::generate()).is_none());
+
+    // A sealed bid only goes under its own bid key, and doesn't open once moved to another
+    let accepts = |key, private_bid: &PrivateBid| {
+        Namespace::Bid
+            .validate(&key, &private_bid.serialized_to_bytes().unwrap())
+            .is_ok()
+    };
+    assert!(accepts(bid_key(&auction.id, &bid.id), &private_bid));
+    assert!(!accepts(bid_key("other", &bid.id), &private_bid));
+    let mut moved = private_bid.clone();
+    moved.private_auction_id = "other".to_string();
+    assert!(accepts(bid_key("other", &bid.id), &moved));
+    assert!(moved.open(&auction_key).is_none());
+}
+
+// Test that bidders off the allowlist are rejected and that the outcome can be audited with the key
+#[test]
+fn test_private_auction_allowlist_and_audit() {
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let carol = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Painting".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    )
+    .with_invitees(vec![alice.get_public_key()]);
+    let (private_auction, auction_key) = PrivateAuction::seal(&auction).unwrap();
+    let alice_bid = Bid::new(auction.id.clone(), alice.get_public_key(), eur(300)).signed(&alice);
+    // Higher, but carol wasn't invited
+    let carol_bid = Bid::new(auction.id.clone(), carol.get_public_key(), eur(500)).signed(&carol);
+
+    // Private auctions are committed by hash, whatever the ledger mode
+    let mode = LedgerMode::FullRecords.for_auction(&auction);
+    assert_eq!(mode, LedgerMode::HashOnly);
+    let mut blocks = [
+        vec![
+            (genesis_allocation(&[&alice, &carol]), 900),
+            (mode.auction_transaction(&auction), 950),
+        ],
+        empty_blocks(2, 1_000),
+        vec![
+            (mode.bid_transaction(&alice_bid), 1_100),
+            (mode.bid_transaction(&carol_bid), 1_200),
+        ],
+    ]
+    .concat();
+    let bids = vec![alice_bid.clone(), carol_bid.clone()];
+    let chain = build_chain(blocks.clone());
+    let accounts = Accounts::replay(&chain, std::slice::from_ref(&auction), &bids);
+    let accepted: Vec<String> = accounts
+        .get_accepted_bids(&auction.id)
+        .into_iter()
+        .map(|bid| bid.id)
+        .collect();
+    assert_eq!(accepted, vec![alice_bid.id.clone()]);
+
+    let settlement = SettlementTransaction::new(&chain, &auction, bids.clone());
+    assert_eq!(settlement.winner_id, Some(alice.get_public_key()));
+    blocks.extend(empty_blocks(6, 2_100));
+    blocks.push((settlement.serialized_to_bytes().unwrap(), 2_200));
+    let chain = build_chain(blocks);
+
+    // An auditor given the key rebuilds the auction and bids from the encrypted records
+    let private_bids: Vec<PrivateBid> = bids
+        .iter()
+        .map(|bid| PrivateBid::seal(&auction_key, bid))
+        .collect();
+    let (audited_auction, audited_bids) =
+        private::audit(&chain, &private_auction, &private_bids, &auction_key).unwrap();
+    assert_eq!(audited_bids.len(), 2);
+    let (audited_settlement, _) =
+        SettlementTransaction::get_valid_settlement(&chain, &audited_auction).unwrap();
+    assert_eq!(audited_settlement.price_paid, Some(eur(300)));
+
+    // Records that aren't committed on the chain are left out
+    let other_auction = Auction::new(
+        seller.get_public_key(),
+        "Sculpture".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    )
+    .with_invitees(vec![alice.get_public_key()]);
+    let (other_private, other_key) = PrivateAuction::seal(&other_auction).unwrap();
+    assert!(private::audit(&chain, &other_private, &[], &other_key).is_none());
+}
@@ -0,0 +1,98 @@
+use super::helpers::eur;
+use crate::auction::Auction;
+use crate::auction::bid::Bid;
+use crate::auction::money::{Currency, Money};
+use crate::auction::proxy::{ProxyBid, ProxyDecision};
+
+// Test the proxy bids the minimum winning amount and stops at its maximum
+#[test]
+fn test_proxy_next_bid() {
+    let auction = Auction::new(
+        vec![1u8; 32],
+        "Laptop".to_string(),
+        eur(1_000),
+        1_000,
+        2_000,
+    );
+    let me = vec![2u8; 32];
+    let other = vec![3u8; 32];
+    let proxy = ProxyBid::new(auction.id.clone(), me.clone(), eur(2_000), eur(250));
+    let bid = |bidder_id: &Vec<u8>, amount: u64| {
+        Bid::new(auction.id.clone(), bidder_id.clone(), eur(amount))
+    };
+
+    // No bids yet, open at the starting price
+    assert_eq!(
+        proxy.next_bid(&auction, &[]),
+        ProxyDecision::Bid(eur(1_000))
+    );
+    // Outbid, raise by the increment
+    assert_eq!(
+        proxy.next_bid(&auction, &[bid(&me, 1_000), bid(&other, 1_200)]),
+        ProxyDecision::Bid(eur(1_450))
+    );
+    // Still ahead, wait
+    assert_eq!(
+        proxy.next_bid(&auction, &[bid(&other, 1_200), bid(&me, 1_450)]),
+        ProxyDecision::Leading
+    );
+    // The leader is the highest bid wherever it is in the list
+    assert_eq!(
+        proxy.next_bid(&auction, &[bid(&other, 1_450), bid(&me, 1_200)]),
+        ProxyDecision::Bid(eur(1_700))
+    );
+    // On a tie the earliest bid leads, as in the settlement
+    assert_eq!(
+        proxy.next_bid(&auction, &[bid(&me, 1_450), bid(&other, 1_450)]),
+        ProxyDecision::Leading
+    );
+    assert_eq!(
+        proxy.next_bid(&auction, &[bid(&other, 1_450), bid(&me, 1_450)]),
+        ProxyDecision::Bid(eur(1_700))
+    );
+    // The increment would go over the maximum, bid the maximum instead
+    assert_eq!(
+        proxy.next_bid(&auction, &[bid(&other, 1_900)]),
+        ProxyDecision::Bid(eur(2_000))
+    );
+    // Outbid at or above the maximum, stop
+    assert_eq!(
+        proxy.next_bid(&auction, &[bid(&other, 2_000)]),
+        ProxyDecision::CapReached
+    );
+
+    // A starting price above the maximum can't be met
+    let low_cap = ProxyBid::new(auction.id.clone(), me, eur(500), eur(100));
+    assert_eq!(low_cap.next_bid(&auction, &[]), ProxyDecision::CapReached);
+}
+
+// Test the proxy never bids when its maximum or increment is in another currency than the auction
+#[test]
+fn test_proxy_other_currency() {
+    let auction = Auction::new(
+        vec![1u8; 32],
+        "Laptop".to_string(),
+        eur(1_000),
+        1_000,
+        2_000,
+    );
+    let me = vec![2u8; 32];
+    let other = vec![3u8; 32];
+    let dollars = |amount: u64| Money::new(amount, Currency::Usd);
+    let others_bid = [Bid::new(auction.id.clone(), other, eur(1_200))];
+
+    // A maximum in dollars can't cap a bid in euros
+    let usd_cap = ProxyBid::new(auction.id.clone(), me.clone(), dollars(5_000), eur(250));
+    assert_eq!(usd_cap.next_bid(&auction, &[]), ProxyDecision::CapReached);
+    assert_eq!(
+        usd_cap.next_bid(&auction, &others_bid),
+        ProxyDecision::CapReached
+    );
+
+    // Nor can a dollar increment raise one
+    let usd_step = ProxyBid::new(auction.id.clone(), me, eur(5_000), dollars(250));
+    assert_eq!(
+        usd_step.next_bid(&auction, &others_bid),
+        ProxyDecision::CapReached
+    );
+}
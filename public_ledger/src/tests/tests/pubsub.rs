@@ -0,0 +1,95 @@
+use super::helpers::build_chain;
+use crate::auction::bid::Bid;
+use crate::auction::money::{Currency, Money};
+use crate::auction::record::LedgerMode;
+use crate::auction::{Auction, auction_key};
+use crate::kademlia::key::Key;
+use crate::pubsub::{AUCTIONS_TOPIC, BLOCKS_TOPIC, EventBus};
+
+// Test that a block is published once, on the topics of the transaction it carries
+#[test]
+fn test_event_bus_topics() {
+    let auction = Auction::new(
+        vec![1u8; 32],
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let bid = Bid::new(
+        auction.id.clone(),
+        vec![2u8; 32],
+        Money::new(300, Currency::Eur),
+    );
+    let chain = build_chain(vec![
+        (LedgerMode::HashOnly.auction_transaction(&auction), 1_000),
+        (LedgerMode::FullRecords.bid_transaction(&bid), 1_100),
+    ]);
+
+    let event_bus = EventBus::new();
+    let mut receiver = event_bus.subscribe();
+
+    let auction_block = chain.get_block_at_height(0).unwrap();
+    assert!(event_bus.publish_block(auction_block));
+    // Heard again from another peer, dropped
+    assert!(!event_bus.publish_block(auction_block));
+    // Stored values that aren't blocks are ignored
+    event_bus.publish_stored_value(auction.serialized().as_bytes());
+    event_bus.publish_stored_value(
+        chain
+            .get_block_at_height(1)
+            .unwrap()
+            .serialized()
+            .as_bytes(),
+    );
+
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        events.push((event.topic, event.kind));
+    }
+    let auction_topic = auction_key(&auction.id);
+    assert_eq!(
+        events,
+        vec![
+            (BLOCKS_TOPIC.to_string(), "block".to_string()),
+            (AUCTIONS_TOPIC.to_string(), "auction".to_string()),
+            (auction_topic.clone(), "auction".to_string()),
+            (BLOCKS_TOPIC.to_string(), "block".to_string()),
+            (auction_topic, "bid".to_string()),
+        ]
+    );
+}
+
+// Test that blocks without a known transaction only reach the blocks topic, that values which
+// aren't blocks publish nothing, and that a peer is only relayed once at a time
+#[test]
+fn test_event_bus_ignores_unknown_values() {
+    let chain = build_chain(vec![
+        (vec![], 1_000),
+        (b"not a transaction".to_vec(), 1_100),
+    ]);
+
+    let event_bus = EventBus::new();
+    // Nobody subscribed yet, the block is still marked as published
+    assert!(event_bus.publish_block(chain.get_block_at_height(0).unwrap()));
+    assert!(!event_bus.publish_block(chain.get_block_at_height(0).unwrap()));
+
+    let mut receiver = event_bus.subscribe();
+    event_bus.publish_stored_value(b"garbage");
+    event_bus.publish_stored_value(&[]);
+    assert!(event_bus.publish_block(chain.get_block_at_height(1).unwrap()));
+    let mut events = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        events.push((event.topic, event.kind));
+    }
+    assert_eq!(
+        events,
+        vec![(BLOCKS_TOPIC.to_string(), "block".to_string())]
+    );
+
+    let peer = Key::from([7; 20]);
+    assert!(event_bus.start_relay(peer));
+    assert!(!event_bus.start_relay(peer));
+    event_bus.end_relay(&peer);
+    assert!(event_bus.start_relay(peer));
+}
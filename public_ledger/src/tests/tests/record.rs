@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use crate::identity::Identity;
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+use crate::kademlia::record::MutableRecord;
+use crate::kademlia::routing_table::RoutingTable;
+
+fn bytes(record: &MutableRecord) -> Vec<u8> {
+    record.serialized_to_bytes().unwrap()
+}
+
+// Key of the identity's `latest_block` pointer
+fn latest_block_key(identity: &Identity) -> Key {
+    MutableRecord::key(
+        &MutableRecord::publisher_id(&identity.get_public_key()),
+        "latest_block",
+    )
+}
+
+// Test that a stored pointer only moves to its publisher's records with a higher sequence number
+#[test]
+fn test_mutable_record_store() {
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    let (now, ttl) = (Instant::now(), Duration::from_secs(60));
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let key = latest_block_key(&alice);
+    let mut store =
+        |value: Vec<u8>| routing_table.store_record(key, Namespace::Pointer, value, 100, ttl, now);
+
+    let first = MutableRecord::new(&alice, "latest_block", 1, b"block 1".to_vec());
+    let second = MutableRecord::new(&alice, "latest_block", 2, b"block 2".to_vec());
+    assert_eq!(first.get_key(), key);
+    assert!(store(bytes(&first)));
+    assert!(store(bytes(&second)));
+    // Older and equal sequence numbers, even published later
+    assert!(!routing_table.store_record(key, Namespace::Pointer, bytes(&first), 200, ttl, now));
+    let rival = MutableRecord::new(&alice, "latest_block", 2, b"other block 2".to_vec());
+    assert!(!routing_table.store_record(key, Namespace::Pointer, bytes(&rival), 200, ttl, now));
+    // The same record again is fine
+    assert!(routing_table.store_record(key, Namespace::Pointer, bytes(&second), 200, ttl, now));
+
+    // Plain values, forged records, records of another pointer and other publishers' records,
+    // however fresh, don't replace it
+    let mut store =
+        |value: Vec<u8>| routing_table.store_record(key, Namespace::Pointer, value, 300, ttl, now);
+    assert!(!store(b"block 9".to_vec()));
+    let mut forged = MutableRecord::new(&alice, "latest_block", 3, b"block 3".to_vec());
+    forged.value = b"block 9".to_vec();
+    assert!(!store(bytes(&forged)));
+    let replayed = MutableRecord::new(&alice, "other", 9, b"block 9".to_vec());
+    assert!(!store(bytes(&replayed)));
+    let frozen = MutableRecord::new(&bob, "latest_block", u64::MAX, b"block 9".to_vec());
+    assert!(!store(bytes(&frozen)));
+    let mut claimed = frozen.clone();
+    claimed.public_key = alice.get_public_key();
+    assert!(!store(bytes(&claimed)));
+    assert_eq!(routing_table.get(key), Some(&bytes(&second)));
+
+    // Nor can they be stored under a fresh key that isn't theirs
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    assert!(!routing_table.store_record(key, Namespace::Pointer, bytes(&frozen), 100, ttl, now));
+}
+
+// Test that the freshest valid record wins among the versions found, the first on ties
+#[test]
+fn test_freshest_record() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let key = latest_block_key(&alice);
+    let record = |seq: u64, value: &[u8]| {
+        bytes(&MutableRecord::new(
+            &alice,
+            "latest_block",
+            seq,
+            value.to_vec(),
+        ))
+    };
+    let mut forged = MutableRecord::new(&alice, "latest_block", 9, b"block 1".to_vec());
+    forged.seq = 10;
+    let candidates = [
+        record(2, b"block 2"),
+        bytes(&forged),
+        bytes(&MutableRecord::new(
+            &bob,
+            "latest_block",
+            u64::MAX,
+            b"block 9".to_vec(),
+        )),
+        record(3, b"block 3"),
+        record(3, b"other block 3"),
+        b"not a record".to_vec(),
+    ];
+    let freshest = MutableRecord::freshest(&key, candidates.iter().map(Vec::as_slice)).unwrap();
+    assert_eq!((freshest.seq, freshest.value), (3, b"block 3".to_vec()));
+    assert!(
+        MutableRecord::freshest(&Key::from([8; 20]), candidates.iter().map(Vec::as_slice))
+            .is_none()
+    );
+}
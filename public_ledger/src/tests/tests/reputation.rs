@@ -0,0 +1,242 @@
+use super::helpers::{build_chain, empty_blocks, eur, genesis_allocation};
+use crate::auction::Auction;
+use crate::auction::account::FaucetTransaction;
+use crate::auction::bid::Bid;
+use crate::auction::record::LedgerMode;
+use crate::auction::reputation::{FeedbackRating, FeedbackTransaction, Reputation};
+use crate::auction::seller::{SellerAction, SellerTransaction};
+use crate::auction::settlement::SettlementTransaction;
+use crate::auction::signature::BidSignature;
+use crate::identity::Identity;
+
+fn feedback(from: &Identity, auction: &Auction, to: &Identity, rating: FeedbackRating) -> Vec<u8> {
+    FeedbackTransaction::new(
+        from,
+        auction.id.clone(),
+        to.get_public_key(),
+        rating,
+        "".to_string(),
+    )
+    .serialized_to_bytes()
+    .unwrap()
+}
+
+// Test the stats of a seller, a winner and a bidder who couldn't pay, and which feedback counts
+#[test]
+fn test_reputation_from_chain() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let carol = Identity::generate();
+    let new_auction = |item: &str| {
+        Auction::new(
+            seller.get_public_key(),
+            item.to_string(),
+            eur(100),
+            1_000,
+            2_000,
+        )
+    };
+    let sold = new_auction("Laptop");
+    let withdrawn = new_auction("Phone");
+    let bid = |bidder: &Identity, amount: u64| {
+        Bid::new(sold.id.clone(), bidder.get_public_key(), eur(amount)).signed(bidder)
+    };
+    let alice_bid = bid(&alice, 500);
+    // Bob only has 600, his bid is the highest but can't be paid
+    let bob_bid = bid(&bob, 700);
+    let alice_id = alice.get_public_key();
+    let bob_id = bob.get_public_key();
+
+    let mut blocks = [
+        vec![
+            (
+                FaucetTransaction::test_network(vec![alice_id.clone()], eur(1_000))
+                    .serialized_to_bytes()
+                    .unwrap(),
+                900,
+            ),
+            (
+                FaucetTransaction::new(vec![bob_id.clone()], eur(600))
+                    .serialized_to_bytes()
+                    .unwrap(),
+                910,
+            ),
+            (full.auction_transaction(&sold), 950),
+            (full.auction_transaction(&withdrawn), 960),
+            (
+                SellerTransaction::new(&seller, withdrawn.id.clone(), SellerAction::Cancel)
+                    .serialized_to_bytes()
+                    .unwrap(),
+                970,
+            ),
+        ],
+        // The median time past reaches the starting time
+        empty_blocks(5, 1_000),
+        vec![
+            (full.bid_transaction(&alice_bid), 1_100),
+            (full.bid_transaction(&bob_bid), 1_200),
+            // Before the settlement, ignored
+            (
+                feedback(&alice, &sold, &seller, FeedbackRating::Negative),
+                1_300,
+            ),
+        ],
+    ]
+    .concat();
+    let chain = build_chain(blocks.clone());
+    blocks.extend(empty_blocks(6, 2_100));
+    blocks.push((
+        SettlementTransaction::new(&chain, &sold, vec![])
+            .serialized_to_bytes()
+            .unwrap(),
+        2_200,
+    ));
+    blocks.extend([
+        (
+            feedback(&alice, &sold, &seller, FeedbackRating::Positive),
+            2_300,
+        ),
+        (
+            feedback(&seller, &sold, &alice, FeedbackRating::Negative),
+            2_400,
+        ),
+        // Second feedback of alice, ignored
+        (
+            feedback(&alice, &sold, &seller, FeedbackRating::Negative),
+            2_500,
+        ),
+        // Carol took no part in the trade, ignored
+        (
+            feedback(&carol, &sold, &seller, FeedbackRating::Negative),
+            2_600,
+        ),
+    ]);
+    let chain = build_chain(blocks);
+
+    let reputation = Reputation::replay(&chain, &[], &[]);
+    let seller_stats = reputation.get(&seller.get_public_key());
+    assert_eq!(seller_stats.auctions_completed, 1);
+    assert_eq!(seller_stats.auctions_cancelled, 1);
+    assert_eq!(seller_stats.positive_feedback, 1);
+    assert_eq!(seller_stats.negative_feedback, 0);
+    assert_eq!(seller_stats.score(), 1);
+
+    let alice_stats = reputation.get(&alice_id);
+    assert_eq!(alice_stats.auctions_won, 1);
+    assert_eq!(alice_stats.unpaid_wins, 0);
+    assert_eq!(alice_stats.negative_feedback, 1);
+    assert_eq!(alice_stats.score(), 0);
+
+    let bob_stats = reputation.get(&bob_id);
+    assert_eq!(bob_stats.auctions_won, 0);
+    assert_eq!(bob_stats.unpaid_wins, 1);
+    assert_eq!(bob_stats.score(), -2);
+
+    assert_eq!(reputation.get(&carol.get_public_key()), Default::default());
+    let ranking: Vec<Vec<u8>> = reputation
+        .ranking()
+        .into_iter()
+        .map(|(identity, _)| identity)
+        .collect();
+    assert_eq!(ranking, vec![seller.get_public_key(), alice_id, bob_id]);
+}
+
+// Test that sealed bids count against their bidder when still unrevealed once the auction is settled
+#[test]
+fn test_unrevealed_sealed_bids() {
+    let full = LedgerMode::FullRecords;
+    let seller = Identity::generate();
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let carol = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Painting".to_string(),
+        eur(100),
+        1_000,
+        2_000,
+    );
+    let sealed = |bidder: &Identity, amount: u64| {
+        let bid = Bid::new(auction.id.clone(), bidder.get_public_key(), eur(amount)).signed(bidder);
+        let commitment = BidSignature::sealed(bidder, &bid);
+        (bid, commitment)
+    };
+    // Alice reveals her bid, bob never does
+    let (alice_bid, alice_commitment) = sealed(&alice, 500);
+    let (_, bob_commitment) = sealed(&bob, 700);
+    // A commitment claiming carol as its bidder, signed by bob
+    let (_, mut forged_commitment) = sealed(&bob, 300);
+    forged_commitment.sealed_bidder_id = carol.get_public_key();
+    assert_eq!(
+        bob_commitment.get_sealed_bidder(),
+        Some(bob.get_public_key().as_slice())
+    );
+    assert_eq!(forged_commitment.get_sealed_bidder(), None);
+
+    let mut blocks = [
+        vec![
+            (genesis_allocation(&[&alice, &bob, &carol]), 900),
+            (full.auction_transaction(&auction), 950),
+        ],
+        empty_blocks(2, 1_000),
+    ]
+    .concat();
+    for commitment in [alice_commitment, bob_commitment, forged_commitment] {
+        blocks.push((commitment.serialized_to_bytes().unwrap(), 1_100));
+    }
+    let bids = vec![alice_bid];
+    let chain = build_chain(blocks.clone());
+    // Not judged before the settlement
+    let reputation = Reputation::replay(&chain, &[], &bids);
+    assert_eq!(
+        reputation.get(&bob.get_public_key()).unrevealed_sealed_bids,
+        0
+    );
+
+    blocks.extend(empty_blocks(6, 2_100));
+    blocks.push((
+        SettlementTransaction::new(&chain, &auction, bids.clone())
+            .serialized_to_bytes()
+            .unwrap(),
+        2_200,
+    ));
+    let chain = build_chain(blocks);
+    let reputation = Reputation::replay(&chain, &[], &bids);
+    assert_eq!(
+        reputation.get(&seller.get_public_key()).auctions_completed,
+        1
+    );
+    assert_eq!(reputation.get(&alice.get_public_key()).auctions_won, 1);
+    assert_eq!(
+        reputation
+            .get(&alice.get_public_key())
+            .unrevealed_sealed_bids,
+        0
+    );
+    let bob_stats = reputation.get(&bob.get_public_key());
+    assert_eq!(bob_stats.unrevealed_sealed_bids, 1);
+    assert_eq!(bob_stats.score(), -1);
+    assert_eq!(reputation.get(&carol.get_public_key()), Default::default());
+}
+
+// Test that feedback altered after signing, or claiming another author, doesn't verify
+#[test]
+fn test_feedback_signature() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let mut transaction = FeedbackTransaction::new(
+        &alice,
+        "auction".to_string(),
+        bob.get_public_key(),
+        FeedbackRating::Positive,
+        "Fast payment".to_string(),
+    );
+    assert!(transaction.verify_signature());
+    transaction.rating = FeedbackRating::Negative;
+    assert!(!transaction.verify_signature());
+    transaction.rating = FeedbackRating::Positive;
+    transaction.feedback_from = bob.get_public_key();
+    assert!(!transaction.verify_signature());
+}
@@ -1,11 +1,12 @@
 #[test]
 fn test_routing_table_order() {
+    use crate::kademlia::key::Key;
     use crate::kademlia::routing_table::RoutingTable;
     use crate::kademlia::routing_table::node::Node;
 
     let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
-    let node1: Node = Node::new("127.0.0.1".to_string(), 2);
-    let node2: Node = Node::new("127.0.0.1".to_string(), 3);
+    let node1: Node = Node::with_id(Key::random(), "127.0.0.1".to_string(), 2);
+    let node2: Node = Node::with_id(Key::random(), "127.0.0.1".to_string(), 3);
     routing_table.add_node(node2);
     routing_table.add_node(node1);
     for (k, k_bucket) in routing_table.get_k_bucket_map() {
@@ -13,4 +14,39 @@ fn test_routing_table_order() {
             println!("k-bucket index: {}, node: {:?}", k, node.get_id());
         }
     }
-}
\ No newline at end of file
+}
+// Test that the node never adds itself and that a full bucket makes room for a new node by
+// dropping the one heard from least recently
+#[test]
+fn test_routing_table_full_bucket() {
+    use crate::kademlia::key::Key;
+    use crate::kademlia::routing_table::RoutingTable;
+    use crate::kademlia::routing_table::node::Node;
+    use crate::kademlia::routing_table::params::MAX_BUCKET_SIZE;
+
+    let mut routing_table = RoutingTable::new("127.0.0.1".to_string(), 1);
+    let own_id = *routing_table.get_curr_node().get_id();
+    routing_table.add_node(Node::with_id(own_id, "127.0.0.1".to_string(), 1));
+    assert_eq!(routing_table.get_size(), 0);
+
+    // Every ID differing from ours in the first bit lands in the same bucket
+    let mut far: [u8; 20] = own_id.as_ref().try_into().unwrap();
+    far[0] ^= 0x80;
+    let in_far_bucket = |n: u8| {
+        let mut id = far;
+        id[19] = n;
+        Key::from(id)
+    };
+    for n in 0..MAX_BUCKET_SIZE as u8 {
+        routing_table.add_node(Node::with_id(in_far_bucket(n), "127.0.0.1".to_string(), 2));
+    }
+    // Heard from again, the first node is no longer the least recent
+    routing_table.add_node(Node::with_id(in_far_bucket(0), "127.0.0.1".to_string(), 2));
+    let newcomer = in_far_bucket(MAX_BUCKET_SIZE as u8);
+    routing_table.add_node(Node::with_id(newcomer, "127.0.0.1".to_string(), 2));
+
+    assert_eq!(routing_table.get_size(), MAX_BUCKET_SIZE);
+    assert!(routing_table.get_node(&newcomer).is_some());
+    assert!(routing_table.get_node(&in_far_bucket(0)).is_some());
+    assert!(routing_table.get_node(&in_far_bucket(1)).is_none());
+}
@@ -0,0 +1,273 @@
+use super::helpers::{
+    auction_signature, bid_signature, build_chain, empty_blocks, genesis_allocation,
+};
+use crate::auction::Auction;
+use crate::auction::bid::Bid;
+use crate::auction::money::{Currency, Money};
+use crate::identity::Identity;
+
+// Test that a settlement is only valid if it records the winner picked by the rule set
+#[test]
+fn test_settlement_verification() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    use crate::auction::settlement::SettlementTransaction;
+
+    let auction = Auction::new(
+        vec![1u8; 20],
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let low = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+    let high = Bid::new(
+        auction.id.clone(),
+        bob.get_public_key(),
+        Money::new(500, Currency::Eur),
+    )
+    .signed(&bob);
+    let bids = vec![low.clone(), high.clone()];
+
+    let open_blocks = vec![
+        (genesis_allocation(&[&alice, &bob]), 900),
+        (auction_signature(&auction), 1_000),
+        (bid_signature(&low), 1_100),
+        (bid_signature(&high), 1_200),
+    ];
+    let open_chain = build_chain(open_blocks.clone());
+
+    // Computed before the closing block, recorded in it
+    let settlement = SettlementTransaction::new(&open_chain, &auction, bids.clone());
+    assert_eq!(settlement.winning_bid_id, Some(high.id.clone()));
+    assert_eq!(settlement.price_paid, Some(high.amount));
+
+    // The median time past passes the ending time at height 7, the settlement's block
+    let mut blocks = open_blocks.clone();
+    blocks.extend(empty_blocks(3, 2_100));
+    blocks.push((settlement.serialized_to_bytes().unwrap(), 2_200));
+    let chain = build_chain(blocks);
+    let (recorded, confirmation) =
+        SettlementTransaction::get_valid_settlement(&chain, &auction).unwrap();
+    assert_eq!(recorded, settlement);
+    assert_eq!(confirmation.height, 7);
+
+    // A settlement naming the wrong winner doesn't verify
+    let mut forged = settlement.clone();
+    forged.winning_bid_id = Some(low.id.clone());
+    forged.winner_id = Some(low.bidder_id.clone());
+    forged.price_paid = Some(low.amount);
+    let mut blocks = open_blocks.clone();
+    blocks.extend(empty_blocks(3, 2_100));
+    blocks.push((forged.serialized_to_bytes().unwrap(), 2_200));
+    let chain = build_chain(blocks);
+    assert!(SettlementTransaction::get_valid_settlement(&chain, &auction).is_none());
+
+    // A settlement recorded while the auction is still open doesn't verify either
+    let mut blocks = open_blocks.clone();
+    blocks.push((settlement.serialized_to_bytes().unwrap(), 1_500));
+    let chain = build_chain(blocks);
+    assert!(SettlementTransaction::get_valid_settlement(&chain, &auction).is_none());
+}
+
+// Test that the winner certificate verifies on its own and detects tampering
+#[test]
+fn test_winner_certificate() {
+    let alice = Identity::generate();
+    use crate::auction::certificate::WinnerCertificate;
+    use crate::auction::settlement::SettlementTransaction;
+
+    let auction = Auction::new(
+        vec![1u8; 20],
+        "Phone".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let bid = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(250, Currency::Eur),
+    )
+    .signed(&alice);
+    let mut blocks = vec![
+        (genesis_allocation(&[&alice]), 900),
+        (auction_signature(&auction), 1_000),
+        (bid_signature(&bid), 1_100),
+    ];
+    let settlement =
+        SettlementTransaction::new(&build_chain(blocks.clone()), &auction, vec![bid.clone()]);
+    blocks.extend(empty_blocks(2, 2_100));
+    blocks.push((settlement.serialized_to_bytes().unwrap(), 2_200));
+    let chain = build_chain(blocks);
+
+    let certificate = WinnerCertificate::new(&chain, &auction, vec![bid.clone()]).unwrap();
+    assert_eq!(certificate.chain_proof.len(), 5);
+    assert!(certificate.verify());
+    assert!(certificate.verify_against(&chain));
+
+    // Survives the export format
+    let exported: WinnerCertificate = serde_json::from_str(&certificate.serialized()).unwrap();
+    assert!(exported.verify_against(&chain));
+
+    // Changing the winning amount breaks the bid commitment
+    let mut tampered = certificate.clone();
+    tampered.winning_bid.as_mut().unwrap().amount = Money::new(1, Currency::Eur);
+    assert!(!tampered.verify());
+
+    // Dropping a block breaks the hash links
+    let mut tampered = certificate.clone();
+    tampered.chain_proof.remove(1);
+    tampered.settlement_height -= 1;
+    assert!(!tampered.verify());
+
+    // A certificate from another chain doesn't verify against ours
+    let other_chain = build_chain(vec![(vec![], 900)]);
+    assert!(!certificate.verify_against(&other_chain));
+}
+
+// Test that nodes holding different bid records agree on the settlement, it is checked against
+// the bids known from the chain, and that a bidder left out can overturn it by recording their bid
+#[test]
+fn test_settlement_same_on_every_node() {
+    use crate::auction::account::Accounts;
+    use crate::auction::record::BidRecord;
+    use crate::auction::settlement::SettlementTransaction;
+
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let seller = Identity::generate();
+    let auction = Auction::new(
+        seller.get_public_key(),
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let low = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+    let high = Bid::new(
+        auction.id.clone(),
+        bob.get_public_key(),
+        Money::new(500, Currency::Eur),
+    )
+    .signed(&bob);
+    let mut blocks = [
+        vec![
+            (genesis_allocation(&[&alice, &bob]), 900),
+            (auction_signature(&auction), 1_000),
+            (bid_signature(&low), 1_100),
+            (bid_signature(&high), 1_200),
+        ],
+        empty_blocks(3, 2_100),
+    ]
+    .concat();
+    let closed_chain = build_chain(blocks.clone());
+
+    // The settlement reveals the records committed by hash only
+    let settlement =
+        SettlementTransaction::new(&closed_chain, &auction, vec![low.clone(), high.clone()]);
+    assert_eq!(settlement.revealed_bids, vec![low.clone(), high.clone()]);
+    let mut settled_blocks = blocks.clone();
+    settled_blocks.push((settlement.serialized_to_bytes().unwrap(), 2_200));
+    let chain = build_chain(settled_blocks);
+    let (recorded, _) = SettlementTransaction::get_valid_settlement(&chain, &auction).unwrap();
+    assert_eq!(recorded.winner_id, Some(bob.get_public_key()));
+
+    // A node without any bid record and one with both pay the seller the same
+    let auctions = std::slice::from_ref(&auction);
+    for bids in [vec![], vec![low.clone(), high.clone()]] {
+        let accounts = Accounts::replay(&chain, auctions, &bids);
+        assert!(accounts.get_settled(&auction.id).is_some());
+        assert_eq!(
+            accounts.available(&seller.get_public_key(), Currency::Eur),
+            high.amount
+        );
+    }
+
+    // A settlement computed without the highest bid verifies, until that bid is recorded in full
+    let censored = SettlementTransaction::new(&closed_chain, &auction, vec![low.clone()]);
+    blocks.push((censored.serialized_to_bytes().unwrap(), 2_200));
+    let chain = build_chain(blocks.clone());
+    let (recorded, _) = SettlementTransaction::get_valid_settlement(&chain, &auction).unwrap();
+    assert_eq!(recorded.winner_id, Some(alice.get_public_key()));
+    blocks.push((
+        BidRecord::new(high.clone()).serialized_to_bytes().unwrap(),
+        2_300,
+    ));
+    let chain = build_chain(blocks);
+    assert!(SettlementTransaction::get_valid_settlement(&chain, &auction).is_none());
+}
+
+// Test that a settlement under another rule set, or naming a winner nobody bid as, doesn't verify,
+// and that no certificate is issued without a valid settlement
+#[test]
+fn test_invalid_settlements_rejected() {
+    let alice = Identity::generate();
+    use crate::auction::certificate::WinnerCertificate;
+    use crate::auction::settlement::{SettlementTransaction, UNIFORM_PRICE_RULES};
+
+    let auction = Auction::new(
+        vec![1u8; 20],
+        "Phone".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let unsold = Auction::new(
+        vec![1u8; 20],
+        "Charger".to_string(),
+        Money::new(10, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let bid = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(250, Currency::Eur),
+    )
+    .signed(&alice);
+    let open_blocks = vec![
+        (genesis_allocation(&[&alice]), 900),
+        (auction_signature(&auction), 1_000),
+        (auction_signature(&unsold), 1_050),
+        (bid_signature(&bid), 1_100),
+    ];
+    let open_chain = build_chain(open_blocks.clone());
+    let closed = |settlement: &SettlementTransaction| {
+        let mut blocks = open_blocks.clone();
+        blocks.extend(empty_blocks(3, 2_100));
+        blocks.push((settlement.serialized_to_bytes().unwrap(), 2_200));
+        build_chain(blocks)
+    };
+
+    let sold = SettlementTransaction::new(&open_chain, &auction, vec![bid.clone()]);
+    assert!(SettlementTransaction::get_valid_settlement(&closed(&sold), &auction).is_some());
+
+    // The right winner, under the rule set of lot auctions
+    let mut wrong_rules = sold.clone();
+    wrong_rules.rule_set = UNIFORM_PRICE_RULES.to_string();
+    let chain = closed(&wrong_rules);
+    assert!(SettlementTransaction::get_valid_settlement(&chain, &auction).is_none());
+    assert!(WinnerCertificate::new(&chain, &auction, vec![bid.clone()]).is_none());
+
+    // The auction nobody bid on settled with a winner taken from the other one
+    let mut invented = SettlementTransaction::new(&open_chain, &unsold, vec![]);
+    assert_eq!(invented.winning_bid_id, None);
+    invented.winning_bid_id = sold.winning_bid_id;
+    invented.winning_bid_hash = sold.winning_bid_hash;
+    invented.winner_id = sold.winner_id;
+    invented.price_paid = sold.price_paid;
+    let chain = closed(&invented);
+    assert!(SettlementTransaction::get_valid_settlement(&chain, &unsold).is_none());
+    assert!(WinnerCertificate::new(&chain, &unsold, vec![bid]).is_none());
+}
@@ -0,0 +1,151 @@
+use super::helpers::{build_chain, empty_blocks, genesis_allocation};
+use crate::auction::Auction;
+use crate::auction::bid::Bid;
+use crate::auction::lifecycle::LifecycleState;
+use crate::auction::money::{Currency, Money};
+use crate::auction::record::LedgerMode;
+use crate::auction::settlement::SettlementTransaction;
+use crate::auction::signature::AuctionSignature;
+use crate::auction::state::AuctionState;
+use crate::identity::Identity;
+
+// Test that auctions, bids and winners recorded in full are rebuilt from the chain alone
+#[test]
+fn test_state_rebuilt_from_full_records() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let full = LedgerMode::FullRecords;
+    let auction = Auction::new(
+        vec![1u8; 32],
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    let low = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+    let high = Bid::new(
+        auction.id.clone(),
+        bob.get_public_key(),
+        Money::new(500, Currency::Eur),
+    )
+    .signed(&bob);
+    // Only committed by hash, so it can't be rebuilt without the DHT
+    let hashed = Auction::new(
+        vec![4u8; 32],
+        "Phone".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+
+    let open_blocks = vec![
+        (genesis_allocation(&[&alice, &bob]), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (LedgerMode::HashOnly.auction_transaction(&hashed), 1_050),
+        (full.bid_transaction(&low), 1_100),
+        (full.bid_transaction(&high), 1_200),
+    ];
+    let chain = build_chain(open_blocks.clone());
+
+    // Full records commit to the auction like signatures do
+    assert_eq!(AuctionSignature::get_auction_ids(&chain).len(), 2);
+    assert!(AuctionSignature::get_confirmation(&chain, &auction).is_some());
+
+    let state = AuctionState::replay(&chain);
+    assert!(state.get_auction(&hashed.id).is_none());
+    let projected = state.get_auction(&auction.id).unwrap();
+    assert_eq!(projected.auction.get_hash(), auction.get_hash());
+    assert_eq!(projected.bids.len(), 2);
+    assert_eq!(projected.valid_bids.len(), 2);
+    assert_eq!(
+        projected.winning_bid.as_ref().map(|bid| &bid.id),
+        Some(&high.id)
+    );
+    assert_eq!(projected.lifecycle.state, LifecycleState::Open);
+
+    // Once settled, the projection follows
+    let settlement = SettlementTransaction::new(&chain, &auction, vec![low, high.clone()]);
+    let mut blocks = open_blocks;
+    blocks.extend(empty_blocks(4, 2_100));
+    blocks.push((settlement.serialized_to_bytes().unwrap(), 2_200));
+    let chain = build_chain(blocks);
+    let state = AuctionState::replay(&chain);
+    let projected = state.get_auction(&auction.id).unwrap();
+    assert_eq!(projected.lifecycle.state, LifecycleState::Settled);
+    assert_eq!(
+        projected.winning_bid.as_ref().map(|bid| &bid.id),
+        Some(&high.id)
+    );
+}
+
+// Test that records the rules reject are left out of the projection: auctions with a forged ID,
+// a second record under an ID already projected, and bids that are unsigned or in another currency
+#[test]
+fn test_state_skips_invalid_records() {
+    let alice = Identity::generate();
+    let bob = Identity::generate();
+    let full = LedgerMode::FullRecords;
+    let auction = Auction::new(
+        vec![1u8; 32],
+        "Laptop".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    // Another item under the ID the seller already used
+    let mut replaced = auction.clone();
+    replaced.item_name = "Brick".to_string();
+    // Claims an ID its seller and nonce don't derive
+    let mut forged = Auction::new(
+        vec![2u8; 32],
+        "Phone".to_string(),
+        Money::new(100, Currency::Eur),
+        1_000,
+        2_000,
+    );
+    forged.id = "forged".to_string();
+    let valid = Bid::new(
+        auction.id.clone(),
+        alice.get_public_key(),
+        Money::new(300, Currency::Eur),
+    )
+    .signed(&alice);
+    let unsigned = Bid::new(
+        auction.id.clone(),
+        bob.get_public_key(),
+        Money::new(900, Currency::Eur),
+    );
+    let dollars = Bid::new(
+        auction.id.clone(),
+        bob.get_public_key(),
+        Money::new(800, Currency::Usd),
+    )
+    .signed(&bob);
+
+    let chain = build_chain(vec![
+        (genesis_allocation(&[&alice, &bob]), 900),
+        (full.auction_transaction(&auction), 1_000),
+        (full.auction_transaction(&replaced), 1_020),
+        (full.auction_transaction(&forged), 1_050),
+        (full.bid_transaction(&valid), 1_100),
+        (full.bid_transaction(&unsigned), 1_200),
+        (full.bid_transaction(&dollars), 1_300),
+    ]);
+
+    let state = AuctionState::replay(&chain);
+    assert!(state.get_auction("forged").is_none());
+    let projected = state.get_auction(&auction.id).unwrap();
+    assert_eq!(projected.auction.item_name, "Laptop");
+    assert_eq!(projected.bids.len(), 3);
+    let valid_ids: Vec<&String> = projected.valid_bids.iter().map(|bid| &bid.id).collect();
+    assert_eq!(valid_ids, vec![&valid.id]);
+    assert_eq!(
+        projected.winning_bid.as_ref().map(|bid| &bid.id),
+        Some(&valid.id)
+    );
+}
@@ -0,0 +1,234 @@
+use std::time::{Duration, Instant};
+
+use crate::auction::index::{IndexEntry, PostingsList};
+use crate::kademlia::StoreResult;
+use crate::kademlia::key::Key;
+use crate::kademlia::namespace::Namespace;
+use crate::kademlia::routing_table::RoutingTable;
+use crate::kademlia::routing_table::node::Node;
+use crate::kademlia::routing_table::params::{MAX_PUBLISHED_AT_SKEW, StorageTimings};
+use crate::kademlia::routing_table::storage::{cached_ttl, received_published_at};
+
+fn seconds(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+fn key(n: u8) -> Key {
+    Key::from([n; 20])
+}
+
+fn routing_table() -> RoutingTable {
+    RoutingTable::with_storage_timings(
+        "127.0.0.1".to_string(),
+        1,
+        StorageTimings {
+            value_ttl: seconds(10),
+            republish_interval: seconds(8),
+            replicate_interval: seconds(3),
+            maintenance_interval: seconds(1),
+            refresh_interval: seconds(5),
+        },
+    )
+}
+
+// Test that values expire unless stored again, that the publisher refreshes its own values with a
+// full TTL and that replicas are pushed on with the TTL they have left
+#[test]
+fn test_storage_lifecycle() {
+    let mut routing_table = routing_table();
+    let start = Instant::now();
+    let at = |secs: u64| start + seconds(secs);
+    let published_at =
+        routing_table.publish(key(1), Namespace::Auction, b"published".to_vec(), start);
+    assert!(routing_table.store_record(
+        key(2),
+        Namespace::Auction,
+        b"replica".to_vec(),
+        100,
+        seconds(60),
+        start
+    ));
+    // Remote TTLs are capped by ours
+    assert_eq!(
+        routing_table.get_stored(key(2), start).unwrap().expires_at,
+        at(10)
+    );
+
+    // Nothing is due before the replication interval
+    assert!(routing_table.take_due_pushes(at(2)).is_empty());
+    let mut pushes = routing_table.take_due_pushes(at(3));
+    pushes.sort_by_key(|push| push.key);
+    let pushed: Vec<(u8, u64, Duration)> = pushes
+        .iter()
+        .map(|push| (push.key.as_ref()[0], push.published_at, push.ttl))
+        .collect();
+    assert_eq!(
+        pushed,
+        vec![(1, published_at, seconds(7)), (2, 100, seconds(7))]
+    );
+    assert!(routing_table.take_due_pushes(at(5)).is_empty());
+
+    // The publisher refreshes its value with a full TTL, the replica keeps running out
+    let mut pushes = routing_table.take_due_pushes(at(8));
+    pushes.sort_by_key(|push| push.key);
+    let ttls: Vec<Duration> = pushes.iter().map(|push| push.ttl).collect();
+    assert_eq!(ttls, vec![seconds(10), seconds(2)]);
+    assert_eq!(routing_table.expire(at(9)), 0);
+    assert_eq!(routing_table.expire(at(10)), 1);
+    assert!(routing_table.get_stored(key(2), at(10)).is_none());
+    assert!(routing_table.get_stored(key(1), at(17)).is_some());
+
+    // Storing a replica again keeps it alive
+    assert!(routing_table.store_record(
+        key(2),
+        Namespace::Auction,
+        b"replica".to_vec(),
+        100,
+        seconds(10),
+        at(10)
+    ));
+    assert!(routing_table.store_record(
+        key(2),
+        Namespace::Auction,
+        b"replica".to_vec(),
+        100,
+        seconds(4),
+        at(15)
+    ));
+    assert!(routing_table.get_stored(key(2), at(19)).is_some());
+    assert!(routing_table.get_stored(key(2), at(20)).is_none());
+}
+
+// Test that a value published earlier than the stored one doesn't replace it, unless it can be
+// merged like the postings lists of the auction index
+#[test]
+fn test_stale_values_refused() {
+    let mut routing_table = routing_table();
+    let now = Instant::now();
+    let ttl = seconds(10);
+    assert!(routing_table.store_record(
+        key(1),
+        Namespace::Pointer,
+        b"tip 2".to_vec(),
+        200,
+        ttl,
+        now
+    ));
+    assert!(!routing_table.store_record(
+        key(1),
+        Namespace::Pointer,
+        b"tip 1".to_vec(),
+        100,
+        ttl,
+        now
+    ));
+    assert_eq!(routing_table.get(key(1)), Some(&b"tip 2".to_vec()));
+    assert!(routing_table.store_record(
+        key(1),
+        Namespace::Pointer,
+        b"tip 3".to_vec(),
+        300,
+        ttl,
+        now
+    ));
+    assert_eq!(routing_table.get(key(1)), Some(&b"tip 3".to_vec()));
+
+    let postings = |auction_id: &str| {
+        PostingsList::new(
+            "keyword:laptop",
+            vec![IndexEntry {
+                auction_id: auction_id.to_string(),
+                ending_time: 1_000,
+            }],
+        )
+        .serialized()
+        .into_bytes()
+    };
+    assert!(routing_table.store_record(key(2), Namespace::Index, postings("newer"), 200, ttl, now));
+    assert!(routing_table.store_record(key(2), Namespace::Index, postings("older"), 100, ttl, now));
+    let stored = routing_table.get(key(2)).unwrap();
+    let merged = PostingsList::deserialized_from_bytes(stored).unwrap();
+    assert_eq!(merged.postings.len(), 2);
+}
+
+// Test that a received publication time can't be far in the future, so a value dated u64::MAX
+// doesn't shut out every later publication
+#[test]
+fn test_published_at_capped() {
+    let now = 1_000_000;
+    assert_eq!(received_published_at(0, now), now);
+    assert_eq!(received_published_at(now - 10, now), now - 10);
+    assert_eq!(
+        received_published_at(u64::MAX, now),
+        now + MAX_PUBLISHED_AT_SKEW
+    );
+
+    let mut routing_table = routing_table();
+    let (instant, ttl) = (Instant::now(), seconds(10));
+    let future = received_published_at(u64::MAX, now);
+    assert!(routing_table.store_record(
+        key(1),
+        Namespace::UserData,
+        b"squat".to_vec(),
+        future,
+        ttl,
+        instant
+    ));
+    let later = received_published_at(
+        now + MAX_PUBLISHED_AT_SKEW + 1,
+        now + MAX_PUBLISHED_AT_SKEW + 1,
+    );
+    assert!(routing_table.store_record(
+        key(1),
+        Namespace::UserData,
+        b"real".to_vec(),
+        later,
+        ttl,
+        instant
+    ));
+}
+
+// Test that a write quorum counts the closest nodes that stored the value, and only needs every
+// node of a network smaller than the quorum
+#[test]
+fn test_write_quorum() {
+    let nodes = |count: u16| -> Vec<Node> {
+        (0..count)
+            .map(|port| Node::with_id(Key::random(), "127.0.0.1".to_string(), port))
+            .collect()
+    };
+    let result = StoreResult {
+        acknowledged: nodes(2),
+        failed: nodes(2),
+    };
+    assert!(result.has_quorum(2));
+    assert!(!result.has_quorum(3));
+
+    let small_network = StoreResult {
+        acknowledged: nodes(1),
+        failed: vec![],
+    };
+    assert!(small_network.has_quorum(2));
+    let unreachable = StoreResult {
+        acknowledged: vec![],
+        failed: nodes(1),
+    };
+    assert!(!unreachable.has_quorum(2));
+}
+
+// Test that copies cached along a lookup path live shorter the more nodes are closer to the key
+#[test]
+fn test_cached_ttl() {
+    let mut routing_table = routing_table();
+    for n in [1, 2, 3, 8] {
+        routing_table.add_node(Node::with_id([n; 20], "127.0.0.1".to_string(), 2));
+    }
+    assert_eq!(routing_table.count_closer_nodes(&key(0), &key(1)), 0);
+    assert_eq!(routing_table.count_closer_nodes(&key(0), &key(4)), 3);
+
+    let value_ttl = routing_table.get_storage_timings().value_ttl;
+    assert_eq!(cached_ttl(value_ttl, 0), seconds(10));
+    assert_eq!(cached_ttl(value_ttl, 1), seconds(5));
+    assert_eq!(cached_ttl(value_ttl, 3), Duration::from_millis(1_250));
+    assert!(cached_ttl(value_ttl, usize::MAX) < seconds(1));
+}